@@ -0,0 +1,4 @@
+// 迁移文件通过 sqlx::migrate! 编译进程序，修改后需要重新编译
+fn main() {
+    println!("cargo:rerun-if-changed=migrations");
+}
@@ -0,0 +1,23 @@
+-- 认证服务基础表结构
+
+CREATE TABLE IF NOT EXISTS auth_user (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    username VARCHAR(20) NOT NULL UNIQUE,
+    password VARCHAR(100) UNIQUE,
+    email VARCHAR(255) NOT NULL UNIQUE,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS site_catalogues (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    category_name VARCHAR(20) NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS site_list (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    site_title VARCHAR(20) NOT NULL,
+    catalogue_id INT NOT NULL,
+    site_info VARCHAR(255),
+    site_url VARCHAR(255)
+);
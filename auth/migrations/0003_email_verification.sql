@@ -0,0 +1,4 @@
+-- 邮箱验证时间，NULL 表示未验证
+ALTER TABLE auth_user ADD COLUMN email_verified_at TIMESTAMP NULL;
+-- 已有账号视为已验证
+UPDATE auth_user SET email_verified_at = created_at WHERE email_verified_at IS NULL;
@@ -0,0 +1,27 @@
+-- 账号角色，admin 可以管理邀请码
+ALTER TABLE auth_user ADD COLUMN role VARCHAR(20) NOT NULL DEFAULT 'user';
+
+-- 邀请码，max_uses / expires_at 为 NULL 表示不限制
+-- role / email 不为 NULL 时，注册的账号使用预设的角色、必须使用指定的邮箱
+CREATE TABLE IF NOT EXISTS invitations (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    code VARCHAR(64) NOT NULL UNIQUE,
+    max_uses INT NULL,
+    used_count INT NOT NULL DEFAULT 0,
+    expires_at TIMESTAMP NULL,
+    role VARCHAR(20) NULL,
+    email VARCHAR(255) NULL,
+    created_by INT NULL,
+    revoked_at TIMESTAMP NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+-- 邀请码使用记录
+CREATE TABLE IF NOT EXISTS invitation_uses (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    invitation_id INT NOT NULL,
+    user_id INT NOT NULL,
+    used_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    INDEX (invitation_id),
+    INDEX (user_id)
+);
@@ -0,0 +1,22 @@
+-- 登录会话，一次登录产生一个 family，刷新令牌轮换时保持不变
+-- access token 的 sid 即 family id，family 被撤销后该次登录的所有令牌失效
+CREATE TABLE IF NOT EXISTS token_families (
+    id CHAR(36) PRIMARY KEY,
+    user_id INT NOT NULL,
+    revoked_at TIMESTAMP NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    INDEX (user_id)
+);
+
+-- 刷新令牌，只保存令牌的 sha256 摘要，used_at 不为 NULL 表示已轮换
+CREATE TABLE IF NOT EXISTS refresh_tokens (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    family_id CHAR(36) NOT NULL,
+    user_id INT NOT NULL,
+    token_hash CHAR(64) NOT NULL UNIQUE,
+    expires_at TIMESTAMP NOT NULL,
+    used_at TIMESTAMP NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    INDEX (family_id),
+    INDEX (user_id)
+);
@@ -0,0 +1,6 @@
+-- OpenID Connect：授权码记录 scope 和 nonce，用于签发 ID token
+-- 有 client_secret 的应用可以不使用 PKCE
+ALTER TABLE authorization_codes
+    ADD COLUMN scope VARCHAR(255) NULL,
+    ADD COLUMN nonce VARCHAR(255) NULL,
+    MODIFY code_challenge VARCHAR(128) NULL;
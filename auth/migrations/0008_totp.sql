@@ -0,0 +1,37 @@
+-- TOTP 两步验证，totp_enabled_at 为 NULL 表示尚未启用
+-- totp_last_step 记录最后一次使用的时间步，同一个验证码不能重复使用
+ALTER TABLE auth_user
+    ADD COLUMN totp_secret VARCHAR(64) NULL,
+    ADD COLUMN totp_enabled_at TIMESTAMP NULL,
+    ADD COLUMN totp_last_step BIGINT NULL;
+
+-- 一次性恢复码，只保存 sha256 摘要
+CREATE TABLE IF NOT EXISTS recovery_codes (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    user_id INT NOT NULL,
+    code_hash CHAR(64) NOT NULL,
+    used_at TIMESTAMP NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    INDEX (user_id)
+);
+
+-- 密码验证通过后等待第二步验证的登录，purpose 为 verify 或 enroll
+-- params 保存应用跳转过来时的授权参数
+CREATE TABLE IF NOT EXISTS login_challenges (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    token_hash CHAR(64) NOT NULL UNIQUE,
+    user_id INT NOT NULL,
+    purpose VARCHAR(10) NOT NULL,
+    params TEXT NULL,
+    attempts INT NOT NULL DEFAULT 0,
+    expires_at TIMESTAMP NOT NULL,
+    used_at TIMESTAMP NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+-- 要求启用两步验证的角色
+CREATE TABLE IF NOT EXISTS mfa_policy (
+    role VARCHAR(20) PRIMARY KEY,
+    required BOOL NOT NULL DEFAULT FALSE
+);
+INSERT IGNORE INTO mfa_policy (role, required) VALUES ('user', FALSE), ('admin', TRUE);
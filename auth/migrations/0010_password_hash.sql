@@ -0,0 +1,4 @@
+-- argon2id 的 PHC 格式哈希超过 100 位
+-- 每个哈希使用不同的盐，密码列不应该有唯一约束
+ALTER TABLE auth_user DROP INDEX password;
+ALTER TABLE auth_user MODIFY password VARCHAR(255);
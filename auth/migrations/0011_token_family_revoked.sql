@@ -0,0 +1,2 @@
+-- 其他服务按撤销时间同步退出登录
+ALTER TABLE token_families ADD INDEX idx_token_families_revoked_at (revoked_at);
@@ -0,0 +1,2 @@
+-- 通过授权码登录时记录应用，刷新令牌只能由该应用使用，NULL 为直接登录
+ALTER TABLE token_families ADD COLUMN client_id VARCHAR(64) NULL;
@@ -20,7 +20,22 @@ pub enum Err {
     DataBaseError,
     InvalidCsrfToken,
     AccessError,
-    TemplateError
+    TemplateError,
+    PasswordMismatch,
+    EmailNotVerified,
+    InvitationInvalid,
+    InvalidRole,
+    InvalidClient,
+    InvalidGrant,
+    InvalidRequest,
+    UnsupportedGrantType,
+    MfaRequired,
+    MfaAlreadyEnabled,
+    InvalidMfaCode,
+    TooManyAttempts,
+    PasswordTooShort,
+    PasswordTooLong,
+    PasswordBreached,
 }
 
 // 为每个错误提供状态码和消息
@@ -39,7 +54,22 @@ impl Err {
             Err::DataBaseError => StatusCode::INTERNAL_SERVER_ERROR,
             Err::InvalidCsrfToken => StatusCode::FORBIDDEN,
             Err::AccessError => StatusCode::FORBIDDEN,
-            Err::TemplateError => StatusCode::INTERNAL_SERVER_ERROR
+            Err::TemplateError => StatusCode::INTERNAL_SERVER_ERROR,
+            Err::PasswordMismatch => StatusCode::BAD_REQUEST,
+            Err::EmailNotVerified => StatusCode::FORBIDDEN,
+            Err::InvitationInvalid => StatusCode::FORBIDDEN,
+            Err::InvalidRole => StatusCode::BAD_REQUEST,
+            Err::InvalidClient => StatusCode::UNAUTHORIZED,
+            Err::InvalidGrant => StatusCode::BAD_REQUEST,
+            Err::InvalidRequest => StatusCode::BAD_REQUEST,
+            Err::UnsupportedGrantType => StatusCode::BAD_REQUEST,
+            Err::MfaRequired => StatusCode::FORBIDDEN,
+            Err::MfaAlreadyEnabled => StatusCode::CONFLICT,
+            Err::InvalidMfaCode => StatusCode::FORBIDDEN,
+            Err::TooManyAttempts => StatusCode::TOO_MANY_REQUESTS,
+            Err::PasswordTooShort => StatusCode::BAD_REQUEST,
+            Err::PasswordTooLong => StatusCode::BAD_REQUEST,
+            Err::PasswordBreached => StatusCode::BAD_REQUEST,
         }
     }
 
@@ -57,7 +87,23 @@ impl Err {
             Err::DataBaseError => "Database error".to_string(),
             Err::InvalidCsrfToken => "Invalid CSRF token".to_string(),
             Err::AccessError => "access error".to_string(),
-            Err::TemplateError => "Template error".to_string()
+            Err::TemplateError => "Template error".to_string(),
+            Err::PasswordMismatch => "Passwords do not match".to_string(),
+            Err::EmailNotVerified => "Email not verified".to_string(),
+            Err::InvitationInvalid => "Invitation code is invalid".to_string(),
+            Err::InvalidRole => "Invalid role".to_string(),
+            // 授权相关的错误使用 OAuth 2.0 规定的错误码
+            Err::InvalidClient => "invalid_client".to_string(),
+            Err::InvalidGrant => "invalid_grant".to_string(),
+            Err::InvalidRequest => "invalid_request".to_string(),
+            Err::UnsupportedGrantType => "unsupported_grant_type".to_string(),
+            Err::MfaRequired => "Two-factor authentication required".to_string(),
+            Err::MfaAlreadyEnabled => "Two-factor authentication already enabled".to_string(),
+            Err::InvalidMfaCode => "Invalid verification code".to_string(),
+            Err::TooManyAttempts => "Too many failed login attempts, try again later".to_string(),
+            Err::PasswordTooShort => "Password is too short".to_string(),
+            Err::PasswordTooLong => "Password is too long".to_string(),
+            Err::PasswordBreached => "Password is too common, choose another one".to_string(),
         }
     }
 }
@@ -1,58 +1,98 @@
-use crate::auth::{generate_token, verify_token};
+use crate::auth::is_token_format;
+use crate::config::Config;
 use crate::error::Err;
+use crate::invite::*;
+use crate::keys::{retire_key, rotate_key};
+use crate::lockout::client_ip;
+use crate::mailer::Mail;
+use crate::mfa::*;
 use crate::model::*;
+use crate::oauth::*;
+use crate::oidc::*;
+use crate::refresh::*;
+use crate::reset::*;
+use crate::verify::*;
 use crate::AppState;
 use axum::body::Body;
-use axum::extract::State;
-use axum::http::{Request, StatusCode};
-use axum::response::{Html, IntoResponse};
+use axum::extract::{ConnectInfo, Path, Query, RawQuery, State};
+use axum::http::{header, HeaderMap, Request, StatusCode};
+use base64::engine::general_purpose::STANDARD as BASE64_ENGINE;
+use base64::Engine;
+use axum::response::{Html, IntoResponse, Response};
 use axum::{response::Redirect, routing::post, Router};
-use axum::{Form, Json};
+use axum::{Extension, Form, Json};
 use std::fs::read_to_string;
+use std::net::SocketAddr;
 use std::sync::Arc;
+use tower_cookies::cookie::{time, SameSite};
 use tower_cookies::{Cookie, CookieManagerLayer, Cookies};
 use tracing::{debug, error, info, trace};
-use std::env;
-pub async fn login(
-    app_state: State<Arc<AppState>>,
-    login_data: Json<LoginRequest>,
-) -> Result<impl IntoResponse, Err> {
+// 校验用户名和密码，用户不存在和密码错误返回相同的错误
+// 同一账号或同一 IP 连续失败后暂时锁定，登录完成后才清除账号的失败计数
+async fn authenticate(
+    app_state: &AppState,
+    username: &str,
+    password: &str,
+    ip: &str,
+) -> Result<User, Err> {
+    app_state.login_guard.check(username, ip).await?;
     let user: Option<User> =
         sqlx::query_as::<_, User>("SELECT * FROM auth_user WHERE username = ?")
-            .bind(&login_data.username)
+            .bind(username)
             .fetch_optional(&app_state.pool)
             .await
             .map_err(|e| {
                 error!("{}", e);
                 Err::InternalError
             })?;
-    let user = user.ok_or(Err::UserNotFound)?;
-    match bcrypt::verify(&login_data.password, &user.password) {
-        Ok(valid) => {
-            if valid {
-                let key = std::env::var("KEY").unwrap_or_else(|_| "keynotset".to_string());
-
-                let token = generate_token(&user.username, &key).await?;
-
-                let res = LoginResponse { token };
-                Ok((StatusCode::OK, Json(res)))
-            } else {
-                Err(Err::UsernamePasswdError)
+    // 不存在的用户也做一次密码校验，使两种失败的响应时间一致
+    let policy = &app_state.password_policy;
+    let valid = policy
+        .verify(password, user.as_ref().map(|u| u.password.as_str()))
+        .await;
+    match user {
+        Some(user) if valid => {
+            if policy.needs_rehash(&user.password) {
+                policy.rehash(&app_state.pool, user.id, password).await;
             }
+            Ok(user)
         }
-        Err(e) => {
-            // 错误处理逻辑，例如记录错误或返回错误响应
-            error!("Error verifying password: {}", e);
+        _ => {
+            debug!("login failed for {} from {}", username, ip);
+            app_state.login_guard.record_failure(username, ip).await?;
             Err(Err::UsernamePasswdError)
         }
     }
 }
 
+pub async fn login(
+    app_state: State<Arc<AppState>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    login_data: Json<LoginRequest>,
+) -> Result<impl IntoResponse, Err> {
+    let ip = client_ip(app_state.config.server.trust_proxy, &headers, addr);
+    let user = authenticate(&app_state, &login_data.username, &login_data.password, &ip).await?;
+    if user.email_verified_at.is_none() {
+        return Err(Err::EmailNotVerified);
+    }
+    // 需要两步验证的账号只能通过登录页登录
+    if login_challenge_purpose(&app_state.pool, user.id)
+        .await?
+        .is_some()
+    {
+        return Err(Err::MfaRequired);
+    }
+    app_state.login_guard.record_success(&user.username).await?;
+    let res = issue_tokens(&app_state, &AuthedUser::from(&user), None).await?;
+    Ok((StatusCode::OK, Json(res)))
+}
+
 pub async fn register(
     app_state: State<Arc<AppState>>,
     register_data: Json<RegisterRequest>,
 ) -> Result<impl IntoResponse, Err> {
-    info!("register data: {:?}", register_data);
+    info!("register request: username = {}, email = {}", register_data.username, register_data.email);
     // 检查用户名是否已存在
     let user: Option<User> =
         sqlx::query_as::<_, User>("SELECT * FROM auth_user WHERE username = ?")
@@ -69,27 +109,33 @@ pub async fn register(
     }
 
     // 对密码进行哈希处理
-    let password_hash = bcrypt::hash(&register_data.password, 10).unwrap();
-
-    // 插入新用户数据并获取插入的 ID
-    let result = sqlx::query(
-        r#"
-        INSERT INTO auth_user (username, password, email)
-        VALUES (?, ?, ?)
-        "#
+    app_state
+        .password_policy
+        .check(&register_data.password, Some(&register_data.username))?;
+    let password_hash = app_state.password_policy.hash(&register_data.password).await?;
+
+    // 插入新用户数据并获取插入的 ID，需要时消耗邀请码
+    let user_id = register_user_db(
+        &app_state.pool,
+        &register_data.username,
+        &register_data.email,
+        &password_hash,
+        &register_data.access,
+        RegistrationMode::from_config(&app_state.config.registration),
     )
-    .bind(&register_data.username)
-    .bind(&password_hash)
-    .bind(&register_data.email)
-    .execute(&app_state.pool)
-    .await
-    .map_err(|e| {
-        error!("{}", e);
-        Err::DataBaseError
-    })?;
+    .await?;
+
+    let new_user = AuthedUser {
+        id: user_id,
+        username: register_data.username.clone(),
+        email: register_data.email.clone(),
+    };
+    if let Err(e) = send_verification_mail(&app_state, &new_user).await {
+        error!("send verification mail failed: {:?}", e);
+    }
 
     let res = MsgResponse {
-        msg: "register success".to_string(),
+        msg: "register success, please verify your email".to_string(),
     };
 
     Ok((StatusCode::OK, Json(res)))
@@ -103,19 +149,16 @@ pub async fn auth_token(
     if let Some(auth_header) = req.headers().get("Authorization") {
         if let Ok(auth_str) = auth_header.to_str() {
             if let Some(token) = auth_str.strip_prefix("Bearer ") {
-                let key = std::env::var("KEY").unwrap_or_else(|_| "keynotset".to_string());
-                let timeout = std::env::var("TOKEN_TIMEOUT")
-                    .unwrap_or_else(|_| "2".to_string())
-                    .parse::<u64>()
-                    .unwrap();
-                let username = verify_token(&token, &key, timeout).await.map_err(|e| {
-                    error!("{:?}", e);
-                    Err::TokenInvalid
-                })?;
+                let claims = verify_access_token(&app_state, token)
+                    .await
+                    .map_err(|e| {
+                        error!("{:?}", e);
+                        e
+                    })?;
                 let user: Option<AuthedUser> = sqlx::query_as::<_, AuthedUser>(
-                    "SELECT id,username,email FROM auth_user WHERE username = ?",
+                    "SELECT id,username,email FROM auth_user WHERE id = ?",
                 )
-                .bind(&username)
+                .bind(&claims.sub)
                 .fetch_optional(&app_state.pool)
                 .await
                 .map_err(|e| {
@@ -125,6 +168,10 @@ pub async fn auth_token(
                 match user {
                     Some(user) => {
                         debug!("get user info success: {:?}", user);
+                        let user = TokenUser {
+                            user,
+                            sid: claims.sid,
+                        };
                         return Ok((StatusCode::OK, Json(user)));
                     }
                     _ => {
@@ -141,8 +188,13 @@ pub async fn auth_token(
     Err(Err::TokenInvalid)
 }
 
-pub async fn register_form(cookies: Cookies) -> impl IntoResponse {
-    let template_path = env::var("TEMPLATE_PATH").unwrap_or_else(|_| "templates/**/*".to_string());
+// 发布签名公钥（JWKS），其他服务据此在本地验证 token
+pub async fn jwks(app_state: State<Arc<AppState>>) -> impl IntoResponse {
+    Json(app_state.keys.jwk_set())
+}
+
+pub async fn register_form(app_state: State<Arc<AppState>>, cookies: Cookies) -> impl IntoResponse {
+    let template_path = &app_state.config.server.template_path;
     let register_template_path = format!("{}/register.html", template_path);
     let mut html = read_to_string(register_template_path).unwrap();
     let csrf_token = uuid::Uuid::new_v4().to_string();
@@ -153,8 +205,16 @@ pub async fn register_form(cookies: Cookies) -> impl IntoResponse {
 
     Html(html)
 }
-pub async fn login_form(cookies: Cookies) -> impl IntoResponse {
-    let template_path = env::var("TEMPLATE_PATH").unwrap_or_else(|_| "templates/**/*".to_string());
+pub async fn login_form(
+    app_state: State<Arc<AppState>>,
+    cookies: Cookies,
+    Query(params): Query<AuthorizeParams>,
+) -> Result<impl IntoResponse, Err> {
+    // 从应用跳转过来时先校验授权参数，参数错误时不显示登录页
+    if params.client_id.is_some() {
+        check_authorize(&app_state.pool, &params).await?;
+    }
+    let template_path = &app_state.config.server.template_path;
     let login_template_path = format!("{}/login.html", template_path);
     let mut html = read_to_string(login_template_path).unwrap();
     let csrf_token = uuid::Uuid::new_v4().to_string();
@@ -163,72 +223,156 @@ pub async fn login_form(cookies: Cookies) -> impl IntoResponse {
     // 将 CSRF 令牌插入到 HTML 中
     html = html.replace("{{ authenticity_token }}", &csrf_token);
 
-    Html(html)
+    Ok(Html(html))
 }
 pub async fn handle_login_form(
     app_state: State<Arc<AppState>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
     cookies: Cookies,
     Form(login_data): Form<LoginKey>,
 ) -> Result<impl IntoResponse, Err> {
     // 验证 CSRF 令牌
     if let Some(csrf_token) = cookies.get("csrf_token") {
         if csrf_token.value() != login_data.authenticity_token {
-            error!(
-                "Invalid CSRF token: submitted {}, expected {}",
-                login_data.authenticity_token,
-                csrf_token.value()
-            );
+            error!("Invalid CSRF token: submitted token does not match cookie");
             return Err(Err::InvalidCsrfToken);
         }
 
-        let user: Option<User> =
-            sqlx::query_as::<_, User>("SELECT * FROM auth_user WHERE username = ?")
-                .bind(&login_data.username)
-                .fetch_optional(&app_state.pool)
-                .await
-                .map_err(|e| {
-                    error!("{}", e);
-                    Err::InternalError
-                })?;
-
-        let user = user.ok_or(Err::UserNotFound)?;
-        match bcrypt::verify(&login_data.password, &user.password) {
-            Ok(valid) => {
-                if valid {
-                    let key = std::env::var("KEY").unwrap_or_else(|_| "keynotset".to_string());
-                    let token = generate_token(&user.username, &key).await?;
-
-                    if let Some(redirect) = login_data.redirect {
-                        let redirect_url = if redirect.starts_with("http://")
-                            || redirect.starts_with("https://")
-                        {
-                            redirect.to_string()
-                        } else {
-                            format!("https://{}", redirect) // 默认使用 https://，你也可以改为 http://
-                        };
-                        // 跳转到指定页面
-                        debug!("redirect to: {}", redirect_url);
-                        Ok(Redirect::to(&format!("{}?token={}", redirect_url, token)))
-                    } else {
-                        let res = LoginResponse { token };
-                        debug!("login success: {:?}", res);
-                        return Ok(Redirect::to("/")); 
-                    }
-                } else {
-                    error!("Invalid username or password");
-                    return Err(Err::UsernamePasswdError);
-                }
-            }
-            Err(e) => {
-                error!("Error verifying password: {}", e);
-                return Err(Err::UsernamePasswdError);
-            }
+        let ip = client_ip(app_state.config.server.trust_proxy, &headers, addr);
+        let user =
+            authenticate(&app_state, &login_data.username, &login_data.password, &ip).await?;
+        // 邮箱未验证时跳转到重新发送验证邮件页面
+        if user.email_verified_at.is_none() {
+            debug!("email not verified: {}", user.username);
+            return Ok(Redirect::to("/auth/verify-email/resend").into_response());
         }
+        let params = AuthorizeParams::from(&login_data);
+        // 启用了两步验证，或者角色要求两步验证时，先完成第二步
+        if let Some(purpose) = login_challenge_purpose(&app_state.pool, user.id).await? {
+            let ticket =
+                create_login_challenge(&app_state.pool, user.id, purpose, &params).await?;
+            let html = if purpose == CHALLENGE_ENROLL {
+                render_totp_enroll(&app_state, &cookies, &user, &ticket).await?
+            } else {
+                render_csrf_template(&app_state, &cookies, "totp.html")?.replace("{{ ticket }}", &ticket)
+            };
+            return Ok(Html(html).into_response());
+        }
+        app_state.login_guard.record_success(&user.username).await?;
+        finish_login(&app_state, &cookies, &AuthedUser::from(&user), &params).await
     } else {
         error!("CSRF token not found in cookies");
         return Err(Err::InvalidCsrfToken);
     }
 }
+// 登录完成：从应用跳转过来时签发一次性授权码，由应用的服务端换取 token
+async fn finish_login(
+    app_state: &AppState,
+    cookies: &Cookies,
+    user: &AuthedUser,
+    params: &AuthorizeParams,
+) -> Result<Response, Err> {
+    if let Some(client_id) = &params.client_id {
+        let redirect_url = authorize_redirect(&app_state.pool, params, user.id).await?;
+        debug!("authorization code issued to {}", client_id);
+        return Ok(Redirect::to(&redirect_url).into_response());
+    }
+    let tokens = issue_tokens(app_state, user, None).await?;
+    if let Some(refresh_token) = tokens.refresh_token {
+        cookies.add(refresh_cookie(&app_state.config, refresh_token));
+    }
+    debug!("login success: {}", user.username);
+    Ok(Redirect::to("/").into_response())
+}
+
+// 绑定两步验证的页面，还没有确认的密钥继续使用，恢复码每次重新生成
+async fn render_totp_enroll(
+    app_state: &AppState,
+    cookies: &Cookies,
+    user: &User,
+    ticket: &str,
+) -> Result<String, Err> {
+    let setup = resume_totp_setup(
+        &app_state.pool,
+        user.id,
+        &user.username,
+        &app_state.config.mfa.totp_issuer,
+    ).await?;
+    Ok(render_csrf_template(app_state, cookies, "totp_enroll.html")?
+        .replace("{{ ticket }}", ticket)
+        .replace("{{ secret }}", &setup.secret)
+        .replace("{{ otpauth_uri }}", &setup.otpauth_uri.replace('&', "&amp;"))
+        .replace("{{ recovery_codes }}", &setup.recovery_codes.join("\n")))
+}
+
+async fn challenge_user(pool: &sqlx::MySqlPool, user_id: i64) -> Result<AuthedUser, Err> {
+    sqlx::query_as::<_, AuthedUser>("SELECT id,username,email FROM auth_user WHERE id = ?")
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or(Err::TokenInvalid)
+}
+
+// 第二步验证失败：票据和账号、IP 的登录失败都计数，重新输入密码不能绕过锁定
+async fn fail_second_factor(
+    app_state: &AppState,
+    ticket: &str,
+    username: &str,
+    ip: &str,
+) -> Result<(), Err> {
+    fail_login_challenge(&app_state.pool, ticket).await?;
+    app_state.login_guard.record_failure(username, ip).await
+}
+
+// 登录第二步：TOTP 验证码或恢复码
+pub async fn handle_totp_form(
+    app_state: State<Arc<AppState>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    cookies: Cookies,
+    Form(totp_data): Form<TotpKey>,
+) -> Result<impl IntoResponse, Err> {
+    check_csrf(&cookies, &totp_data.authenticity_token)?;
+    let user_id =
+        find_login_challenge(&app_state.pool, &totp_data.ticket, CHALLENGE_VERIFY).await?;
+    let user = challenge_user(&app_state.pool, user_id).await?;
+    let ip = client_ip(app_state.config.server.trust_proxy, &headers, addr);
+    app_state.login_guard.check(&user.username, &ip).await?;
+    if !verify_second_factor(&app_state.pool, user.id, &user.username, &totp_data.code).await? {
+        fail_second_factor(&app_state, &totp_data.ticket, &user.username, &ip).await?;
+        return Err(Err::InvalidMfaCode);
+    }
+    let params = complete_login_challenge(&app_state.pool, &totp_data.ticket).await?;
+    app_state.login_guard.record_success(&user.username).await?;
+    finish_login(&app_state, &cookies, &user, &params).await
+}
+
+// 登录时绑定两步验证，验证码正确后启用并完成登录
+pub async fn handle_totp_enroll_form(
+    app_state: State<Arc<AppState>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    cookies: Cookies,
+    Form(enroll_data): Form<TotpEnrollKey>,
+) -> Result<impl IntoResponse, Err> {
+    check_csrf(&cookies, &enroll_data.authenticity_token)?;
+    let user_id =
+        find_login_challenge(&app_state.pool, &enroll_data.ticket, CHALLENGE_ENROLL).await?;
+    let user = challenge_user(&app_state.pool, user_id).await?;
+    let ip = client_ip(app_state.config.server.trust_proxy, &headers, addr);
+    app_state.login_guard.check(&user.username, &ip).await?;
+    if let Err(e) =
+        enable_totp(&app_state.pool, user.id, &user.username, &enroll_data.code).await
+    {
+        fail_second_factor(&app_state, &enroll_data.ticket, &user.username, &ip).await?;
+        return Err(e);
+    }
+    let params = complete_login_challenge(&app_state.pool, &enroll_data.ticket).await?;
+    app_state.login_guard.record_success(&user.username).await?;
+    finish_login(&app_state, &cookies, &user, &params).await
+}
+
 pub async fn handle_register_form(
     app_state: State<Arc<AppState>>,
     cookies: Cookies,
@@ -237,18 +381,10 @@ pub async fn handle_register_form(
     // 验证 CSRF 令牌
     if let Some(csrf_token) = cookies.get("csrf_token") {
         if csrf_token.value() != register_data.authenticity_token {
-            error!(
-                "Invalid CSRF token: submitted {}, expected {}",
-                register_data.authenticity_token,
-                csrf_token.value()
-            );
+            error!("Invalid CSRF token: submitted token does not match cookie");
             return Err(Err::InvalidCsrfToken);
         } else {
-            if register_data.access != "qweasdzxc" {
-                error!("accedd invalid");
-                return Err(Err::AccessError);
-            }
-            info!("register data: {:?}", register_data);
+            info!("register request: username = {}, email = {}", register_data.username, register_data.email);
             let user: Option<User> =
                 sqlx::query_as::<_, User>("SELECT * FROM auth_user WHERE username = ?")
                     .bind(&register_data.username)
@@ -264,31 +400,32 @@ pub async fn handle_register_form(
                 return Err(Err::UserExistence);
             }
 
-            let password_hash = bcrypt::hash(&register_data.password, 10).unwrap();
+            app_state
+                .password_policy
+                .check(&register_data.password, Some(&register_data.username))?;
+            let password_hash = app_state.password_policy.hash(&register_data.password).await?;
 
-            let result = sqlx::query(
-                r#"
-        INSERT INTO auth_user (username, password, email)
-        VALUES (?, ?, ?)
-        "#,
-  
+            let user_id = register_user_db(
+                &app_state.pool,
+                &register_data.username,
+                &register_data.email,
+                &password_hash,
+                &register_data.access,
+                RegistrationMode::from_config(&app_state.config.registration),
             )
-            .bind(register_data.username)
-            .bind(password_hash)
-            .bind(register_data.email)
-            .execute(&app_state.pool)
-            .await
-            .map_err(|e| {
-                error!("{}", e);
-                Err::DataBaseError
-            })?;
+            .await?;
 
-            let res = MsgResponse {
-                msg: "register success".to_string(),
+            // 新账号需要先验证邮箱
+            let new_user = AuthedUser {
+                id: user_id,
+                username: register_data.username,
+                email: register_data.email,
             };
+            if let Err(e) = send_verification_mail(&app_state, &new_user).await {
+                error!("send verification mail failed: {:?}", e);
+            }
 
-
-            Ok(Redirect::to("/auth/login"))
+            Ok(Redirect::to("/auth/verify-email/resend"))
         }
     } else {
         error!("CSRF token not found in cookies");
@@ -296,6 +433,490 @@ pub async fn handle_register_form(
     }
 }
 
+// 应用服务端使用授权码和 PKCE code_verifier 换取 token
+// 应用身份可以放在表单中（client_secret_post），也可以使用 HTTP Basic（client_secret_basic）
+pub async fn oauth_token(
+    app_state: State<Arc<AppState>>,
+    headers: HeaderMap,
+    Form(mut req): Form<TokenRequest>,
+) -> Result<impl IntoResponse, Err> {
+    if let Some((client_id, client_secret)) = basic_client(&headers)? {
+        req.client_id = Some(client_id);
+        req.client_secret = Some(client_secret);
+    }
+    let res = exchange_token(&app_state, &req).await?;
+    // token 响应不能被缓存
+    Ok((
+        StatusCode::OK,
+        [(header::CACHE_CONTROL, "no-store")],
+        Json(res),
+    ))
+}
+
+// HTTP Basic 中的 client_id 和 client_secret
+fn basic_client(headers: &HeaderMap) -> Result<Option<(String, String)>, Err> {
+    let basic = headers
+        .get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Basic "))
+        .and_then(|b| BASE64_ENGINE.decode(b.trim()).ok())
+        .and_then(|b| String::from_utf8(b).ok());
+    match basic {
+        Some(basic) => {
+            let (client_id, client_secret) = basic.split_once(':').ok_or(Err::InvalidClient)?;
+            Ok(Some((client_id.to_string(), client_secret.to_string())))
+        }
+        None => Ok(None),
+    }
+}
+
+// 已撤销的登录会话，其他服务定期拉取后结束对应的本地登录
+// 只对有 client_secret 的应用开放
+pub async fn revocations(
+    app_state: State<Arc<AppState>>,
+    headers: HeaderMap,
+    Form(mut req): Form<RevocationRequest>,
+) -> Result<impl IntoResponse, Err> {
+    if let Some((client_id, client_secret)) = basic_client(&headers)? {
+        req.client_id = Some(client_id);
+        req.client_secret = Some(client_secret);
+    }
+    let client = check_client(
+        &app_state.pool,
+        req.client_id.as_deref(),
+        req.client_secret.as_deref(),
+    )
+    .await?;
+    if client.client_secret_hash.is_none() {
+        return Err(Err::InvalidClient);
+    }
+    let (revoked, now) = revoked_families_since(&app_state.pool, req.since).await?;
+    Ok((
+        StatusCode::OK,
+        [(header::CACHE_CONTROL, "no-store")],
+        Json(RevocationList { revoked, now }),
+    ))
+}
+
+// OpenID Connect 服务发现
+pub async fn openid_configuration(app_state: State<Arc<AppState>>) -> impl IntoResponse {
+    Json(discovery_document(&app_state.config.token))
+}
+
+// OpenID Connect 授权端点，校验参数后带上原参数进入登录页
+pub async fn authorize(
+    app_state: State<Arc<AppState>>,
+    Query(params): Query<AuthorizeParams>,
+    RawQuery(query): RawQuery,
+) -> Result<impl IntoResponse, Err> {
+    if params.response_type.as_deref() != Some("code") {
+        return Err(Err::InvalidRequest);
+    }
+    check_authorize(&app_state.pool, &params).await?;
+    Ok(Redirect::to(&format!(
+        "/auth/login?{}",
+        query.unwrap_or_default()
+    )))
+}
+
+// 使用 access token 获取当前用户信息
+pub async fn userinfo(
+    app_state: State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, Err> {
+    let token = headers
+        .get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))
+        .ok_or(Err::TokenInvalid)?;
+    let claims = verify_client_access_token(&app_state, token).await?;
+    let info = userinfo_db(&app_state.pool, &claims.sub).await?;
+    Ok((StatusCode::OK, Json(info)))
+}
+
+// 刷新令牌 cookie，设置 cookie.domain 后在所有子域名下共享
+fn refresh_cookie(config: &Config, value: String) -> Cookie<'static> {
+    let mut cookie = Cookie::build((REFRESH_COOKIE, value))
+        .path("/auth")
+        .http_only(true)
+        .secure(config.cookie.secure)
+        .same_site(SameSite::Lax)
+        .max_age(time::Duration::days(config.token.refresh_ttl_days))
+        .build();
+    if let Some(domain) = &config.cookie.domain {
+        cookie.set_domain(domain.clone());
+    }
+    cookie
+}
+
+// 刷新令牌：请求体中的 refresh_token 优先，其次是 cookie
+fn refresh_token_from(cookies: &Cookies, body: Option<Json<RefreshRequest>>) -> Option<String> {
+    body.and_then(|Json(body)| body.refresh_token)
+        .or_else(|| cookies.get(REFRESH_COOKIE).map(|c| c.value().to_string()))
+        .filter(|t| is_token_format(t))
+}
+
+// 使用刷新令牌换取新的 access token，刷新令牌同时轮换
+pub async fn refresh(
+    app_state: State<Arc<AppState>>,
+    cookies: Cookies,
+    body: Option<Json<RefreshRequest>>,
+) -> Result<impl IntoResponse, Err> {
+    let token = refresh_token_from(&cookies, body).ok_or(Err::TokenInvalid)?;
+    let res = rotate_refresh_token(&app_state, &token, None).await?;
+    if let Some(refresh_token) = &res.refresh_token {
+        cookies.add(refresh_cookie(&app_state.config, refresh_token.clone()));
+    }
+    Ok((StatusCode::OK, Json(res)))
+}
+
+// 退出登录，撤销该用户在所有子域名下的登录
+// 使用 access token（Authorization 头）或刷新令牌确认用户身份
+pub async fn logout(
+    app_state: State<Arc<AppState>>,
+    cookies: Cookies,
+    headers: HeaderMap,
+    body: Option<Json<RefreshRequest>>,
+) -> Result<impl IntoResponse, Err> {
+    let bearer = headers
+        .get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "));
+    let user_id = match bearer {
+        Some(token) => {
+            let claims = verify_access_token(&app_state, token).await?;
+            Some(claims.sub.parse::<i64>().map_err(|_| Err::TokenInvalid)?)
+        }
+        None => match refresh_token_from(&cookies, body) {
+            Some(token) => refresh_token_user(&app_state.pool, &token).await?,
+            None => None,
+        },
+    };
+    if let Some(user_id) = user_id {
+        revoke_user_tokens(&app_state.pool, user_id).await?;
+        info!("user {} logged out", user_id);
+    }
+    cookies.remove(refresh_cookie(&app_state.config, String::new()));
+    let res = MsgResponse {
+        msg: "logout success".to_string(),
+    };
+    Ok((StatusCode::OK, Json(res)))
+}
+
+// 校验表单提交的 CSRF 令牌与 cookie 中的一致
+fn check_csrf(cookies: &Cookies, authenticity_token: &str) -> Result<(), Err> {
+    match cookies.get("csrf_token") {
+        Some(csrf_token) if csrf_token.value() == authenticity_token => Ok(()),
+        // 不记录令牌的值，避免写入日志
+        Some(_) => {
+            error!("Invalid CSRF token: submitted token does not match cookie");
+            Err(Err::InvalidCsrfToken)
+        }
+        None => {
+            error!("CSRF token not found in cookies");
+            Err(Err::InvalidCsrfToken)
+        }
+    }
+}
+// 读取模板并写入新的 CSRF 令牌
+fn render_csrf_template(app_state: &AppState, cookies: &Cookies, name: &str) -> Result<String, Err> {
+    let template_path = &app_state.config.server.template_path;
+    let html = read_to_string(format!("{}/{}", template_path, name)).map_err(|e| {
+        error!("read template {} failed: {}", name, e);
+        Err::TemplateError
+    })?;
+    let csrf_token = uuid::Uuid::new_v4().to_string();
+    cookies.add(Cookie::new("csrf_token", csrf_token.clone()));
+    Ok(html.replace("{{ authenticity_token }}", &csrf_token))
+}
+
+pub async fn forgot_password_form(
+    app_state: State<Arc<AppState>>,
+    cookies: Cookies,
+) -> Result<impl IntoResponse, Err> {
+    Ok(Html(render_csrf_template(&app_state, &cookies, "forgot_password.html")?))
+}
+// 发送找回密码邮件，无论邮箱是否存在都返回相同的结果
+pub async fn handle_forgot_password_form(
+    app_state: State<Arc<AppState>>,
+    cookies: Cookies,
+    Form(forgot_data): Form<ForgotPasswordKey>,
+) -> Result<impl IntoResponse, Err> {
+    check_csrf(&cookies, &forgot_data.authenticity_token)?;
+    let user: Option<User> = sqlx::query_as::<_, User>("SELECT * FROM auth_user WHERE email = ?")
+        .bind(&forgot_data.email)
+        .fetch_optional(&app_state.pool)
+        .await
+        .map_err(|e| {
+            error!("{}", e);
+            Err::DataBaseError
+        })?;
+
+    if let Some(user) = user {
+        let ttl = app_state.config.token.reset_ttl_minutes;
+        let token = create_reset_token(&app_state.pool, user.id, ttl).await?;
+        let public_url = &app_state.config.server.public_url;
+        let mail = Mail {
+            to: user.email.clone(),
+            subject: "重置密码".to_string(),
+            body: format!(
+                "{}，你好：\n\n请在 {} 分钟内打开下面的链接重置密码：\n{}/auth/reset-password?token={}\n\n如果不是你本人操作，请忽略这封邮件。",
+                user.username,
+                ttl,
+                public_url,
+                token
+            ),
+        };
+        app_state.mailer.send(&mail).await?;
+    } else {
+        debug!("forgot password for unknown email");
+    }
+
+    let res = MsgResponse {
+        msg: "if the email is registered, a reset link has been sent".to_string(),
+    };
+    Ok((StatusCode::OK, Json(res)))
+}
+
+pub async fn reset_password_form(
+    app_state: State<Arc<AppState>>,
+    cookies: Cookies,
+    Query(query): Query<ResetPasswordQuery>,
+) -> Result<impl IntoResponse, Err> {
+    if !is_token_format(&query.token) {
+        return Err(Err::TokenInvalid);
+    }
+    // 打开页面前先检查令牌是否有效
+    find_reset_token_user(&app_state.pool, &query.token).await?;
+    let html = render_csrf_template(&app_state, &cookies, "reset_password.html")?;
+    Ok(Html(html.replace("{{ token }}", &query.token)))
+}
+
+pub async fn handle_reset_password_form(
+    app_state: State<Arc<AppState>>,
+    cookies: Cookies,
+    Form(reset_data): Form<ResetPasswordKey>,
+) -> Result<impl IntoResponse, Err> {
+    check_csrf(&cookies, &reset_data.authenticity_token)?;
+    if reset_data.password.is_empty() || reset_data.password != reset_data.password_conform {
+        return Err(Err::PasswordMismatch);
+    }
+    app_state.password_policy.check(&reset_data.password, None)?;
+    let password_hash = app_state.password_policy.hash(&reset_data.password).await?;
+    let user_id =
+        reset_password_by_token(&app_state.pool, &reset_data.token, &password_hash).await?;
+    // 修改密码后之前的登录全部失效
+    revoke_user_tokens(&app_state.pool, user_id).await?;
+    Ok(Redirect::to("/auth/login"))
+}
+
+// 打开邮件中的验证链接
+pub async fn verify_email(
+    app_state: State<Arc<AppState>>,
+    Query(query): Query<VerifyEmailQuery>,
+) -> Result<impl IntoResponse, Err> {
+    let claims = check_email_verification_token(&app_state, &query.token)?;
+    let user_id = claims.sub.parse::<i64>().map_err(|_| Err::TokenInvalid)?;
+    mark_email_verified(&app_state.pool, user_id, &claims.email).await?;
+    Ok(Redirect::to("/auth/login"))
+}
+
+pub async fn resend_verification_form(
+    app_state: State<Arc<AppState>>,
+    cookies: Cookies,
+) -> Result<impl IntoResponse, Err> {
+    Ok(Html(render_csrf_template(&app_state, &cookies, "resend_verification.html")?))
+}
+// 重新发送验证邮件，无论邮箱是否存在都返回相同的结果
+pub async fn handle_resend_verification_form(
+    app_state: State<Arc<AppState>>,
+    cookies: Cookies,
+    Form(resend_data): Form<ResendVerificationKey>,
+) -> Result<impl IntoResponse, Err> {
+    check_csrf(&cookies, &resend_data.authenticity_token)?;
+    let user: Option<AuthedUser> = sqlx::query_as::<_, AuthedUser>(
+        "SELECT id,username,email FROM auth_user WHERE email = ? AND email_verified_at IS NULL",
+    )
+    .bind(&resend_data.email)
+    .fetch_optional(&app_state.pool)
+    .await
+    .map_err(|e| {
+        error!("{}", e);
+        Err::DataBaseError
+    })?;
+    if let Some(user) = user {
+        send_verification_mail(&app_state, &user).await?;
+    }
+
+    let res = MsgResponse {
+        msg: "if the email is registered and not verified, a verification link has been sent"
+            .to_string(),
+    };
+    Ok((StatusCode::OK, Json(res)))
+}
+
+// 管理员创建邀请码
+pub async fn create_invitation(
+    app_state: State<Arc<AppState>>,
+    Extension(admin): Extension<AuthedUser>,
+    Json(invitation): Json<InvitationCreate>,
+) -> Result<impl IntoResponse, Err> {
+    let invitation = create_invitation_db(&app_state.pool, admin.id, &invitation).await?;
+    Ok((StatusCode::CREATED, Json(invitation)))
+}
+
+pub async fn list_invitations(app_state: State<Arc<AppState>>) -> Result<impl IntoResponse, Err> {
+    let invitations = list_invitations_db(&app_state.pool).await?;
+    Ok((StatusCode::OK, Json(invitations)))
+}
+
+pub async fn revoke_invitation(
+    app_state: State<Arc<AppState>>,
+    Extension(admin): Extension<AuthedUser>,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse, Err> {
+    revoke_invitation_db(&app_state.pool, id).await?;
+    info!("invitation {} revoked by {}", id, admin.username);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// 查看邀请码被哪些账号使用
+pub async fn invitation_uses(
+    app_state: State<Arc<AppState>>,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse, Err> {
+    let uses = invitation_uses_db(&app_state.pool, id).await?;
+    Ok((StatusCode::OK, Json(uses)))
+}
+
+// 解除账号的登录锁定
+pub async fn unlock_user(
+    app_state: State<Arc<AppState>>,
+    Extension(admin): Extension<AuthedUser>,
+    Path(username): Path<String>,
+) -> Result<impl IntoResponse, Err> {
+    app_state.login_guard.unlock(&username).await?;
+    info!("login lock of {} cleared by {}", username, admin.username);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn list_keys(app_state: State<Arc<AppState>>) -> Result<impl IntoResponse, Err> {
+    Ok((StatusCode::OK, Json(app_state.keys.list()?)))
+}
+
+// 生成新的签名密钥并通过 JWKS 发布，keys.activation_delay_seconds 之后才用于签名
+// 多实例部署时其他实例需要在启用之前 reload
+pub async fn rotate_keys(
+    app_state: State<Arc<AppState>>,
+    Extension(admin): Extension<AuthedUser>,
+) -> Result<impl IntoResponse, Err> {
+    let delay = app_state.config.keys.activation_delay();
+    let (kid, at) = rotate_key(&app_state.keys.dir, delay)?;
+    info!("signing key {} published by {}, active from {}", kid, admin.username, at);
+    Ok((StatusCode::OK, Json(app_state.keys.reload()?)))
+}
+
+// 重新加载密钥目录，用于命令行修改密钥之后
+pub async fn reload_keys(app_state: State<Arc<AppState>>) -> Result<impl IntoResponse, Err> {
+    Ok((StatusCode::OK, Json(app_state.keys.reload()?)))
+}
+
+pub async fn retire_signing_key(
+    app_state: State<Arc<AppState>>,
+    Extension(admin): Extension<AuthedUser>,
+    Path(kid): Path<String>,
+) -> Result<impl IntoResponse, Err> {
+    retire_key(&app_state.keys.dir, &kid)?;
+    app_state.keys.reload()?;
+    info!("signing key {} retired by {}", kid, admin.username);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn list_clients(app_state: State<Arc<AppState>>) -> Result<impl IntoResponse, Err> {
+    let clients = list_clients_db(&app_state.pool).await?;
+    Ok((StatusCode::OK, Json(clients)))
+}
+
+// 注册接入单点登录的应用
+pub async fn create_client(
+    app_state: State<Arc<AppState>>,
+    Json(client): Json<ClientCreate>,
+) -> Result<impl IntoResponse, Err> {
+    let client = create_client_db(&app_state.pool, &client).await?;
+    Ok((StatusCode::CREATED, Json(client)))
+}
+
+pub async fn delete_client(
+    app_state: State<Arc<AppState>>,
+    Path(client_id): Path<String>,
+) -> Result<impl IntoResponse, Err> {
+    delete_client_db(&app_state.pool, &client_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// 生成 TOTP 密钥和恢复码，需要调用 enable 确认后才会启用
+pub async fn totp_setup(
+    app_state: State<Arc<AppState>>,
+    Extension(user): Extension<AuthedUser>,
+) -> Result<impl IntoResponse, Err> {
+    let setup = setup_totp(
+        &app_state.pool,
+        user.id,
+        &user.username,
+        &app_state.config.mfa.totp_issuer,
+    ).await?;
+    Ok((StatusCode::OK, Json(setup)))
+}
+
+pub async fn totp_enable(
+    app_state: State<Arc<AppState>>,
+    Extension(user): Extension<AuthedUser>,
+    Json(req): Json<MfaCodeRequest>,
+) -> Result<impl IntoResponse, Err> {
+    enable_totp(&app_state.pool, user.id, &user.username, &req.code).await?;
+    info!("totp enabled for {}", user.username);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn totp_disable(
+    app_state: State<Arc<AppState>>,
+    Extension(user): Extension<AuthedUser>,
+    Json(req): Json<MfaCodeRequest>,
+) -> Result<impl IntoResponse, Err> {
+    disable_totp(&app_state.pool, user.id, &user.username, &req.code).await?;
+    info!("totp disabled for {}", user.username);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn recovery_codes(
+    app_state: State<Arc<AppState>>,
+    Extension(user): Extension<AuthedUser>,
+    Json(req): Json<MfaCodeRequest>,
+) -> Result<impl IntoResponse, Err> {
+    let recovery_codes =
+        regenerate_recovery_codes(&app_state.pool, user.id, &user.username, &req.code).await?;
+    Ok((StatusCode::OK, Json(RecoveryCodesResponse { recovery_codes })))
+}
+
+pub async fn list_mfa_policy(app_state: State<Arc<AppState>>) -> Result<impl IntoResponse, Err> {
+    let policy = list_mfa_policy_db(&app_state.pool).await?;
+    Ok((StatusCode::OK, Json(policy)))
+}
+
+// 设置角色是否要求两步验证
+pub async fn set_mfa_policy(
+    app_state: State<Arc<AppState>>,
+    Extension(admin): Extension<AuthedUser>,
+    Json(policy): Json<MfaPolicy>,
+) -> Result<impl IntoResponse, Err> {
+    set_mfa_policy_db(&app_state.pool, &policy).await?;
+    info!(
+        "mfa policy of {} set to {} by {}",
+        policy.role, policy.required, admin.username
+    );
+    Ok(StatusCode::NO_CONTENT)
+}
+
 use serde_json::Value; // 引入 serde_json 库
 
 use tera::{Tera, Context};
@@ -339,9 +960,8 @@ pub async fn index(app_state: State<Arc<AppState>>) -> Result<impl IntoResponse,
     }
 
     // 初始化 Tera
-    let template_path = env::var("TEMPLATE_PATH").unwrap_or_else(|_| "templates/**/*".to_string());
+    let template_path = &app_state.config.server.template_path;
     let tera_path = format!("{}/**/*", template_path);
-        // 使用环境变量设置模板路径
     let tera = Tera::new(&tera_path).unwrap();
     
     // 创建上下文并插入数据
@@ -0,0 +1,234 @@
+use crate::config::RegistrationConfig;
+use crate::error::Err;
+use crate::model::{Invitation, InvitationCreate, InvitationUse, ROLE_USER, USER_ROLES};
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use sqlx::MySqlPool;
+use tracing::{debug, error, info};
+
+// 注册模式：open 开放注册，invite 必须使用邀请码
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegistrationMode {
+    Open,
+    Invite,
+}
+
+impl RegistrationMode {
+    // registration.mode，默认 invite
+    pub fn from_config(config: &RegistrationConfig) -> RegistrationMode {
+        match config.mode.as_str() {
+            "open" => RegistrationMode::Open,
+            _ => RegistrationMode::Invite,
+        }
+    }
+}
+
+// 随机生成 16 位邀请码
+pub fn generate_invitation_code() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(16)
+        .map(char::from)
+        .collect()
+}
+
+fn check_role(role: &str) -> Result<(), Err> {
+    if USER_ROLES.contains(&role) {
+        Ok(())
+    } else {
+        Err(Err::InvalidRole)
+    }
+}
+
+pub async fn create_invitation_db(
+    pool: &MySqlPool,
+    created_by: i64,
+    invitation: &InvitationCreate,
+) -> Result<Invitation, Err> {
+    if let Some(role) = &invitation.role {
+        check_role(role)?;
+    }
+    if matches!(invitation.max_uses, Some(n) if n <= 0) {
+        return Err(Err::InvitationInvalid);
+    }
+    let code = match &invitation.code {
+        Some(code) if !code.trim().is_empty() => code.trim().to_string(),
+        _ => generate_invitation_code(),
+    };
+    let res = sqlx::query(
+        r#"
+        INSERT INTO invitations (code, max_uses, expires_at, role, email, created_by)
+        VALUES (?, ?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(&code)
+    .bind(invitation.max_uses)
+    .bind(invitation.expires_at)
+    .bind(&invitation.role)
+    .bind(&invitation.email)
+    .bind(created_by)
+    .execute(pool)
+    .await
+    .map_err(|e| {
+        error!("create invitation failed: {:?}", e);
+        Err::DataBaseError
+    })?;
+    info!("invitation {} created by {}", res.last_insert_id(), created_by);
+    get_invitation_db(pool, res.last_insert_id() as i64).await
+}
+
+pub async fn get_invitation_db(pool: &MySqlPool, id: i64) -> Result<Invitation, Err> {
+    let invitation = sqlx::query_as::<_, Invitation>("SELECT * FROM invitations WHERE id = ?")
+        .bind(id)
+        .fetch_optional(pool)
+        .await?;
+    invitation.ok_or(Err::RequestNotFound)
+}
+
+pub async fn list_invitations_db(pool: &MySqlPool) -> Result<Vec<Invitation>, Err> {
+    let invitations =
+        sqlx::query_as::<_, Invitation>("SELECT * FROM invitations ORDER BY created_at DESC")
+            .fetch_all(pool)
+            .await?;
+    Ok(invitations)
+}
+
+// 作废邀请码，已注册的账号不受影响
+pub async fn revoke_invitation_db(pool: &MySqlPool, id: i64) -> Result<(), Err> {
+    let res = sqlx::query(
+        r#"UPDATE invitations SET revoked_at = COALESCE(revoked_at, NOW()) WHERE id = ?"#,
+    )
+    .bind(id)
+    .execute(pool)
+    .await?;
+    match res.rows_affected() {
+        0 => Err(Err::RequestNotFound),
+        _ => Ok(()),
+    }
+}
+
+// 哪些账号使用了这个邀请码
+pub async fn invitation_uses_db(pool: &MySqlPool, id: i64) -> Result<Vec<InvitationUse>, Err> {
+    get_invitation_db(pool, id).await?;
+    let uses = sqlx::query_as::<_, InvitationUse>(
+        r#"
+        SELECT u.user_id, a.username, a.email, u.used_at
+        FROM invitation_uses u
+        JOIN auth_user a ON a.id = u.user_id
+        WHERE u.invitation_id = ?
+        ORDER BY u.used_at
+        "#,
+    )
+    .bind(id)
+    .fetch_all(pool)
+    .await?;
+    Ok(uses)
+}
+
+// 创建账号，需要邀请码时在同一个事务中锁定并消耗邀请码
+// 开放注册时邀请码可以为空，填写了则同样校验并使用其预设的角色
+pub async fn register_user_db(
+    pool: &MySqlPool,
+    username: &str,
+    email: &str,
+    password_hash: &str,
+    access: &str,
+    mode: RegistrationMode,
+) -> Result<i64, Err> {
+    let access = access.trim();
+    if access.is_empty() && mode == RegistrationMode::Invite {
+        debug!("registration requires an invitation code");
+        return Err(Err::InvitationInvalid);
+    }
+    let mut conn = pool.begin().await.map_err(|e| {
+        error!("begin transaction failed: {:?}", e);
+        Err::DataBaseError
+    })?;
+
+    let invitation = if access.is_empty() {
+        None
+    } else {
+        let invitation = sqlx::query_as::<_, Invitation>(
+            r#"SELECT * FROM invitations WHERE code = ? FOR UPDATE"#,
+        )
+        .bind(access)
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or(Err::InvitationInvalid)?;
+        let now = chrono::Utc::now();
+        let usable = invitation.revoked_at.is_none()
+            && invitation.expires_at.is_none_or(|t| t > now)
+            && invitation
+                .max_uses
+                .is_none_or(|max| invitation.used_count < max)
+            && invitation
+                .email
+                .as_ref()
+                .is_none_or(|e| e.eq_ignore_ascii_case(email));
+        if !usable {
+            debug!("invitation {} is not usable", invitation.id);
+            return Err(Err::InvitationInvalid);
+        }
+        Some(invitation)
+    };
+
+    let role = invitation
+        .as_ref()
+        .and_then(|i| i.role.as_deref())
+        .unwrap_or(ROLE_USER);
+    let res = sqlx::query(
+        r#"
+        INSERT INTO auth_user (username, password, email, role)
+        VALUES (?, ?, ?, ?)
+        "#,
+    )
+    .bind(username)
+    .bind(password_hash)
+    .bind(email)
+    .bind(role)
+    .execute(&mut *conn)
+    .await
+    .map_err(|e| {
+        error!("{}", e);
+        Err::DataBaseError
+    })?;
+    let user_id = res.last_insert_id() as i64;
+
+    if let Some(invitation) = invitation {
+        sqlx::query(r#"UPDATE invitations SET used_count = used_count + 1 WHERE id = ?"#)
+            .bind(invitation.id)
+            .execute(&mut *conn)
+            .await?;
+        sqlx::query(r#"INSERT INTO invitation_uses (invitation_id, user_id) VALUES (?, ?)"#)
+            .bind(invitation.id)
+            .bind(user_id)
+            .execute(&mut *conn)
+            .await?;
+    }
+    conn.commit().await.map_err(|e| {
+        error!("commit transaction failed: {:?}", e);
+        Err::DataBaseError
+    })?;
+    Ok(user_id)
+}
+
+// 修改账号角色：auth set-role <username> <role>
+pub async fn set_user_role_db(pool: &MySqlPool, username: &str, role: &str) -> Result<(), Err> {
+    check_role(role)?;
+    let res = sqlx::query(r#"UPDATE auth_user SET role = ? WHERE username = ?"#)
+        .bind(role)
+        .bind(username)
+        .execute(pool)
+        .await?;
+    match res.rows_affected() {
+        // 角色没有变化时 rows_affected 也为 0，再确认一次账号是否存在
+        0 => {
+            let exists = sqlx::query_scalar::<_, i64>("SELECT id FROM auth_user WHERE username = ?")
+                .bind(username)
+                .fetch_optional(pool)
+                .await?;
+            exists.map(|_| ()).ok_or(Err::UserNotFound)
+        }
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,372 @@
+use crate::auth::{is_token_format, SigningKey};
+use crate::error::Err;
+use chrono::{DateTime, Utc};
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::Validation;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::time::Duration;
+use tracing::{debug, error, info};
+
+// 密钥目录中 active 文件保存当前用于签名的 kid
+const ACTIVE_FILE: &str = "active";
+// 轮换时新密钥先发布到 JWKS，next 文件保存 "<kid> <启用时间戳>"，到时间后才用于签名
+const NEXT_FILE: &str = "next";
+// 退役的密钥移到这个子目录，不再用于验签
+const RETIRED_DIR: &str = "retired";
+
+// 密钥目录：每把密钥一个 <kid>.pem，其中一把用于签名，其余的只用于验证之前签发的 token
+pub struct KeyRing {
+    pub active: String,
+    // 等待启用的密钥和启用时间
+    pub next: Option<(String, DateTime<Utc>)>,
+    pub keys: Vec<SigningKey>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct KeyInfo {
+    pub kid: String,
+    pub active: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub activates_at: Option<DateTime<Utc>>,
+}
+
+fn io_error(action: &str, path: &Path, e: std::io::Error) -> Err {
+    error!("{} {:?} failed: {:?}", action, path, e);
+    Err::InternalError
+}
+
+impl KeyRing {
+    // 目录不存在、没有密钥或者 active 指向的密钥不存在时返回错误
+    pub fn load(dir: &Path) -> Result<KeyRing, Err> {
+        let entries = std::fs::read_dir(dir).map_err(|e| io_error("read key dir", dir, e))?;
+        let mut keys = Vec::new();
+        for entry in entries {
+            let path = entry.map_err(|e| io_error("read key dir", dir, e))?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("pem") {
+                continue;
+            }
+            let pem =
+                std::fs::read_to_string(&path).map_err(|e| io_error("read key", &path, e))?;
+            let key = SigningKey::from_pem(&pem)?;
+            // 文件名必须与密钥的 kid 一致，避免手动复制时弄错
+            if path.file_stem().and_then(|s| s.to_str()) != Some(key.kid.as_str()) {
+                error!("key file {:?} does not match kid {}", path, key.kid);
+                return Err(Err::InternalError);
+            }
+            keys.push(key);
+        }
+        keys.sort_by(|a, b| a.kid.cmp(&b.kid));
+        let active_path = dir.join(ACTIVE_FILE);
+        let active = std::fs::read_to_string(&active_path)
+            .map_err(|e| io_error("read active key", &active_path, e))?
+            .trim()
+            .to_string();
+        if !keys.iter().any(|k| k.kid == active) {
+            error!("active key {} not found in {:?}", active, dir);
+            return Err(Err::InternalError);
+        }
+        let next = read_next(dir)?;
+        if let Some((kid, _)) = &next {
+            if !keys.iter().any(|k| &k.kid == kid) {
+                error!("next key {} not found in {:?}", kid, dir);
+                return Err(Err::InternalError);
+            }
+        }
+        Ok(KeyRing { active, next, keys })
+    }
+
+    // 用于签名的 kid：等待启用的密钥到时间后替换当前密钥，各实例不需要 reload
+    pub fn signing_kid(&self, now: DateTime<Utc>) -> &str {
+        match &self.next {
+            Some((kid, at)) if *at <= now => kid,
+            _ => &self.active,
+        }
+    }
+
+    // load 已经检查过 kid，找不到说明密钥目录状态不一致，不能换用其他密钥签名
+    pub fn active_key(&self) -> Result<&SigningKey, Err> {
+        let kid = self.signing_kid(Utc::now());
+        self.key(kid).ok_or_else(|| {
+            error!("signing key {} not loaded", kid);
+            Err::InternalError
+        })
+    }
+
+    pub fn key(&self, kid: &str) -> Option<&SigningKey> {
+        self.keys.iter().find(|k| k.kid == kid)
+    }
+
+    pub fn infos(&self) -> Vec<KeyInfo> {
+        let now = Utc::now();
+        let active = self.signing_kid(now);
+        self.keys
+            .iter()
+            .map(|k| KeyInfo {
+                kid: k.kid.clone(),
+                active: k.kid == active,
+                activates_at: self
+                    .next
+                    .as_ref()
+                    .filter(|(kid, at)| *kid == k.kid && *at > now)
+                    .map(|(_, at)| *at),
+            })
+            .collect()
+    }
+}
+
+fn read_next(dir: &Path) -> Result<Option<(String, DateTime<Utc>)>, Err> {
+    let path = dir.join(NEXT_FILE);
+    let content = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(io_error("read next key", &path, e)),
+    };
+    let parsed = content.split_once(' ').and_then(|(kid, at)| {
+        let at = DateTime::from_timestamp(at.trim().parse().ok()?, 0)?;
+        Some((kid.to_string(), at))
+    });
+    match parsed {
+        Some(next) => Ok(Some(next)),
+        None => {
+            error!("invalid next key file {:?}", path);
+            Err(Err::InternalError)
+        }
+    }
+}
+
+fn clear_next(dir: &Path) -> Result<(), Err> {
+    let path = dir.join(NEXT_FILE);
+    match std::fs::remove_file(&path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            Err(io_error("remove next key", &path, e))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn write_private(path: &Path, content: &str) -> Result<(), Err> {
+    std::fs::write(path, content).map_err(|e| io_error("write", path, e))?;
+    // 私钥只允许当前用户读取
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))
+            .map_err(|e| io_error("chmod", path, e))?;
+    }
+    Ok(())
+}
+
+// 生成新的密钥但不启用，先通过 JWKS 发布公钥，其他服务缓存更新后再启用
+pub fn generate_key(dir: &Path) -> Result<String, Err> {
+    std::fs::create_dir_all(dir).map_err(|e| io_error("create key dir", dir, e))?;
+    let (key, pem) = SigningKey::generate()?;
+    write_private(&dir.join(format!("{}.pem", key.kid)), &pem)?;
+    info!("signing key {} generated in {:?}", key.kid, dir);
+    Ok(key.kid)
+}
+
+// 导入已有的 PKCS#8 PEM 私钥，例如之前 SIGNING_KEY_PATH 指向的文件
+pub fn import_key(dir: &Path, pem_path: &Path) -> Result<String, Err> {
+    let pem = std::fs::read_to_string(pem_path).map_err(|e| io_error("read", pem_path, e))?;
+    let key = SigningKey::from_pem(&pem)?;
+    std::fs::create_dir_all(dir).map_err(|e| io_error("create key dir", dir, e))?;
+    write_private(&dir.join(format!("{}.pem", key.kid)), &pem)?;
+    info!("signing key {} imported into {:?}", key.kid, dir);
+    Ok(key.kid)
+}
+
+pub fn activate_key(dir: &Path, kid: &str) -> Result<(), Err> {
+    if !is_token_format(kid) {
+        return Err(Err::InvalidRequest);
+    }
+    if !dir.join(format!("{}.pem", kid)).is_file() {
+        return Err(Err::RequestNotFound);
+    }
+    std::fs::write(dir.join(ACTIVE_FILE), format!("{}\n", kid))
+        .map_err(|e| io_error("write active key", dir, e))?;
+    // 手动启用取消等待中的轮换
+    clear_next(dir)?;
+    info!("signing key {} activated", kid);
+    Ok(())
+}
+
+// 生成新的密钥，立即通过 JWKS 发布，delay 之后才用于签名，旧的密钥继续用于验签
+// delay 不能小于其他服务缓存 JWKS 的时间；还没有可用的密钥时直接启用
+pub fn rotate_key(dir: &Path, delay: Duration) -> Result<(String, DateTime<Utc>), Err> {
+    // 上一次轮换的密钥已经到启用时间时先写入 active
+    let current = KeyRing::load(dir).ok();
+    let kid = generate_key(dir)?;
+    let now = Utc::now();
+    let Some(ring) = current else {
+        activate_key(dir, &kid)?;
+        return Ok((kid, now));
+    };
+    let signing = ring.signing_kid(now);
+    if signing != ring.active {
+        std::fs::write(dir.join(ACTIVE_FILE), format!("{}\n", signing))
+            .map_err(|e| io_error("write active key", dir, e))?;
+    }
+    // next 文件只保存到秒
+    let at = now + chrono::Duration::from_std(delay).map_err(|_| Err::InternalError)?;
+    let at = DateTime::from_timestamp(at.timestamp(), 0).ok_or(Err::InternalError)?;
+    std::fs::write(dir.join(NEXT_FILE), format!("{} {}\n", kid, at.timestamp()))
+        .map_err(|e| io_error("write next key", dir, e))?;
+    info!("signing key {} published, used for signing from {}", kid, at);
+    Ok((kid, at))
+}
+
+// 退役密钥，由它签发的 token 全部失效；正在使用的密钥不能退役
+pub fn retire_key(dir: &Path, kid: &str) -> Result<(), Err> {
+    // kid 会拼进文件路径，只允许 base64url 字符
+    if !is_token_format(kid) {
+        return Err(Err::InvalidRequest);
+    }
+    let active = std::fs::read_to_string(dir.join(ACTIVE_FILE)).unwrap_or_default();
+    if active.trim() == kid || read_next(dir)?.is_some_and(|(next, _)| next == kid) {
+        return Err(Err::InvalidRequest);
+    }
+    let from = dir.join(format!("{}.pem", kid));
+    if !from.is_file() {
+        return Err(Err::RequestNotFound);
+    }
+    let retired = dir.join(RETIRED_DIR);
+    std::fs::create_dir_all(&retired).map_err(|e| io_error("create retired dir", &retired, e))?;
+    std::fs::rename(&from, retired.join(format!("{}.pem", kid)))
+        .map_err(|e| io_error("move key", &from, e))?;
+    info!("signing key {} retired", kid);
+    Ok(())
+}
+
+// 运行中使用的密钥，修改密钥目录后重新加载
+pub struct KeyStore {
+    pub dir: PathBuf,
+    ring: RwLock<KeyRing>,
+}
+
+impl KeyStore {
+    pub fn open(dir: impl Into<PathBuf>) -> Result<KeyStore, Err> {
+        let dir = dir.into();
+        let ring = KeyRing::load(&dir)?;
+        info!(
+            "loaded {} signing keys from {:?}, active {}, next {:?}",
+            ring.keys.len(),
+            dir,
+            ring.active,
+            ring.next
+        );
+        Ok(KeyStore {
+            dir,
+            ring: RwLock::new(ring),
+        })
+    }
+
+    // 重新加载失败时保留原来的密钥
+    pub fn reload(&self) -> Result<Vec<KeyInfo>, Err> {
+        let ring = KeyRing::load(&self.dir)?;
+        let infos = ring.infos();
+        *self.ring.write().map_err(|_| Err::InternalError)? = ring;
+        info!("signing keys reloaded from {:?}", self.dir);
+        Ok(infos)
+    }
+
+    pub fn list(&self) -> Result<Vec<KeyInfo>, Err> {
+        Ok(self.ring.read().map_err(|_| Err::InternalError)?.infos())
+    }
+
+    // 使用当前启用的密钥签名，header 中带上 kid
+    pub fn sign<T: Serialize>(&self, claims: &T) -> Result<String, Err> {
+        let ring = self.ring.read().map_err(|_| Err::InternalError)?;
+        ring.active_key()?.sign(claims)
+    }
+
+    // 按 header 中的 kid 选择验签的密钥，已退役或未知的 kid 验证失败
+    pub fn decode<T: DeserializeOwned>(
+        &self,
+        token: &str,
+        validation: &Validation,
+    ) -> Result<T, Err> {
+        let header = jsonwebtoken::decode_header(token).map_err(|_| Err::TokenInvalid)?;
+        let kid = header.kid.ok_or(Err::TokenInvalid)?;
+        let ring = self.ring.read().map_err(|_| Err::InternalError)?;
+        match ring.key(&kid) {
+            Some(key) => key.decode(token, validation),
+            None => {
+                debug!("unknown signing key {}", kid);
+                Err(Err::TokenInvalid)
+            }
+        }
+    }
+
+    // 发布所有未退役密钥的公钥
+    pub fn jwk_set(&self) -> JwkSet {
+        let keys = match self.ring.read() {
+            Ok(ring) => ring.keys.iter().map(|k| k.jwk.clone()).collect(),
+            Err(_) => Vec::new(),
+        };
+        JwkSet { keys }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("auth-keys-{}-{}", name, uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn first_rotation_activates_immediately() {
+        let dir = temp_dir("first");
+        let (kid, _) = rotate_key(&dir, Duration::from_secs(600)).unwrap();
+        let ring = KeyRing::load(&dir).unwrap();
+        assert_eq!(ring.active, kid);
+        assert!(ring.next.is_none());
+        assert_eq!(ring.active_key().unwrap().kid, kid);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn rotation_publishes_before_signing() {
+        let dir = temp_dir("staged");
+        let (old, _) = rotate_key(&dir, Duration::ZERO).unwrap();
+        let (new, at) = rotate_key(&dir, Duration::from_secs(600)).unwrap();
+        let ring = KeyRing::load(&dir).unwrap();
+        // 新密钥已经可以验签，但还不用于签名
+        assert!(ring.key(&new).is_some());
+        assert_eq!(ring.active_key().unwrap().kid, old);
+        assert_eq!(ring.signing_kid(at), new);
+        assert!(ring.infos().iter().any(|k| k.kid == new && k.activates_at == Some(at)));
+        // 等待启用的密钥不能退役
+        assert!(matches!(retire_key(&dir, &new), Err(Err::InvalidRequest)));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn due_rotation_is_promoted() {
+        let dir = temp_dir("due");
+        rotate_key(&dir, Duration::ZERO).unwrap();
+        let (second, _) = rotate_key(&dir, Duration::ZERO).unwrap();
+        assert_eq!(KeyRing::load(&dir).unwrap().active_key().unwrap().kid, second);
+        let (third, _) = rotate_key(&dir, Duration::from_secs(600)).unwrap();
+        let ring = KeyRing::load(&dir).unwrap();
+        assert_eq!(ring.active, second);
+        assert_eq!(ring.next.as_ref().map(|(kid, _)| kid.as_str()), Some(third.as_str()));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn missing_active_key_is_an_error() {
+        let dir = temp_dir("missing");
+        let (kid, _) = rotate_key(&dir, Duration::ZERO).unwrap();
+        let mut ring = KeyRing::load(&dir).unwrap();
+        ring.keys.retain(|k| k.kid != kid);
+        assert!(ring.active_key().is_err());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
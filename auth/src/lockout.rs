@@ -0,0 +1,358 @@
+use crate::config::LoginConfig;
+use crate::error::Err;
+use async_trait::async_trait;
+use axum::http::HeaderMap;
+use chrono::{DateTime, Utc};
+use sqlx::MySqlPool;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+// 距离上次失败超过这个时间后重新计数，秒
+const FAILURE_WINDOW: u64 = 24 * 60 * 60;
+
+// 登录失败计数，按 key 区分账号和 IP
+#[async_trait]
+pub trait AttemptStore: Send + Sync {
+    // 记录一次失败，返回窗口内的连续失败次数
+    async fn record_failure(&self, key: &str) -> Result<u32, Err>;
+    async fn lock(&self, key: &str, duration: Duration) -> Result<(), Err>;
+    // 剩余的锁定时间，未锁定返回 None
+    async fn locked_for(&self, key: &str) -> Result<Option<Duration>, Err>;
+    async fn reset(&self, key: &str) -> Result<(), Err>;
+}
+
+#[derive(Debug, Default)]
+struct Attempts {
+    failures: u32,
+    last_failure: Option<DateTime<Utc>>,
+    locked_until: Option<DateTime<Utc>>,
+}
+
+// 保存在内存中，重启后清空，用于单实例部署和测试
+#[derive(Default)]
+pub struct MemoryAttemptStore {
+    attempts: Mutex<HashMap<String, Attempts>>,
+}
+
+#[async_trait]
+impl AttemptStore for MemoryAttemptStore {
+    async fn record_failure(&self, key: &str) -> Result<u32, Err> {
+        let now = Utc::now();
+        let window = chrono::Duration::seconds(FAILURE_WINDOW as i64);
+        let mut attempts = self.attempts.lock().map_err(|_| Err::InternalError)?;
+        // 清理过期的记录，避免按 IP 计数时无限增长
+        attempts.retain(|_, a| {
+            a.last_failure.is_some_and(|t| t > now - window)
+                || a.locked_until.is_some_and(|t| t > now)
+        });
+        let entry = attempts.entry(key.to_string()).or_default();
+        entry.failures += 1;
+        entry.last_failure = Some(now);
+        Ok(entry.failures)
+    }
+
+    async fn lock(&self, key: &str, duration: Duration) -> Result<(), Err> {
+        let until = Utc::now()
+            + chrono::Duration::from_std(duration).map_err(|_| Err::InternalError)?;
+        let mut attempts = self.attempts.lock().map_err(|_| Err::InternalError)?;
+        attempts.entry(key.to_string()).or_default().locked_until = Some(until);
+        Ok(())
+    }
+
+    async fn locked_for(&self, key: &str) -> Result<Option<Duration>, Err> {
+        let attempts = self.attempts.lock().map_err(|_| Err::InternalError)?;
+        let remaining = attempts
+            .get(key)
+            .and_then(|a| a.locked_until)
+            .and_then(|until| (until - Utc::now()).to_std().ok())
+            .filter(|d| !d.is_zero());
+        Ok(remaining)
+    }
+
+    async fn reset(&self, key: &str) -> Result<(), Err> {
+        let mut attempts = self.attempts.lock().map_err(|_| Err::InternalError)?;
+        attempts.remove(key);
+        Ok(())
+    }
+}
+
+// 保存在 login_attempts 表中，多个实例共享计数
+pub struct MySqlAttemptStore {
+    pub pool: MySqlPool,
+}
+
+#[async_trait]
+impl AttemptStore for MySqlAttemptStore {
+    async fn record_failure(&self, key: &str) -> Result<u32, Err> {
+        // failures 先于 last_failure_at 更新，比较的是上一次失败的时间
+        sqlx::query(
+            r#"
+            INSERT INTO login_attempts (attempt_key, failures, last_failure_at)
+            VALUES (?, 1, NOW())
+            ON DUPLICATE KEY UPDATE
+                failures = IF(last_failure_at < DATE_SUB(NOW(), INTERVAL ? SECOND), 1, failures + 1),
+                last_failure_at = NOW()
+            "#,
+        )
+        .bind(key)
+        .bind(FAILURE_WINDOW)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| {
+            error!("record login failure failed: {:?}", e);
+            Err::DataBaseError
+        })?;
+        let failures = sqlx::query_scalar::<_, i32>(
+            r#"SELECT failures FROM login_attempts WHERE attempt_key = ?"#,
+        )
+        .bind(key)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(failures.max(0) as u32)
+    }
+
+    async fn lock(&self, key: &str, duration: Duration) -> Result<(), Err> {
+        sqlx::query(
+            r#"UPDATE login_attempts SET locked_until = DATE_ADD(NOW(), INTERVAL ? SECOND) WHERE attempt_key = ?"#,
+        )
+        .bind(duration.as_secs())
+        .bind(key)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn locked_for(&self, key: &str) -> Result<Option<Duration>, Err> {
+        let remaining = sqlx::query_scalar::<_, i64>(
+            r#"
+            SELECT CAST(TIMESTAMPDIFF(SECOND, NOW(), locked_until) AS SIGNED)
+            FROM login_attempts
+            WHERE attempt_key = ? AND locked_until > NOW()
+            "#,
+        )
+        .bind(key)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(remaining.map(|secs| Duration::from_secs(secs.max(1) as u64)))
+    }
+
+    async fn reset(&self, key: &str) -> Result<(), Err> {
+        sqlx::query(r#"DELETE FROM login_attempts WHERE attempt_key = ?"#)
+            .bind(key)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
+
+// 连续失败达到阈值后锁定，之后每多失败一次锁定时间翻倍，不超过上限
+#[derive(Debug, Clone)]
+pub struct LockoutPolicy {
+    pub account_threshold: u32,
+    pub ip_threshold: u32,
+    pub base: Duration,
+    pub max: Duration,
+}
+
+impl LockoutPolicy {
+    pub fn from_config(config: &LoginConfig) -> LockoutPolicy {
+        LockoutPolicy {
+            account_threshold: config.max_failures,
+            ip_threshold: config.ip_max_failures,
+            base: config.lockout(),
+            max: config.lockout_max(),
+        }
+    }
+
+    pub fn lock_duration(&self, failures: u32, threshold: u32) -> Option<Duration> {
+        if threshold == 0 || failures < threshold {
+            return None;
+        }
+        let exponent = (failures - threshold).min(16);
+        Some(self.base.saturating_mul(1 << exponent).min(self.max))
+    }
+}
+
+// 登录失败限制，账号和 IP 分别计数
+pub struct LoginGuard {
+    pub store: Box<dyn AttemptStore>,
+    pub policy: LockoutPolicy,
+}
+
+fn account_key(username: &str) -> String {
+    // 用户名比较不区分大小写，与数据库的排序规则一致；用户名最长 20 位，截断过长的输入
+    let username: String = username.trim().to_lowercase().chars().take(64).collect();
+    format!("user:{}", username)
+}
+
+fn ip_key(ip: &str) -> String {
+    format!("ip:{}", ip)
+}
+
+impl LoginGuard {
+    // login.attempt_store = memory 时保存在内存中，默认保存在数据库
+    pub fn from_config(config: &LoginConfig, pool: &MySqlPool) -> LoginGuard {
+        let store: Box<dyn AttemptStore> = match config.attempt_store.as_str() {
+            "memory" => Box::new(MemoryAttemptStore::default()),
+            _ => Box::new(MySqlAttemptStore { pool: pool.clone() }),
+        };
+        LoginGuard {
+            store,
+            policy: LockoutPolicy::from_config(config),
+        }
+    }
+
+    // 账号或 IP 处于锁定状态时拒绝登录，不论账号是否存在
+    pub async fn check(&self, username: &str, ip: &str) -> Result<(), Err> {
+        for key in [account_key(username), ip_key(ip)] {
+            if let Some(remaining) = self.store.locked_for(&key).await? {
+                warn!("login rejected, {} locked for {}s", key, remaining.as_secs());
+                return Err(Err::TooManyAttempts);
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn record_failure(&self, username: &str, ip: &str) -> Result<(), Err> {
+        let keys = [
+            (account_key(username), self.policy.account_threshold),
+            (ip_key(ip), self.policy.ip_threshold),
+        ];
+        for (key, threshold) in keys {
+            let failures = self.store.record_failure(&key).await?;
+            if let Some(duration) = self.policy.lock_duration(failures, threshold) {
+                warn!(
+                    "{} locked for {}s after {} failed logins",
+                    key,
+                    duration.as_secs(),
+                    failures
+                );
+                self.store.lock(&key, duration).await?;
+            }
+        }
+        Ok(())
+    }
+
+    // 登录成功只清除账号的计数，IP 的计数继续累积
+    pub async fn record_success(&self, username: &str) -> Result<(), Err> {
+        self.store.reset(&account_key(username)).await
+    }
+
+    pub async fn unlock(&self, username: &str) -> Result<(), Err> {
+        self.store.reset(&account_key(username)).await?;
+        info!("account {} unlocked", username);
+        Ok(())
+    }
+}
+
+// 客户端 IP，server.trust_proxy 开启时使用反向代理追加的 X-Forwarded-For 最后一项
+pub fn client_ip(trust_proxy: bool, headers: &HeaderMap, addr: SocketAddr) -> String {
+    if trust_proxy {
+        let forwarded = headers
+            .get("x-forwarded-for")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.rsplit(',').next())
+            .map(|v| v.trim())
+            .filter(|v| !v.is_empty());
+        if let Some(ip) = forwarded {
+            return ip.to_string();
+        }
+    }
+    addr.ip().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn guard(account_threshold: u32, ip_threshold: u32) -> LoginGuard {
+        LoginGuard {
+            store: Box::new(MemoryAttemptStore::default()),
+            policy: LockoutPolicy {
+                account_threshold,
+                ip_threshold,
+                base: Duration::from_secs(30),
+                max: Duration::from_secs(3600),
+            },
+        }
+    }
+
+    #[test]
+    fn lock_duration_doubles_up_to_max() {
+        let policy = guard(3, 10).policy;
+        assert_eq!(policy.lock_duration(2, 3), None);
+        assert_eq!(policy.lock_duration(3, 3), Some(Duration::from_secs(30)));
+        assert_eq!(policy.lock_duration(4, 3), Some(Duration::from_secs(60)));
+        assert_eq!(policy.lock_duration(100, 3), Some(Duration::from_secs(3600)));
+        assert_eq!(policy.lock_duration(100, 0), None);
+    }
+
+    #[tokio::test]
+    async fn locks_account_at_threshold() {
+        let guard = guard(3, 100);
+        for _ in 0..2 {
+            guard.record_failure("alice", "10.0.0.1").await.unwrap();
+        }
+        assert!(guard.check("alice", "10.0.0.1").await.is_ok());
+        guard.record_failure("alice", "10.0.0.1").await.unwrap();
+        assert!(matches!(
+            guard.check("alice", "10.0.0.1").await,
+            Err(Err::TooManyAttempts)
+        ));
+        // 账号锁定与 IP 无关，用户名不区分大小写
+        assert!(guard.check("ALICE", "10.0.0.2").await.is_err());
+        assert!(guard.check("bob", "10.0.0.1").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn locks_ip_across_accounts() {
+        let guard = guard(100, 3);
+        for name in ["alice", "bob", "carol"] {
+            guard.record_failure(name, "10.0.0.1").await.unwrap();
+        }
+        assert!(guard.check("dave", "10.0.0.1").await.is_err());
+        assert!(guard.check("dave", "10.0.0.2").await.is_ok());
+        // 登录成功不清除 IP 的计数
+        guard.record_success("alice").await.unwrap();
+        assert!(guard.check("alice", "10.0.0.1").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn success_resets_account_count() {
+        let guard = guard(3, 100);
+        for _ in 0..2 {
+            guard.record_failure("alice", "10.0.0.1").await.unwrap();
+        }
+        guard.record_success("alice").await.unwrap();
+        guard.record_failure("alice", "10.0.0.1").await.unwrap();
+        assert!(guard.check("alice", "10.0.0.1").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn failures_expire_after_window() {
+        let store = MemoryAttemptStore::default();
+        assert_eq!(store.record_failure("user:alice").await.unwrap(), 1);
+        assert_eq!(store.record_failure("user:alice").await.unwrap(), 2);
+        let expired = Utc::now() - chrono::Duration::seconds(FAILURE_WINDOW as i64 + 1);
+        store
+            .attempts
+            .lock()
+            .unwrap()
+            .get_mut("user:alice")
+            .unwrap()
+            .last_failure = Some(expired);
+        assert_eq!(store.record_failure("user:alice").await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn unlock_clears_account_lock() {
+        let guard = guard(1, 100);
+        guard.record_failure("alice", "10.0.0.1").await.unwrap();
+        assert!(guard.check("alice", "10.0.0.2").await.is_err());
+        guard.unlock("alice").await.unwrap();
+        assert!(guard.check("alice", "10.0.0.2").await.is_ok());
+    }
+}
@@ -0,0 +1,73 @@
+use crate::config::MailConfig;
+use crate::error::Err;
+use async_trait::async_trait;
+use std::path::PathBuf;
+use tracing::{error, info};
+
+#[derive(Debug, Clone)]
+pub struct Mail {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+// 邮件发送接口，可以替换为 SMTP 等实现
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, mail: &Mail) -> Result<(), Err>;
+}
+
+// 输出到标准输出，用于本地开发
+pub struct StdoutMailer;
+
+#[async_trait]
+impl Mailer for StdoutMailer {
+    async fn send(&self, mail: &Mail) -> Result<(), Err> {
+        println!(
+            "To: {}\nSubject: {}\n\n{}\n",
+            mail.to, mail.subject, mail.body
+        );
+        Ok(())
+    }
+}
+
+// 每封邮件写入目录下的一个 .eml 文件，用于本地开发和测试
+pub struct FileMailer {
+    pub dir: PathBuf,
+}
+
+#[async_trait]
+impl Mailer for FileMailer {
+    async fn send(&self, mail: &Mail) -> Result<(), Err> {
+        tokio::fs::create_dir_all(&self.dir).await.map_err(|e| {
+            error!("create mail dir failed: {:?}", e);
+            Err::InternalError
+        })?;
+        let file_name = format!(
+            "{}-{}.eml",
+            chrono::Utc::now().format("%Y%m%d%H%M%S"),
+            uuid::Uuid::new_v4()
+        );
+        let path = self.dir.join(file_name);
+        let content = format!(
+            "To: {}\nSubject: {}\n\n{}\n",
+            mail.to, mail.subject, mail.body
+        );
+        tokio::fs::write(&path, content).await.map_err(|e| {
+            error!("write mail failed: {:?}", e);
+            Err::InternalError
+        })?;
+        info!("mail to {} written to {:?}", mail.to, path);
+        Ok(())
+    }
+}
+
+// mail.mailer = file 时写入 mail.dir，默认输出到标准输出
+pub fn mailer_from_config(config: &MailConfig) -> Box<dyn Mailer> {
+    match config.mailer.as_str() {
+        "file" => Box::new(FileMailer {
+            dir: PathBuf::from(&config.dir),
+        }),
+        _ => Box::new(StdoutMailer),
+    }
+}
@@ -1,23 +1,38 @@
+use crate::config::{Cli, Config};
 use crate::model::*;
 use anyhow::{Context, Ok};
 use axum::body::Body;
 use axum::extract::Request;
-use axum::routing::{get, get_service, post};
+use axum::routing::{delete, get, get_service, post};
 use axum::Router;
-use axum_csrf::{CsrfConfig, CsrfLayer, CsrfToken};
+use axum_csrf::{CsrfLayer, CsrfToken};
 use dotenv::dotenv;
 use sqlx::mysql::MySqlPoolOptions;
-use std::env;
+use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
 use tower_http::cors::{Any, CorsLayer};
 use tower_http::trace::{DefaultOnResponse, TraceLayer};
 use tracing::{debug, error, info, trace};
 use tracing_subscriber::{self, fmt::format};
 pub mod auth;
+pub mod config;
 pub mod error;
 pub mod handle;
+pub mod invite;
+pub mod keys;
+pub mod lockout;
+pub mod mailer;
+pub mod mfa;
 pub mod middleware;
+pub mod migrate;
 pub mod model;
+pub mod oauth;
+pub mod oidc;
+pub mod password;
+pub mod refresh;
+pub mod reset;
+pub mod verify;
 use handle::*;
 use reqwest::header::HeaderName;
 use reqwest::header::HeaderValue;
@@ -30,40 +45,154 @@ async fn main() {
     //     .with_max_level(tracing::Level::WARN)
     //     .init();
     dotenv().ok(); // 加载 .env 文件
-    //// 从环境变量中读取日志级别，默认级别为 INFO
-    let env_filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
-    tracing_subscriber::fmt().with_env_filter(env_filter).init();
-    run().await.unwrap();
+    // 配置错误时列出所有问题后退出
+    let (cli, config) = load_config().unwrap_or_else(|e| {
+        eprintln!("{:#}", e);
+        std::process::exit(1);
+    });
+    if cli.print_config {
+        print!("{}", config.to_toml().unwrap());
+        return;
+    }
+    // 日志级别由 log.level 或 RUST_LOG 设置，默认为 info
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::new(&config.log.level))
+        .init();
+    run(cli, config).await.unwrap();
 }
 
-async fn run() -> anyhow::Result<()> {
-    let config = CsrfConfig::default();
-    let database_url = env::var("DATABASE_URL").with_context(|| "database not set")?;
-    let addr = env::var("BIND_ADDR").unwrap_or("0.0.0.0:8001".to_string());
+// 默认值 < 配置文件 < 环境变量 < 命令行参数
+fn load_config() -> anyhow::Result<(Cli, Config)> {
+    let cli = Cli::parse()?;
+    let config = Config::load(&cli)?;
+    Ok((cli, config))
+}
+
+async fn run(cli: Cli, config: Config) -> anyhow::Result<()> {
+    let addr = config.server.bind_addr.clone();
 
     // let static_files_service = ServeDir::new("static");
+    // 签名密钥管理，不需要连接数据库：auth keys <list|generate|rotate|activate|retire|import>
+    let key_dir = config.keys.dir.clone();
+    if cli.command() == Some("keys") {
+        return key_command(&cli, &config.keys);
+    }
 
     // 创建 MySQL 连接池
     let pool = MySqlPoolOptions::new()
-        .max_connections(5)
-        .connect(&database_url)
+        .max_connections(config.database.max_connections)
+        .min_connections(config.database.min_connections)
+        .acquire_timeout(Duration::from_secs(config.database.acquire_timeout_seconds))
+        .connect(&config.database.url)
         .await?;
+    // 执行数据库迁移
+    common::migrate::run_migrations(&migrate::MIGRATOR, &pool).await?;
+    if common::migrate::is_migrate_command(cli.command()) {
+        return Ok(());
+    }
+    // 管理员手动验证邮箱：auth verify-email <username>
+    if cli.command() == Some("verify-email") {
+        let username = cli.arg(1).context("usage: auth verify-email <username>")?;
+        verify::verify_email_by_username(&pool, username)
+            .await
+            .map_err(|e| anyhow::anyhow!("verify email failed: {:?}", e))?;
+        info!("email of {} is verified", username);
+        return Ok(());
+    }
+    // 设置账号角色，用于指定第一个管理员：auth set-role <username> <role>
+    if cli.command() == Some("set-role") {
+        let usage = "usage: auth set-role <username> <user|admin>";
+        let username = cli.arg(1).context(usage)?;
+        let role = cli.arg(2).context(usage)?;
+        invite::set_user_role_db(&pool, username, role)
+            .await
+            .map_err(|e| anyhow::anyhow!("set role failed: {:?}", e))?;
+        info!("role of {} is set to {}", username, role);
+        return Ok(());
+    }
+    // 注册接入单点登录的应用：auth register-client <client_id> <redirect_uri>...
+    if cli.command() == Some("register-client") {
+        let usage = "usage: auth register-client <client_id> <redirect_uri>...";
+        let client_id = cli.arg(1).context(usage)?.to_string();
+        let redirect_uris: Vec<String> = cli.args.iter().skip(2).cloned().collect();
+        let client = oauth::create_client_db(
+            &pool,
+            &ClientCreate {
+                name: client_id.clone(),
+                client_id,
+                redirect_uris,
+                public: false,
+            },
+        )
+        .await
+        .map_err(|e| anyhow::anyhow!("register client failed: {:?}", e))?;
+        println!("client_id: {}", client.client_id);
+        println!("client_secret: {}", client.client_secret.unwrap_or_default());
+        return Ok(());
+    }
     //配置cors
+    let origins = config
+        .cors
+        .allowed_origins
+        .iter()
+        .map(|origin| HeaderValue::from_str(origin))
+        .collect::<Result<Vec<_>, _>>()?;
     let cors = CorsLayer::new()
-        .allow_origin(vec![
-            HeaderValue::from_static("http://127.0.0.1:8001"),
-            HeaderValue::from_static("http://localhost:8001"),
-            HeaderValue::from_static("http://127.0.0.1:3000"),
-            HeaderValue::from_static("http://localhost:3000"),
-        ])
+        .allow_origin(origins)
         .allow_methods([Method::GET, Method::POST, Method::DELETE])
         .allow_credentials(true)
         .allow_headers(vec![
             HeaderName::from_static("authorization"),
             HeaderName::from_static("content-type"),
         ]);
-    let app_state = Arc::new(AppState { pool });
-    info!("Server is running on: {}", addr);
+    // 没有可用的签名密钥时拒绝启动，先执行 auth keys rotate 生成
+    let key_store = keys::KeyStore::open(&key_dir).map_err(|e| {
+        anyhow::anyhow!(
+            "no signing key in {}: {:?}, run `auth keys rotate` first",
+            key_dir,
+            e
+        )
+    })?;
+    let app_state = Arc::new(AppState {
+        pool: pool.clone(),
+        keys: key_store,
+        mailer: mailer::mailer_from_config(&config.mail),
+        login_guard: lockout::LoginGuard::from_config(&config.login, &pool),
+        password_policy: password::PasswordPolicy::from_config(&config.password)?,
+        config,
+    });
+    info!(
+        "Server is running on: {}, registration mode: {:?}",
+        addr,
+        invite::RegistrationMode::from_config(&app_state.config.registration)
+    );
+    // 管理接口，需要 admin 账号的 token
+    // 需要登录的接口
+    let user_routes = Router::new()
+        .route("/auth/mfa/totp/setup", post(totp_setup))
+        .route("/auth/mfa/totp/enable", post(totp_enable))
+        .route("/auth/mfa/totp/disable", post(totp_disable))
+        .route("/auth/mfa/recovery-codes", post(recovery_codes))
+        .route_layer(axum::middleware::from_fn_with_state(
+            app_state.clone(),
+            middleware::require_login,
+        ));
+    let admin_routes = Router::new()
+        .route("/auth/admin/invitations", get(list_invitations).post(create_invitation))
+        .route("/auth/admin/invitations/:id", delete(revoke_invitation))
+        .route("/auth/admin/invitations/:id/uses", get(invitation_uses))
+        .route("/auth/admin/clients", get(list_clients).post(create_client))
+        .route("/auth/admin/clients/:client_id", delete(delete_client))
+        .route("/auth/admin/mfa-policy", get(list_mfa_policy).post(set_mfa_policy))
+        .route("/auth/admin/users/:username/unlock", post(unlock_user))
+        .route("/auth/admin/keys", get(list_keys))
+        .route("/auth/admin/keys/rotate", post(rotate_keys))
+        .route("/auth/admin/keys/reload", post(reload_keys))
+        .route("/auth/admin/keys/:kid", delete(retire_signing_key))
+        .route_layer(axum::middleware::from_fn_with_state(
+            app_state.clone(),
+            middleware::require_admin,
+        ));
 
     let listener = tokio::net::TcpListener::bind(addr).await?;
     let app = Router::new()
@@ -72,9 +201,27 @@ async fn run() -> anyhow::Result<()> {
         // .route("/login",post(login))
         // .route("/register",post(register))
         .route("/auth/login", get(login_form).post(handle_login_form))
+        .route("/auth/login/totp", post(handle_totp_form))
+        .route("/auth/login/totp/enroll", post(handle_totp_enroll_form))
         .route("/auth/register", get(register_form).post(handle_register_form))
+        .route("/auth/forgot-password", get(forgot_password_form).post(handle_forgot_password_form))
+        .route("/auth/reset-password", get(reset_password_form).post(handle_reset_password_form))
+        .route("/auth/verify-email", get(verify_email))
+        .route("/auth/verify-email/resend", get(resend_verification_form).post(handle_resend_verification_form))
         .route("/auth/token",get(auth_token))
+        .route("/auth/oauth/token",post(oauth_token))
+        // OpenID Connect
+        .route("/.well-known/openid-configuration",get(openid_configuration))
+        .route("/authorize",get(authorize))
+        .route("/token",post(oauth_token))
+        .route("/userinfo",get(userinfo).post(userinfo))
+        .route("/auth/refresh",post(refresh))
+        .route("/auth/logout",post(logout))
+        .route("/auth/revocations",post(revocations))
+        .route("/auth/jwks",get(jwks))
         .route("/",get(index))
+        .merge(user_routes)
+        .merge(admin_routes)
         .layer(cors)
         .layer(CookieManagerLayer::new())
         .layer(TraceLayer::new_for_http()
@@ -84,6 +231,48 @@ async fn run() -> anyhow::Result<()> {
             .on_response(DefaultOnResponse::new()))
         .with_state(app_state.clone());
 
-    axum::serve(listener, app).await?;
+    // 登录失败按 IP 计数需要客户端地址
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await?;
+    Ok(())
+}
+
+fn key_command(cli: &Cli, keys_config: &config::KeysConfig) -> anyhow::Result<()> {
+    let usage = "usage: auth keys <list | generate | rotate | activate <kid> | retire <kid> | import <pem>>";
+    let dir = std::path::Path::new(&keys_config.dir);
+    let arg = |n: usize| cli.arg(n - 1).context(usage);
+    let failed = |e: error::Err| anyhow::anyhow!("key command failed: {:?}", e);
+    match arg(2)? {
+        "list" => {
+            let ring = keys::KeyRing::load(dir).map_err(failed)?;
+            for key in ring.infos() {
+                match key.activates_at {
+                    Some(at) => println!("{} (active from {})", key.kid, at),
+                    None if key.active => println!("{} (active)", key.kid),
+                    None => println!("{}", key.kid),
+                }
+            }
+        }
+        "generate" => println!("{}", keys::generate_key(dir).map_err(failed)?),
+        "rotate" => {
+            let delay = keys_config.activation_delay();
+            let (kid, at) = keys::rotate_key(dir, delay).map_err(failed)?;
+            println!("{} (active from {})", kid, at);
+        }
+        "activate" => keys::activate_key(dir, arg(3)?).map_err(failed)?,
+        "retire" => keys::retire_key(dir, arg(3)?).map_err(failed)?,
+        "import" => {
+            let kid = keys::import_key(dir, std::path::Path::new(arg(3)?)).map_err(failed)?;
+            // 第一把密钥直接启用
+            if keys::KeyRing::load(dir).is_err() {
+                keys::activate_key(dir, &kid).map_err(failed)?;
+            }
+            println!("{}", kid);
+        }
+        _ => anyhow::bail!(usage),
+    }
     Ok(())
 }
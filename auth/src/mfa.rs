@@ -0,0 +1,426 @@
+use crate::auth::{hash_token, random_token};
+use crate::error::Err;
+use crate::model::{AuthorizeParams, MfaPolicy, TotpSetup};
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use sqlx::{MySql, MySqlPool, Transaction};
+use totp_rs::{Algorithm, Secret, TOTP};
+use tracing::{debug, error};
+
+// 第二步验证的有效期，分钟
+const CHALLENGE_TTL: i64 = 5;
+// 第二步验证最多尝试次数
+const CHALLENGE_MAX_ATTEMPTS: i32 = 5;
+const RECOVERY_CODE_COUNT: usize = 10;
+const TOTP_STEP: u64 = 30;
+
+pub const CHALLENGE_VERIFY: &str = "verify";
+pub const CHALLENGE_ENROLL: &str = "enroll";
+
+// 只有生成 otpauth 链接时需要发行方
+fn totp(secret: &str, issuer: Option<&str>, username: &str) -> Result<TOTP, Err> {
+    let secret = Secret::Encoded(secret.to_string()).to_bytes().map_err(|e| {
+        error!("invalid totp secret: {:?}", e);
+        Err::InternalError
+    })?;
+    TOTP::new(
+        Algorithm::SHA1,
+        6,
+        1,
+        TOTP_STEP,
+        secret,
+        issuer.map(str::to_string),
+        username.to_string(),
+    )
+    .map_err(|e| {
+        error!("build totp failed: {:?}", e);
+        Err::InternalError
+    })
+}
+
+// 允许前后各一个时间步的误差，返回匹配的时间步
+fn match_step(totp: &TOTP, code: &str) -> Option<i64> {
+    let now = chrono::Utc::now().timestamp() as u64;
+    let code = code.trim();
+    [now - TOTP_STEP, now, now + TOTP_STEP]
+        .into_iter()
+        .find(|t| totp.generate(*t) == code)
+        .map(|t| (t / TOTP_STEP) as i64)
+}
+
+// 校验 TOTP 验证码，同一个时间步的验证码只能使用一次
+async fn check_totp_code(
+    pool: &MySqlPool,
+    user_id: i64,
+    secret: &str,
+    username: &str,
+    code: &str,
+) -> Result<bool, Err> {
+    let step = match match_step(&totp(secret, None, username)?, code) {
+        Some(step) => step,
+        None => return Ok(false),
+    };
+    let res = sqlx::query(
+        r#"
+        UPDATE auth_user SET totp_last_step = ?
+        WHERE id = ? AND (totp_last_step IS NULL OR totp_last_step < ?)
+        "#,
+    )
+    .bind(step)
+    .bind(user_id)
+    .bind(step)
+    .execute(pool)
+    .await?;
+    if res.rows_affected() == 0 {
+        debug!("totp code replayed for user {}", user_id);
+    }
+    Ok(res.rows_affected() == 1)
+}
+
+// 使用一次性恢复码
+async fn use_recovery_code(pool: &MySqlPool, user_id: i64, code: &str) -> Result<bool, Err> {
+    let res = sqlx::query(
+        r#"UPDATE recovery_codes SET used_at = NOW() WHERE user_id = ? AND code_hash = ? AND used_at IS NULL LIMIT 1"#,
+    )
+    .bind(user_id)
+    .bind(hash_token(&normalize_recovery_code(code)))
+    .execute(pool)
+    .await?;
+    Ok(res.rows_affected() == 1)
+}
+
+// 恢复码不区分大小写，可以省略中间的连字符
+fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+// 生成新的恢复码，之前的恢复码全部作废
+async fn replace_recovery_codes(
+    conn: &mut Transaction<'_, MySql>,
+    user_id: i64,
+) -> Result<Vec<String>, Err> {
+    sqlx::query(r#"DELETE FROM recovery_codes WHERE user_id = ?"#)
+        .bind(user_id)
+        .execute(&mut **conn)
+        .await?;
+    let mut codes = Vec::with_capacity(RECOVERY_CODE_COUNT);
+    for _ in 0..RECOVERY_CODE_COUNT {
+        let raw: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(10)
+            .map(|c| char::from(c).to_ascii_lowercase())
+            .collect();
+        sqlx::query(r#"INSERT INTO recovery_codes (user_id, code_hash) VALUES (?, ?)"#)
+            .bind(user_id)
+            .bind(hash_token(&raw))
+            .execute(&mut **conn)
+            .await?;
+        codes.push(format!("{}-{}", &raw[..5], &raw[5..]));
+    }
+    Ok(codes)
+}
+
+// (totp_secret, 是否已启用, 角色)
+async fn totp_state(pool: &MySqlPool, user_id: i64) -> Result<(Option<String>, bool, String), Err> {
+    let row = sqlx::query_as::<_, (Option<String>, bool, String)>(
+        r#"SELECT totp_secret, totp_enabled_at IS NOT NULL, role FROM auth_user WHERE id = ?"#,
+    )
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+    row.ok_or(Err::UserNotFound)
+}
+
+// 密码验证通过后需要进行的第二步：None 表示不需要
+pub async fn login_challenge_purpose(
+    pool: &MySqlPool,
+    user_id: i64,
+) -> Result<Option<&'static str>, Err> {
+    let (_, enabled, role) = totp_state(pool, user_id).await?;
+    if enabled {
+        return Ok(Some(CHALLENGE_VERIFY));
+    }
+    if mfa_required_for_role(pool, &role).await? {
+        return Ok(Some(CHALLENGE_ENROLL));
+    }
+    Ok(None)
+}
+
+pub async fn mfa_required_for_role(pool: &MySqlPool, role: &str) -> Result<bool, Err> {
+    let required = sqlx::query_scalar::<_, bool>(r#"SELECT required FROM mfa_policy WHERE role = ?"#)
+        .bind(role)
+        .fetch_optional(pool)
+        .await?;
+    Ok(required.unwrap_or(false))
+}
+
+pub async fn list_mfa_policy_db(pool: &MySqlPool) -> Result<Vec<MfaPolicy>, Err> {
+    let policy = sqlx::query_as::<_, MfaPolicy>("SELECT role, required FROM mfa_policy ORDER BY role")
+        .fetch_all(pool)
+        .await?;
+    Ok(policy)
+}
+
+pub async fn set_mfa_policy_db(pool: &MySqlPool, policy: &MfaPolicy) -> Result<(), Err> {
+    if !crate::model::USER_ROLES.contains(&policy.role.as_str()) {
+        return Err(Err::InvalidRole);
+    }
+    sqlx::query(
+        r#"INSERT INTO mfa_policy (role, required) VALUES (?, ?) ON DUPLICATE KEY UPDATE required = VALUES(required)"#,
+    )
+    .bind(&policy.role)
+    .bind(policy.required)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+// 生成新的密钥和恢复码，验证一次验证码后才会启用
+// 已经启用的账号需要先停用
+pub async fn setup_totp(
+    pool: &MySqlPool,
+    user_id: i64,
+    username: &str,
+    issuer: &str,
+) -> Result<TotpSetup, Err> {
+    start_totp_setup(pool, user_id, username, issuer, false).await
+}
+
+// 登录时绑定：继续使用还没有确认的密钥，重复打开页面时已经扫描的密钥仍然有效
+pub async fn resume_totp_setup(
+    pool: &MySqlPool,
+    user_id: i64,
+    username: &str,
+    issuer: &str,
+) -> Result<TotpSetup, Err> {
+    start_totp_setup(pool, user_id, username, issuer, true).await
+}
+
+async fn start_totp_setup(
+    pool: &MySqlPool,
+    user_id: i64,
+    username: &str,
+    issuer: &str,
+    reuse_pending: bool,
+) -> Result<TotpSetup, Err> {
+    let (pending, enabled, _) = totp_state(pool, user_id).await?;
+    if enabled {
+        return Err(Err::MfaAlreadyEnabled);
+    }
+    let pending = pending.filter(|_| reuse_pending);
+    let secret = match &pending {
+        Some(secret) => secret.clone(),
+        None => match Secret::generate_secret().to_encoded() {
+            Secret::Encoded(secret) => secret,
+            Secret::Raw(_) => return Err(Err::InternalError),
+        },
+    };
+    let otpauth_uri = totp(&secret, Some(issuer), username)?.get_url();
+    let mut conn = pool.begin().await.map_err(|e| {
+        error!("begin transaction failed: {:?}", e);
+        Err::DataBaseError
+    })?;
+    if pending.is_none() {
+        sqlx::query(r#"UPDATE auth_user SET totp_secret = ?, totp_last_step = NULL WHERE id = ?"#)
+            .bind(&secret)
+            .bind(user_id)
+            .execute(&mut *conn)
+            .await?;
+    }
+    let recovery_codes = replace_recovery_codes(&mut conn, user_id).await?;
+    conn.commit().await.map_err(|e| {
+        error!("commit transaction failed: {:?}", e);
+        Err::DataBaseError
+    })?;
+    Ok(TotpSetup {
+        secret,
+        otpauth_uri,
+        recovery_codes,
+    })
+}
+
+// 使用验证码确认绑定并启用
+pub async fn enable_totp(
+    pool: &MySqlPool,
+    user_id: i64,
+    username: &str,
+    code: &str,
+) -> Result<(), Err> {
+    let (secret, enabled, _) = totp_state(pool, user_id).await?;
+    if enabled {
+        return Err(Err::MfaAlreadyEnabled);
+    }
+    let secret = secret.ok_or(Err::InvalidMfaCode)?;
+    if !check_totp_code(pool, user_id, &secret, username, code).await? {
+        return Err(Err::InvalidMfaCode);
+    }
+    sqlx::query(r#"UPDATE auth_user SET totp_enabled_at = NOW() WHERE id = ?"#)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+// 校验第二步验证：TOTP 验证码或恢复码
+pub async fn verify_second_factor(
+    pool: &MySqlPool,
+    user_id: i64,
+    username: &str,
+    code: &str,
+) -> Result<bool, Err> {
+    let (secret, enabled, _) = totp_state(pool, user_id).await?;
+    let secret = match secret {
+        Some(secret) if enabled => secret,
+        _ => return Ok(false),
+    };
+    if check_totp_code(pool, user_id, &secret, username, code).await? {
+        return Ok(true);
+    }
+    use_recovery_code(pool, user_id, code).await
+}
+
+// 停用两步验证，角色要求两步验证时不能停用
+pub async fn disable_totp(
+    pool: &MySqlPool,
+    user_id: i64,
+    username: &str,
+    code: &str,
+) -> Result<(), Err> {
+    let (_, _, role) = totp_state(pool, user_id).await?;
+    if mfa_required_for_role(pool, &role).await? {
+        return Err(Err::AccessError);
+    }
+    if !verify_second_factor(pool, user_id, username, code).await? {
+        return Err(Err::InvalidMfaCode);
+    }
+    let mut conn = pool.begin().await.map_err(|e| {
+        error!("begin transaction failed: {:?}", e);
+        Err::DataBaseError
+    })?;
+    sqlx::query(
+        r#"UPDATE auth_user SET totp_secret = NULL, totp_enabled_at = NULL, totp_last_step = NULL WHERE id = ?"#,
+    )
+    .bind(user_id)
+    .execute(&mut *conn)
+    .await?;
+    sqlx::query(r#"DELETE FROM recovery_codes WHERE user_id = ?"#)
+        .bind(user_id)
+        .execute(&mut *conn)
+        .await?;
+    conn.commit().await.map_err(|e| {
+        error!("commit transaction failed: {:?}", e);
+        Err::DataBaseError
+    })?;
+    Ok(())
+}
+
+// 重新生成恢复码
+pub async fn regenerate_recovery_codes(
+    pool: &MySqlPool,
+    user_id: i64,
+    username: &str,
+    code: &str,
+) -> Result<Vec<String>, Err> {
+    if !verify_second_factor(pool, user_id, username, code).await? {
+        return Err(Err::InvalidMfaCode);
+    }
+    let mut conn = pool.begin().await.map_err(|e| {
+        error!("begin transaction failed: {:?}", e);
+        Err::DataBaseError
+    })?;
+    let codes = replace_recovery_codes(&mut conn, user_id).await?;
+    conn.commit().await.map_err(|e| {
+        error!("commit transaction failed: {:?}", e);
+        Err::DataBaseError
+    })?;
+    Ok(codes)
+}
+
+// 密码验证通过后创建第二步验证，返回页面中使用的 ticket
+pub async fn create_login_challenge(
+    pool: &MySqlPool,
+    user_id: i64,
+    purpose: &str,
+    params: &AuthorizeParams,
+) -> Result<String, Err> {
+    let ticket = random_token();
+    let params = if params.client_id.is_some() {
+        Some(serde_json::to_string(params).map_err(|_| Err::InternalError)?)
+    } else {
+        None
+    };
+    sqlx::query(
+        r#"
+        INSERT INTO login_challenges (token_hash, user_id, purpose, params, expires_at)
+        VALUES (?, ?, ?, ?, DATE_ADD(NOW(), INTERVAL ? MINUTE))
+        "#,
+    )
+    .bind(hash_token(&ticket))
+    .bind(user_id)
+    .bind(purpose)
+    .bind(params)
+    .bind(CHALLENGE_TTL)
+    .execute(pool)
+    .await?;
+    Ok(ticket)
+}
+
+// 查找未过期、未使用的第二步验证，返回用户 id
+pub async fn find_login_challenge(
+    pool: &MySqlPool,
+    ticket: &str,
+    purpose: &str,
+) -> Result<i64, Err> {
+    let user_id = sqlx::query_scalar::<_, i64>(
+        r#"
+        SELECT user_id FROM login_challenges
+        WHERE token_hash = ? AND purpose = ? AND used_at IS NULL
+            AND expires_at > NOW() AND attempts < ?
+        "#,
+    )
+    .bind(hash_token(ticket))
+    .bind(purpose)
+    .bind(CHALLENGE_MAX_ATTEMPTS)
+    .fetch_optional(pool)
+    .await?;
+    user_id.ok_or(Err::TokenInvalid)
+}
+
+// 验证失败时记录尝试次数，超过次数后需要重新登录
+pub async fn fail_login_challenge(pool: &MySqlPool, ticket: &str) -> Result<(), Err> {
+    sqlx::query(r#"UPDATE login_challenges SET attempts = attempts + 1 WHERE token_hash = ?"#)
+        .bind(hash_token(ticket))
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+// 第二步验证通过，标记为已使用并返回保存的授权参数
+pub async fn complete_login_challenge(
+    pool: &MySqlPool,
+    ticket: &str,
+) -> Result<AuthorizeParams, Err> {
+    let params = sqlx::query_scalar::<_, Option<String>>(
+        r#"SELECT params FROM login_challenges WHERE token_hash = ? AND used_at IS NULL"#,
+    )
+    .bind(hash_token(ticket))
+    .fetch_optional(pool)
+    .await?
+    .ok_or(Err::TokenInvalid)?;
+    let res = sqlx::query(
+        r#"UPDATE login_challenges SET used_at = NOW() WHERE token_hash = ? AND used_at IS NULL"#,
+    )
+    .bind(hash_token(ticket))
+    .execute(pool)
+    .await?;
+    if res.rows_affected() == 0 {
+        return Err(Err::TokenInvalid);
+    }
+    match params {
+        Some(params) => serde_json::from_str(&params).map_err(|_| Err::InternalError),
+        None => Ok(AuthorizeParams::default()),
+    }
+}
@@ -1 +1,68 @@
+use crate::error::Err;
+use crate::model::{AppState, AuthedUser, ROLE_ADMIN};
+use crate::refresh::verify_access_token;
+use axum::body::Body;
+use axum::extract::{Request, State};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use std::sync::Arc;
+use tracing::{debug, error};
 
+// 取出 Authorization: Bearer <token>
+pub fn bearer_token(req: &Request<Body>) -> Option<&str> {
+    req.headers()
+        .get("Authorization")?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+}
+
+// 需要登录的接口，通过后把当前账号放入 request extensions
+pub async fn require_login(
+    State(app_state): State<Arc<AppState>>,
+    req: Request<Body>,
+    next: Next,
+) -> Result<impl IntoResponse, Err> {
+    authorize_request(&app_state, req, next, None).await
+}
+
+// 管理接口只允许 admin 访问
+pub async fn require_admin(
+    State(app_state): State<Arc<AppState>>,
+    req: Request<Body>,
+    next: Next,
+) -> Result<impl IntoResponse, Err> {
+    authorize_request(&app_state, req, next, Some(ROLE_ADMIN)).await
+}
+
+async fn authorize_request(
+    app_state: &AppState,
+    mut req: Request<Body>,
+    next: Next,
+    role: Option<&str>,
+) -> Result<Response, Err> {
+    let token = bearer_token(&req).ok_or_else(|| {
+        error!("Missing Authorization header");
+        Err::TokenInvalid
+    })?;
+    let claims = verify_access_token(app_state, token).await?;
+    let user: Option<(i64, String, String, String)> = sqlx::query_as(
+        "SELECT id,username,email,role FROM auth_user WHERE id = ?",
+    )
+    .bind(&claims.sub)
+    .fetch_optional(&app_state.pool)
+    .await?;
+    let (id, username, email, user_role) = user.ok_or(Err::TokenInvalid)?;
+    if let Some(role) = role {
+        if user_role != role {
+            debug!("user {} is not {}", id, role);
+            return Err(Err::AccessError);
+        }
+    }
+    req.extensions_mut().insert(AuthedUser {
+        id,
+        username,
+        email,
+    });
+    Ok(next.run(req).await)
+}
@@ -0,0 +1,4 @@
+use sqlx::migrate::Migrator;
+
+// 内嵌的数据库迁移，由 common::migrate::run_migrations 执行
+pub static MIGRATOR: Migrator = sqlx::migrate!();
@@ -1,3 +1,8 @@
+use crate::config::Config;
+use crate::keys::KeyStore;
+use crate::lockout::LoginGuard;
+use crate::mailer::Mailer;
+use crate::password::PasswordPolicy;
 use askama::Template;
 use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
@@ -11,13 +16,30 @@ pub struct User {
     pub email: String,             // VARCHAR 对应 String
     pub created_at: DateTime<Utc>, // 使用 chrono 的 DateTime<Utc> 处理时间
     pub updated_at: DateTime<Utc>, // 使用 chrono 的 DateTime<Utc> 处理时间
+    pub email_verified_at: Option<DateTime<Utc>>, // NULL 表示邮箱未验证
 }
-#[derive(Debug, Serialize, Deserialize, FromRow)]
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct AuthedUser {
     pub id: i64,
     pub username: String,
     pub email: String,
 }
+// /auth/token 的响应，sid 为登录会话 id，其他服务用来同步退出登录
+#[derive(Debug, Serialize)]
+pub struct TokenUser {
+    #[serde(flatten)]
+    pub user: AuthedUser,
+    pub sid: String,
+}
+impl From<&User> for AuthedUser {
+    fn from(user: &User) -> Self {
+        AuthedUser {
+            id: user.id,
+            username: user.username.clone(),
+            email: user.email.clone(),
+        }
+    }
+}
 #[derive(Debug, Serialize, Deserialize)]
 pub struct LoginRequest {
     pub username: String,
@@ -29,12 +51,28 @@ pub struct RegisterRequest {
     pub username: String,
     pub email: String,
     pub password: String,
+    #[serde(default)]
+    pub access: String,
 }
 
 #[derive(Debug, Serialize)]
-pub struct LoginResponse {
-    pub token: String,
-    // user_detail: UserDetail,
+pub struct TokenResponse {
+    pub access_token: String,
+    // 授权码换取 token 时只有 scope 包含 offline_access 才签发
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
+    pub token_type: String,
+    // access token 有效期，秒
+    pub expires_in: u64,
+    // scope 包含 openid 时签发
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id_token: Option<String>,
+}
+
+// 刷新令牌可以放在请求体中，也可以放在 refresh_token cookie 中
+#[derive(Debug, Default, Deserialize)]
+pub struct RefreshRequest {
+    pub refresh_token: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -49,6 +87,12 @@ pub struct Params {
 
 pub struct AppState {
     pub pool: Pool<MySql>,
+    pub keys: KeyStore,
+    // 启动时加载的配置
+    pub config: Config,
+    pub mailer: Box<dyn Mailer>,
+    pub login_guard: LoginGuard,
+    pub password_policy: PasswordPolicy,
 }
 
 #[derive(Template, Deserialize, Serialize, Debug)]
@@ -68,7 +112,236 @@ pub struct LoginKey {
     pub authenticity_token: String,
     pub username: String,
     pub password: String,
-    pub redirect: Option<String>,
+    // 从应用跳转过来登录时带上的授权参数
+    pub client_id: Option<String>,
+    pub redirect_uri: Option<String>,
+    pub state: Option<String>,
+    pub code_challenge: Option<String>,
+    pub code_challenge_method: Option<String>,
+    pub scope: Option<String>,
+    pub nonce: Option<String>,
+}
+
+// 应用跳转到登录页时的授权参数
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct AuthorizeParams {
+    pub response_type: Option<String>,
+    pub client_id: Option<String>,
+    pub redirect_uri: Option<String>,
+    pub state: Option<String>,
+    pub code_challenge: Option<String>,
+    pub code_challenge_method: Option<String>,
+    // OpenID Connect
+    pub scope: Option<String>,
+    pub nonce: Option<String>,
+}
+
+impl AuthorizeParams {
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scope
+            .as_deref()
+            .is_some_and(|s| s.split_whitespace().any(|s| s == scope))
+    }
+}
+
+impl From<&LoginKey> for AuthorizeParams {
+    fn from(login: &LoginKey) -> Self {
+        AuthorizeParams {
+            response_type: Some("code".to_string()),
+            client_id: login.client_id.clone(),
+            redirect_uri: login.redirect_uri.clone(),
+            state: login.state.clone(),
+            code_challenge: login.code_challenge.clone(),
+            code_challenge_method: login.code_challenge_method.clone(),
+            scope: login.scope.clone(),
+            nonce: login.nonce.clone(),
+        }
+    }
+}
+
+// 接入单点登录的应用
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct OAuthClient {
+    pub id: i64,
+    pub client_id: String,
+    #[serde(skip_serializing)]
+    pub client_secret_hash: Option<String>,
+    pub name: String,
+    // 每行一个回调地址
+    pub redirect_uris: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl OAuthClient {
+    pub fn allows_redirect(&self, redirect_uri: &str) -> bool {
+        self.redirect_uris.lines().any(|uri| uri.trim() == redirect_uri)
+    }
+}
+
+// 注册应用，public 为 true 时不生成 client_secret，只能依靠 PKCE
+#[derive(Debug, Deserialize)]
+pub struct ClientCreate {
+    pub client_id: String,
+    pub name: String,
+    pub redirect_uris: Vec<String>,
+    #[serde(default)]
+    pub public: bool,
+}
+
+// client_secret 只在注册时返回一次
+#[derive(Debug, Serialize)]
+pub struct ClientCreated {
+    pub client_id: String,
+    pub client_secret: Option<String>,
+    pub name: String,
+    pub redirect_uris: Vec<String>,
+}
+
+// 应用服务端使用授权码或刷新令牌换取 token
+#[derive(Debug, Deserialize)]
+pub struct TokenRequest {
+    pub grant_type: String,
+    pub code: Option<String>,
+    pub refresh_token: Option<String>,
+    pub redirect_uri: Option<String>,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+    pub code_verifier: Option<String>,
+}
+
+// 其他服务拉取 since（unix 秒）之后撤销的登录，只对有 client_secret 的应用开放
+#[derive(Debug, Deserialize)]
+pub struct RevocationRequest {
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+    pub since: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RevocationList {
+    pub revoked: Vec<String>,
+    // 下次请求的 since
+    pub now: i64,
+}
+
+#[derive(Template, Deserialize, Serialize, Debug)]
+#[template(path = "forgot_password.html")]
+pub struct ForgotPasswordKey {
+    pub authenticity_token: String,
+    pub email: String,
+}
+
+#[derive(Template, Deserialize, Serialize, Debug)]
+#[template(path = "reset_password.html")]
+pub struct ResetPasswordKey {
+    pub authenticity_token: String,
+    pub token: String,
+    pub password: String,
+    pub password_conform: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResetPasswordQuery {
+    pub token: String,
+}
+
+#[derive(Template, Deserialize, Serialize, Debug)]
+#[template(path = "resend_verification.html")]
+pub struct ResendVerificationKey {
+    pub authenticity_token: String,
+    pub email: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VerifyEmailQuery {
+    pub token: String,
+}
+
+// 账号角色
+pub const ROLE_USER: &str = "user";
+pub const ROLE_ADMIN: &str = "admin";
+pub const USER_ROLES: [&str; 2] = [ROLE_USER, ROLE_ADMIN];
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct Invitation {
+    pub id: i64,
+    pub code: String,
+    pub max_uses: Option<i32>,
+    pub used_count: i32,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub role: Option<String>,
+    pub email: Option<String>,
+    pub created_by: Option<i64>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+// 创建邀请码，code 为空时随机生成
+#[derive(Debug, Deserialize)]
+pub struct InvitationCreate {
+    pub code: Option<String>,
+    pub max_uses: Option<i32>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub role: Option<String>,
+    pub email: Option<String>,
+}
+
+// 邀请码使用记录
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct InvitationUse {
+    pub user_id: i64,
+    pub username: String,
+    pub email: String,
+    pub used_at: DateTime<Utc>,
+}
+
+// 两步验证：登录的第二步
+#[derive(Template, Deserialize, Serialize, Debug)]
+#[template(path = "totp.html")]
+pub struct TotpKey {
+    pub authenticity_token: String,
+    pub ticket: String,
+    pub code: String,
+}
+
+// 角色要求两步验证但尚未启用时，登录时先绑定
+#[derive(Template, Deserialize, Serialize, Debug)]
+#[template(path = "totp_enroll.html")]
+pub struct TotpEnrollKey {
+    pub authenticity_token: String,
+    pub ticket: String,
+    pub code: String,
+    #[serde(default)]
+    pub secret: String,
+    #[serde(default)]
+    pub otpauth_uri: String,
+    #[serde(default)]
+    pub recovery_codes: String,
+}
+
+// 新生成的 TOTP 密钥，恢复码只显示这一次
+#[derive(Debug, Serialize)]
+pub struct TotpSetup {
+    pub secret: String,
+    pub otpauth_uri: String,
+    pub recovery_codes: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MfaCodeRequest {
+    pub code: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RecoveryCodesResponse {
+    pub recovery_codes: Vec<String>,
+}
+
+// 角色是否要求两步验证
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct MfaPolicy {
+    pub role: String,
+    pub required: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
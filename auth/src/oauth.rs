@@ -0,0 +1,343 @@
+use crate::auth::{hash_token, random_token};
+use crate::error::Err;
+use crate::model::*;
+use crate::oidc::id_token;
+use crate::refresh::{issue_access_token, issue_tokens, rotate_refresh_token};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD as BASE64_URL;
+use base64::Engine;
+use reqwest::Url;
+use sha2::{Digest, Sha256};
+use sqlx::{FromRow, MySqlPool};
+use tracing::{debug, error, info};
+
+// 授权码有效期，秒
+const AUTHORIZATION_CODE_TTL: i64 = 60;
+
+// PKCE S256：BASE64URL(SHA256(code_verifier))
+pub fn pkce_challenge(verifier: &str) -> String {
+    BASE64_URL.encode(Sha256::digest(verifier.as_bytes()))
+}
+
+fn has_scope(scope: &str, name: &str) -> bool {
+    scope.split_whitespace().any(|s| s == name)
+}
+
+// code_verifier 为 43 到 128 位的 [A-Za-z0-9-._~]
+fn is_code_verifier(verifier: &str) -> bool {
+    (43..=128).contains(&verifier.len())
+        && verifier
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"-._~".contains(&b))
+}
+
+pub async fn get_client_db(pool: &MySqlPool, client_id: &str) -> Result<Option<OAuthClient>, Err> {
+    let client = sqlx::query_as::<_, OAuthClient>("SELECT * FROM oauth_clients WHERE client_id = ?")
+        .bind(client_id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(client)
+}
+
+pub async fn list_clients_db(pool: &MySqlPool) -> Result<Vec<OAuthClient>, Err> {
+    let clients = sqlx::query_as::<_, OAuthClient>("SELECT * FROM oauth_clients ORDER BY id")
+        .fetch_all(pool)
+        .await?;
+    Ok(clients)
+}
+
+// 注册应用，返回只显示一次的 client_secret
+pub async fn create_client_db(pool: &MySqlPool, client: &ClientCreate) -> Result<ClientCreated, Err> {
+    if client.client_id.trim().is_empty() || client.redirect_uris.is_empty() {
+        return Err(Err::InvalidRequest);
+    }
+    // 回调地址必须是完整的 http(s) 地址
+    for uri in &client.redirect_uris {
+        match Url::parse(uri) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") && url.fragment().is_none() => {}
+            _ => return Err(Err::InvalidRequest),
+        }
+    }
+    let client_secret = if client.public {
+        None
+    } else {
+        Some(random_token())
+    };
+    sqlx::query(
+        r#"
+        INSERT INTO oauth_clients (client_id, client_secret_hash, name, redirect_uris)
+        VALUES (?, ?, ?, ?)
+        "#,
+    )
+    .bind(client.client_id.trim())
+    .bind(client_secret.as_deref().map(hash_token))
+    .bind(&client.name)
+    .bind(client.redirect_uris.join("\n"))
+    .execute(pool)
+    .await
+    .map_err(|e| {
+        error!("create client failed: {:?}", e);
+        Err::DataBaseError
+    })?;
+    info!("oauth client {} registered", client.client_id);
+    Ok(ClientCreated {
+        client_id: client.client_id.trim().to_string(),
+        client_secret,
+        name: client.name.clone(),
+        redirect_uris: client.redirect_uris.clone(),
+    })
+}
+
+pub async fn delete_client_db(pool: &MySqlPool, client_id: &str) -> Result<(), Err> {
+    let res = sqlx::query("DELETE FROM oauth_clients WHERE client_id = ?")
+        .bind(client_id)
+        .execute(pool)
+        .await?;
+    match res.rows_affected() {
+        0 => Err(Err::RequestNotFound),
+        _ => Ok(()),
+    }
+}
+
+// 校验应用跳转过来的授权参数
+// 应用必须已注册、回调地址完全一致；没有 client_secret 的应用必须使用 S256 的 PKCE
+pub async fn check_authorize(
+    pool: &MySqlPool,
+    params: &AuthorizeParams,
+) -> Result<OAuthClient, Err> {
+    if params.response_type.as_deref().is_some_and(|t| t != "code") {
+        return Err(Err::InvalidRequest);
+    }
+    let client_id = params.client_id.as_deref().ok_or(Err::InvalidRequest)?;
+    let client = get_client_db(pool, client_id)
+        .await?
+        .ok_or(Err::InvalidClient)?;
+    let redirect_uri = params.redirect_uri.as_deref().ok_or(Err::InvalidRequest)?;
+    if !client.allows_redirect(redirect_uri) {
+        debug!("redirect uri {} not allowed for {}", redirect_uri, client_id);
+        return Err(Err::InvalidRequest);
+    }
+    match params.code_challenge.as_deref() {
+        None if client.client_secret_hash.is_some() => {}
+        Some(challenge)
+            if params.code_challenge_method.as_deref() == Some("S256")
+                && challenge.len() == 43
+                && challenge
+                    .bytes()
+                    .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_') => {}
+        _ => {
+            debug!("invalid pkce challenge from {}", client_id);
+            return Err(Err::InvalidRequest);
+        }
+    }
+    Ok(client)
+}
+
+// 登录成功后签发授权码，返回跳转回应用的地址
+pub async fn authorize_redirect(
+    pool: &MySqlPool,
+    params: &AuthorizeParams,
+    user_id: i64,
+) -> Result<String, Err> {
+    let client = check_authorize(pool, params).await?;
+    let redirect_uri = params.redirect_uri.as_deref().unwrap_or_default();
+    let code = random_token();
+    sqlx::query(
+        r#"
+        INSERT INTO authorization_codes (code_hash, client_id, user_id, redirect_uri, code_challenge, scope, nonce, expires_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?, DATE_ADD(NOW(), INTERVAL ? SECOND))
+        "#,
+    )
+    .bind(hash_token(&code))
+    .bind(&client.client_id)
+    .bind(user_id)
+    .bind(redirect_uri)
+    .bind(&params.code_challenge)
+    .bind(&params.scope)
+    .bind(&params.nonce)
+    .bind(AUTHORIZATION_CODE_TTL)
+    .execute(pool)
+    .await?;
+
+    let mut url = Url::parse(redirect_uri).map_err(|_| Err::InvalidRequest)?;
+    url.query_pairs_mut().append_pair("code", &code);
+    if let Some(state) = &params.state {
+        url.query_pairs_mut().append_pair("state", state);
+    }
+    Ok(url.to_string())
+}
+
+// 校验应用身份，有 client_secret 的应用必须提供正确的 secret
+async fn authenticate_client(pool: &MySqlPool, req: &TokenRequest) -> Result<OAuthClient, Err> {
+    check_client(pool, req.client_id.as_deref(), req.client_secret.as_deref()).await
+}
+
+pub async fn check_client(
+    pool: &MySqlPool,
+    client_id: Option<&str>,
+    client_secret: Option<&str>,
+) -> Result<OAuthClient, Err> {
+    let client_id = client_id.ok_or(Err::InvalidClient)?;
+    let client = get_client_db(pool, client_id)
+        .await?
+        .ok_or(Err::InvalidClient)?;
+    if let Some(secret_hash) = &client.client_secret_hash {
+        match client_secret {
+            Some(secret) if hash_token(secret) == *secret_hash => {}
+            _ => {
+                debug!("client {} authentication failed", client_id);
+                return Err(Err::InvalidClient);
+            }
+        }
+    }
+    Ok(client)
+}
+
+// token 端点，支持 authorization_code 和 refresh_token 两种方式
+pub async fn exchange_token(app_state: &AppState, req: &TokenRequest) -> Result<TokenResponse, Err> {
+    match req.grant_type.as_str() {
+        "authorization_code" => exchange_code(app_state, req).await,
+        "refresh_token" => {
+            let client = authenticate_client(&app_state.pool, req).await?;
+            let token = req.refresh_token.as_deref().ok_or(Err::InvalidRequest)?;
+            rotate_refresh_token(app_state, token, Some(&client.client_id))
+                .await
+                .map_err(|e| match e {
+                    Err::TokenInvalid | Err::TokenExpired => Err::InvalidGrant,
+                    e => e,
+                })
+        }
+        _ => Err(Err::UnsupportedGrantType),
+    }
+}
+
+#[derive(FromRow)]
+struct AuthorizationCode {
+    id: i64,
+    client_id: String,
+    user_id: i64,
+    redirect_uri: String,
+    code_challenge: Option<String>,
+    scope: Option<String>,
+    nonce: Option<String>,
+    used: bool,
+    valid: bool,
+    auth_time: i64,
+}
+
+// 授权码换取 token，授权码只能使用一次
+// scope 包含 openid 时同时签发 ID token
+async fn exchange_code(app_state: &AppState, req: &TokenRequest) -> Result<TokenResponse, Err> {
+    let client = authenticate_client(&app_state.pool, req).await?;
+    let code = req.code.as_deref().ok_or(Err::InvalidRequest)?;
+    if let Some(verifier) = req.code_verifier.as_deref() {
+        if !is_code_verifier(verifier) {
+            return Err(Err::InvalidRequest);
+        }
+    }
+
+    let mut conn = app_state.pool.begin().await.map_err(|e| {
+        error!("begin transaction failed: {:?}", e);
+        Err::DataBaseError
+    })?;
+    let row = sqlx::query_as::<_, AuthorizationCode>(
+        r#"
+        SELECT id, client_id, user_id, redirect_uri, code_challenge, scope, nonce,
+            used_at IS NOT NULL AS used,
+            expires_at > NOW() AS valid,
+            CAST(UNIX_TIMESTAMP(created_at) AS SIGNED) AS auth_time
+        FROM authorization_codes
+        WHERE code_hash = ?
+        FOR UPDATE
+        "#,
+    )
+    .bind(hash_token(code))
+    .fetch_optional(&mut *conn)
+    .await?;
+    // 申请授权码时使用了 PKCE 的，换取时必须提供对应的 code_verifier
+    let pkce_ok = |row: &AuthorizationCode| match &row.code_challenge {
+        Some(challenge) => req
+            .code_verifier
+            .as_deref()
+            .is_some_and(|verifier| pkce_challenge(verifier) == *challenge),
+        None => req.code_verifier.is_none(),
+    };
+    let row = match row {
+        Some(row)
+            if !row.used
+                && row.valid
+                && row.client_id == client.client_id
+                && req.redirect_uri.as_deref() == Some(row.redirect_uri.as_str())
+                && pkce_ok(&row) =>
+        {
+            row
+        }
+        _ => {
+            debug!("invalid authorization code from {}", client.client_id);
+            return Err(Err::InvalidGrant);
+        }
+    };
+    sqlx::query(r#"UPDATE authorization_codes SET used_at = NOW() WHERE id = ?"#)
+        .bind(row.id)
+        .execute(&mut *conn)
+        .await?;
+    conn.commit().await.map_err(|e| {
+        error!("commit transaction failed: {:?}", e);
+        Err::DataBaseError
+    })?;
+
+    let user = sqlx::query_as::<_, AuthedUser>(
+        "SELECT id,username,email FROM auth_user WHERE id = ?",
+    )
+    .bind(row.user_id)
+    .fetch_optional(&app_state.pool)
+    .await?
+    .ok_or(Err::InvalidGrant)?;
+    // 只有申请了 offline_access 的应用才签发刷新令牌，blog 后端登录只需要 access token
+    let scope = row.scope.unwrap_or_default();
+    let mut res = if has_scope(&scope, "offline_access") {
+        issue_tokens(app_state, &user, Some(&client.client_id)).await?
+    } else {
+        issue_access_token(app_state, &user, Some(&client.client_id)).await?
+    };
+    if has_scope(&scope, "openid") {
+        res.id_token = Some(id_token(
+            app_state,
+            &user,
+            &client.client_id,
+            &scope,
+            row.nonce,
+            row.auth_time,
+        )?);
+    }
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pkce_challenge_matches_rfc7636_example() {
+        let verifier = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+        assert_eq!(
+            pkce_challenge(verifier),
+            "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"
+        );
+    }
+
+    #[test]
+    fn code_verifier_length_and_charset() {
+        assert!(is_code_verifier(&"a".repeat(43)));
+        assert!(is_code_verifier(&"A1-._~".repeat(22)[..128]));
+        assert!(!is_code_verifier(&"a".repeat(42)));
+        assert!(!is_code_verifier(&"a".repeat(129)));
+        assert!(!is_code_verifier(&format!("{}+", "a".repeat(43))));
+    }
+
+    #[test]
+    fn scope_is_matched_by_whole_word() {
+        assert!(has_scope("openid offline_access", "offline_access"));
+        assert!(!has_scope("openid offline_access_x", "offline_access"));
+        assert!(!has_scope("", "openid"));
+    }
+}
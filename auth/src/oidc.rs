@@ -0,0 +1,95 @@
+use crate::config::TokenConfig;
+use crate::error::Err;
+use crate::model::{AppState, AuthedUser};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::MySqlPool;
+
+// ID token 载荷，scope 中没有 profile / email 时不包含对应的字段
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IdTokenClaims {
+    pub iss: String,
+    pub sub: String,
+    pub aud: String,
+    pub iat: i64,
+    pub exp: i64,
+    pub auth_time: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preferred_username: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email_verified: Option<bool>,
+}
+
+pub fn id_token(
+    app_state: &AppState,
+    user: &AuthedUser,
+    client_id: &str,
+    scope: &str,
+    nonce: Option<String>,
+    auth_time: i64,
+) -> Result<String, Err> {
+    let has_scope = |name: &str| scope.split_whitespace().any(|s| s == name);
+    let now = chrono::Utc::now().timestamp();
+    let claims = IdTokenClaims {
+        iss: app_state.config.token.issuer.clone(),
+        sub: user.id.to_string(),
+        aud: client_id.to_string(),
+        iat: now,
+        exp: now + app_state.config.token.access_ttl() as i64,
+        auth_time,
+        nonce,
+        preferred_username: has_scope("profile").then(|| user.username.clone()),
+        email: has_scope("email").then(|| user.email.clone()),
+        // 未验证邮箱的账号不能登录
+        email_verified: has_scope("email").then_some(true),
+    };
+    app_state.keys.sign(&claims)
+}
+
+// /.well-known/openid-configuration
+pub fn discovery_document(config: &TokenConfig) -> Value {
+    let issuer = config.issuer.trim_end_matches('/');
+    json!({
+        "issuer": issuer,
+        "authorization_endpoint": format!("{}/authorize", issuer),
+        "token_endpoint": format!("{}/token", issuer),
+        "userinfo_endpoint": format!("{}/userinfo", issuer),
+        "jwks_uri": format!("{}/auth/jwks", issuer),
+        "response_types_supported": ["code"],
+        "grant_types_supported": ["authorization_code", "refresh_token"],
+        "subject_types_supported": ["public"],
+        "id_token_signing_alg_values_supported": ["EdDSA"],
+        "scopes_supported": ["openid", "profile", "email", "offline_access"],
+        "token_endpoint_auth_methods_supported": ["client_secret_basic", "client_secret_post", "none"],
+        "code_challenge_methods_supported": ["S256"],
+        "claims_supported": ["iss", "sub", "aud", "iat", "exp", "auth_time", "nonce", "preferred_username", "email", "email_verified"],
+    })
+}
+
+#[derive(Debug, Serialize)]
+pub struct UserInfo {
+    pub sub: String,
+    pub preferred_username: String,
+    pub email: String,
+    pub email_verified: bool,
+}
+
+pub async fn userinfo_db(pool: &MySqlPool, user_id: &str) -> Result<UserInfo, Err> {
+    let row = sqlx::query_as::<_, (i64, String, String, bool)>(
+        r#"SELECT id, username, email, email_verified_at IS NOT NULL FROM auth_user WHERE id = ?"#,
+    )
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+    let (id, username, email, email_verified) = row.ok_or(Err::TokenInvalid)?;
+    Ok(UserInfo {
+        sub: id.to_string(),
+        preferred_username: username,
+        email,
+        email_verified,
+    })
+}
@@ -0,0 +1,175 @@
+use crate::config::PasswordConfig;
+use crate::error::Err;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::{Algorithm, Argon2, Params, Version};
+use sqlx::MySqlPool;
+use std::collections::HashSet;
+use tracing::{debug, error, info};
+
+// argon2id，默认 19 MiB、2 次迭代、1 个线程
+fn argon2(params: &Params) -> Argon2<'static> {
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params.clone())
+}
+
+// argon2 计算量大，放到阻塞线程池里执行，避免占住 tokio 的工作线程
+async fn blocking<T, F>(f: F) -> Result<T, Err>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    tokio::task::spawn_blocking(f).await.map_err(|e| {
+        error!("password task failed: {}", e);
+        Err::InternalError
+    })
+}
+
+// 生成 PHC 格式的 argon2id 哈希
+fn hash_password(params: &Params, password: &str) -> Result<String, Err> {
+    let salt = SaltString::generate(&mut rand::rngs::OsRng);
+    let hash = argon2(params)
+        .hash_password(password.as_bytes(), &salt)
+        .map_err(|e| {
+            error!("hash password failed: {}", e);
+            Err::InternalError
+        })?;
+    Ok(hash.to_string())
+}
+
+fn is_bcrypt(hash: &str) -> bool {
+    ["$2a$", "$2b$", "$2y$"].iter().any(|p| hash.starts_with(p))
+}
+
+// 校验密码，同时支持 argon2id 和旧的 bcrypt 哈希
+fn verify_password(params: &Params, password: &str, hash: &str) -> bool {
+    if is_bcrypt(hash) {
+        return bcrypt::verify(password, hash).unwrap_or_else(|e| {
+            error!("verify bcrypt password failed: {}", e);
+            false
+        });
+    }
+    match PasswordHash::new(hash) {
+        Ok(parsed) => argon2(params)
+            .verify_password(password.as_bytes(), &parsed)
+            .is_ok(),
+        Err(e) => {
+            error!("invalid password hash: {}", e);
+            false
+        }
+    }
+}
+
+// 注册和重置密码时的密码要求，以及哈希使用的参数
+#[derive(Debug)]
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    pub max_length: usize,
+    // 已泄露的密码，保存为小写
+    pub breached: HashSet<String>,
+    pub params: Params,
+    // 用户不存在时用于校验的哈希，使响应时间与密码错误时一致
+    dummy_hash: String,
+}
+
+impl PasswordPolicy {
+    // 配置了 password.breached_path 但读取失败时拒绝启动
+    pub fn from_config(config: &PasswordConfig) -> anyhow::Result<PasswordPolicy> {
+        let breached = match &config.breached_path {
+            Some(path) => {
+                let content = std::fs::read_to_string(path).map_err(|e| {
+                    anyhow::anyhow!("read breached password list {} failed: {}", path, e)
+                })?;
+                let breached: HashSet<String> = content
+                    .lines()
+                    .map(|line| line.trim().to_lowercase())
+                    .filter(|line| !line.is_empty())
+                    .collect();
+                info!("{} breached passwords loaded from {}", breached.len(), path);
+                breached
+            }
+            None => HashSet::new(),
+        };
+        let params = config
+            .argon2_params()
+            .map_err(|e| anyhow::anyhow!("invalid argon2 params: {}", e))?;
+        let dummy_hash = hash_password(&params, "dummy password")
+            .map_err(|_| anyhow::anyhow!("generate dummy password hash failed"))?;
+        Ok(PasswordPolicy {
+            min_length: config.min_length,
+            max_length: config.max_length,
+            breached,
+            params,
+            dummy_hash,
+        })
+    }
+
+    pub async fn hash(&self, password: &str) -> Result<String, Err> {
+        let params = self.params.clone();
+        let password = password.to_string();
+        blocking(move || hash_password(&params, &password)).await?
+    }
+
+    // 没有哈希（用户不存在）时校验 dummy_hash，结果总是失败
+    pub async fn verify(&self, password: &str, hash: Option<&str>) -> bool {
+        let params = self.params.clone();
+        let password = password.to_string();
+        let (hash, exists) = match hash {
+            Some(hash) => (hash.to_string(), true),
+            None => (self.dummy_hash.clone(), false),
+        };
+        blocking(move || verify_password(&params, &password, &hash) && exists)
+            .await
+            .unwrap_or(false)
+    }
+
+    // 不是 argon2id，或者参数与当前配置不同时需要重新哈希
+    pub fn needs_rehash(&self, hash: &str) -> bool {
+        let Ok(parsed) = PasswordHash::new(hash) else {
+            return true;
+        };
+        if parsed.algorithm != Algorithm::Argon2id.ident() {
+            return true;
+        }
+        match Params::try_from(&parsed) {
+            Ok(params) => {
+                params.m_cost() != self.params.m_cost()
+                    || params.t_cost() != self.params.t_cost()
+                    || params.p_cost() != self.params.p_cost()
+            }
+            Err(_) => true,
+        }
+    }
+
+    // 登录成功后把旧格式的哈希升级为当前配置的 argon2id，失败不影响登录
+    pub async fn rehash(&self, pool: &MySqlPool, user_id: i64, password: &str) {
+        let hash = match self.hash(password).await {
+            Ok(hash) => hash,
+            Err(_) => return,
+        };
+        match sqlx::query(r#"UPDATE auth_user SET password = ? WHERE id = ?"#)
+            .bind(hash)
+            .bind(user_id)
+            .execute(pool)
+            .await
+        {
+            Ok(_) => debug!("password hash of user {} upgraded", user_id),
+            Err(e) => error!("upgrade password hash failed: {:?}", e),
+        }
+    }
+
+    pub fn check(&self, password: &str, username: Option<&str>) -> Result<(), Err> {
+        let length = password.chars().count();
+        if length < self.min_length {
+            return Err(Err::PasswordTooShort);
+        }
+        if length > self.max_length {
+            return Err(Err::PasswordTooLong);
+        }
+        let lower = password.to_lowercase();
+        if self.breached.contains(&lower)
+            || username.is_some_and(|u| u.trim().to_lowercase() == lower)
+        {
+            return Err(Err::PasswordBreached);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,259 @@
+use crate::auth::{generate_token, hash_token, random_token, verify_token, Claims};
+use crate::error::Err;
+use crate::model::{AppState, AuthedUser, TokenResponse};
+use sqlx::{MySql, MySqlPool, Transaction};
+use tracing::{debug, error, warn};
+
+// 浏览器中保存刷新令牌的 cookie
+pub const REFRESH_COOKIE: &str = "refresh_token";
+
+async fn insert_refresh_token(
+    conn: &mut Transaction<'_, MySql>,
+    family_id: &str,
+    user_id: i64,
+    ttl_days: i64,
+) -> Result<String, Err> {
+    let token = random_token();
+    sqlx::query(
+        r#"
+        INSERT INTO refresh_tokens (family_id, user_id, token_hash, expires_at)
+        VALUES (?, ?, ?, DATE_ADD(NOW(), INTERVAL ? DAY))
+        "#,
+    )
+    .bind(family_id)
+    .bind(user_id)
+    .bind(hash_token(&token))
+    .bind(ttl_days)
+    .execute(&mut **conn)
+    .await?;
+    Ok(token)
+}
+
+async fn token_response(
+    app_state: &AppState,
+    user: &AuthedUser,
+    family_id: &str,
+    client_id: Option<&str>,
+    refresh_token: Option<String>,
+) -> Result<TokenResponse, Err> {
+    let access_token = generate_token(
+        user,
+        family_id,
+        client_id.unwrap_or(&app_state.config.token.audience),
+        &app_state.keys,
+        &app_state.config.token,
+    )
+    .await?;
+    Ok(TokenResponse {
+        access_token,
+        refresh_token,
+        token_type: "Bearer".to_string(),
+        expires_in: app_state.config.token.access_ttl(),
+        id_token: None,
+    })
+}
+
+// 登录成功后开始新的 family，签发 access token 和刷新令牌
+// client_id 为授权码所属的应用，直接登录时为 None
+pub async fn issue_tokens(
+    app_state: &AppState,
+    user: &AuthedUser,
+    client_id: Option<&str>,
+) -> Result<TokenResponse, Err> {
+    start_login(app_state, user, client_id, true).await
+}
+
+// 只签发 access token，family 仍然记录这次登录，用于撤销和同步退出登录
+pub async fn issue_access_token(
+    app_state: &AppState,
+    user: &AuthedUser,
+    client_id: Option<&str>,
+) -> Result<TokenResponse, Err> {
+    start_login(app_state, user, client_id, false).await
+}
+
+async fn start_login(
+    app_state: &AppState,
+    user: &AuthedUser,
+    client_id: Option<&str>,
+    with_refresh: bool,
+) -> Result<TokenResponse, Err> {
+    let family_id = uuid::Uuid::new_v4().to_string();
+    let mut conn = app_state.pool.begin().await.map_err(|e| {
+        error!("begin transaction failed: {:?}", e);
+        Err::DataBaseError
+    })?;
+    sqlx::query(r#"INSERT INTO token_families (id, user_id, client_id) VALUES (?, ?, ?)"#)
+        .bind(&family_id)
+        .bind(user.id)
+        .bind(client_id)
+        .execute(&mut *conn)
+        .await?;
+    let refresh_token = if with_refresh {
+        let token = insert_refresh_token(
+            &mut conn,
+            &family_id,
+            user.id,
+            app_state.config.token.refresh_ttl_days,
+        )
+        .await?;
+        Some(token)
+    } else {
+        None
+    };
+    conn.commit().await.map_err(|e| {
+        error!("commit transaction failed: {:?}", e);
+        Err::DataBaseError
+    })?;
+    token_response(app_state, user, &family_id, client_id, refresh_token).await
+}
+
+// 使用刷新令牌换取新的令牌，旧的刷新令牌同时失效
+// 已经轮换过的刷新令牌再次出现说明令牌可能被盗用，撤销整个 family
+// 刷新令牌只能由签发时的应用使用，client_id 为 None 表示直接登录（/auth/refresh）
+pub async fn rotate_refresh_token(
+    app_state: &AppState,
+    token: &str,
+    client_id: Option<&str>,
+) -> Result<TokenResponse, Err> {
+    let mut conn = app_state.pool.begin().await.map_err(|e| {
+        error!("begin transaction failed: {:?}", e);
+        Err::DataBaseError
+    })?;
+    let row = sqlx::query_as::<_, (i64, String, i64, bool, bool, bool, Option<String>)>(
+        r#"
+        SELECT r.id, r.family_id, r.user_id,
+            r.used_at IS NOT NULL AS used,
+            r.expires_at > NOW() AS valid,
+            f.revoked_at IS NULL AS active,
+            f.client_id
+        FROM refresh_tokens r
+        JOIN token_families f ON f.id = r.family_id
+        WHERE r.token_hash = ?
+        FOR UPDATE
+        "#,
+    )
+    .bind(hash_token(token))
+    .fetch_optional(&mut *conn)
+    .await?;
+    let (id, family_id, user_id) = match row {
+        None => return Err(Err::TokenInvalid),
+        Some((_, _, _, _, _, false, _)) => return Err(Err::TokenInvalid),
+        Some((_, family_id, _, _, _, _, owner)) if owner.as_deref() != client_id => {
+            debug!(
+                "refresh token of family {} presented by another client {:?}",
+                family_id, client_id
+            );
+            return Err(Err::TokenInvalid);
+        }
+        Some((_, family_id, user_id, true, _, true, _)) => {
+            warn!(
+                "refresh token reuse detected for user {}, revoking family {}",
+                user_id, family_id
+            );
+            sqlx::query(r#"UPDATE token_families SET revoked_at = NOW() WHERE id = ?"#)
+                .bind(&family_id)
+                .execute(&mut *conn)
+                .await?;
+            conn.commit().await.map_err(|e| {
+                error!("commit transaction failed: {:?}", e);
+                Err::DataBaseError
+            })?;
+            return Err(Err::TokenInvalid);
+        }
+        Some((_, _, _, false, false, true, _)) => return Err(Err::TokenExpired),
+        Some((id, family_id, user_id, false, true, true, _)) => (id, family_id, user_id),
+    };
+    sqlx::query(r#"UPDATE refresh_tokens SET used_at = NOW() WHERE id = ?"#)
+        .bind(id)
+        .execute(&mut *conn)
+        .await?;
+    let refresh_token = insert_refresh_token(
+        &mut conn,
+        &family_id,
+        user_id,
+        app_state.config.token.refresh_ttl_days,
+    )
+    .await?;
+    let user = sqlx::query_as::<_, AuthedUser>(
+        "SELECT id,username,email FROM auth_user WHERE id = ?",
+    )
+    .bind(user_id)
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or(Err::TokenInvalid)?;
+    conn.commit().await.map_err(|e| {
+        error!("commit transaction failed: {:?}", e);
+        Err::DataBaseError
+    })?;
+    debug!("refresh token rotated for user {}", user_id);
+    token_response(app_state, &user, &family_id, client_id, Some(refresh_token)).await
+}
+
+// 刷新令牌对应的用户 id，已撤销的返回 None
+pub async fn refresh_token_user(pool: &MySqlPool, token: &str) -> Result<Option<i64>, Err> {
+    let user_id = sqlx::query_scalar::<_, i64>(
+        r#"
+        SELECT r.user_id FROM refresh_tokens r
+        JOIN token_families f ON f.id = r.family_id
+        WHERE r.token_hash = ? AND f.revoked_at IS NULL
+        "#,
+    )
+    .bind(hash_token(token))
+    .fetch_optional(pool)
+    .await?;
+    Ok(user_id)
+}
+
+// 撤销用户所有的登录
+pub async fn revoke_user_tokens(pool: &MySqlPool, user_id: i64) -> Result<u64, Err> {
+    let res = sqlx::query(
+        r#"UPDATE token_families SET revoked_at = NOW() WHERE user_id = ? AND revoked_at IS NULL"#,
+    )
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+    debug!("revoked {} sessions of user {}", res.rows_affected(), user_id);
+    Ok(res.rows_affected())
+}
+
+// 验证直接登录签发的 access token（aud 为 token.audience），并确认所在的 family 没有被撤销
+pub async fn verify_access_token(app_state: &AppState, token: &str) -> Result<Claims, Err> {
+    let audience = &app_state.config.token.audience;
+    let claims = verify_token(token, &app_state.keys, &app_state.config.token, Some(audience)).await?;
+    check_family_active(app_state, claims).await
+}
+
+// 签发给任何应用的 access token，用于 userinfo
+pub async fn verify_client_access_token(app_state: &AppState, token: &str) -> Result<Claims, Err> {
+    let claims = verify_token(token, &app_state.keys, &app_state.config.token, None).await?;
+    check_family_active(app_state, claims).await
+}
+
+async fn check_family_active(app_state: &AppState, claims: Claims) -> Result<Claims, Err> {
+    let active = sqlx::query_scalar::<_, i64>(
+        r#"SELECT COUNT(*) FROM token_families WHERE id = ? AND revoked_at IS NULL"#,
+    )
+    .bind(&claims.sid)
+    .fetch_one(&app_state.pool)
+    .await?;
+    if active == 0 {
+        debug!("token family {} is revoked", claims.sid);
+        return Err(Err::TokenInvalid);
+    }
+    Ok(claims)
+}
+
+// since 之后撤销的登录会话 id 和数据库的当前时间，其他服务据此同步退出登录
+pub async fn revoked_families_since(pool: &MySqlPool, since: i64) -> Result<(Vec<String>, i64), Err> {
+    let now = sqlx::query_scalar::<_, i64>(r#"SELECT CAST(UNIX_TIMESTAMP() AS SIGNED)"#)
+        .fetch_one(pool)
+        .await?;
+    let revoked = sqlx::query_scalar::<_, String>(
+        r#"SELECT id FROM token_families WHERE revoked_at >= FROM_UNIXTIME(?)"#,
+    )
+    .bind(since)
+    .fetch_all(pool)
+    .await?;
+    Ok((revoked, now))
+}
@@ -0,0 +1,103 @@
+use crate::auth::{hash_token, random_token};
+use crate::error::Err;
+use sqlx::MySqlPool;
+use tracing::{debug, error};
+
+// 为用户创建新的找回密码令牌，之前未使用的令牌全部作废，有效期为分钟
+pub async fn create_reset_token(
+    pool: &MySqlPool,
+    user_id: i64,
+    ttl_minutes: i64,
+) -> Result<String, Err> {
+    let token = random_token();
+    let mut conn = pool.begin().await.map_err(|e| {
+        error!("begin transaction failed: {:?}", e);
+        Err::DataBaseError
+    })?;
+    sqlx::query(
+        r#"UPDATE password_reset_tokens SET used_at = NOW() WHERE user_id = ? AND used_at IS NULL"#,
+    )
+    .bind(user_id)
+    .execute(&mut *conn)
+    .await?;
+    sqlx::query(
+        r#"
+        INSERT INTO password_reset_tokens (user_id, token_hash, expires_at)
+        VALUES (?, ?, DATE_ADD(NOW(), INTERVAL ? MINUTE))
+        "#,
+    )
+    .bind(user_id)
+    .bind(hash_token(&token))
+    .bind(ttl_minutes)
+    .execute(&mut *conn)
+    .await?;
+    conn.commit().await.map_err(|e| {
+        error!("commit transaction failed: {:?}", e);
+        Err::DataBaseError
+    })?;
+    Ok(token)
+}
+
+// 令牌未使用且未过期时返回对应的用户 id
+pub async fn find_reset_token_user(pool: &MySqlPool, token: &str) -> Result<i64, Err> {
+    let row = sqlx::query_as::<_, (i64, bool)>(
+        r#"
+        SELECT user_id, expires_at > NOW() AS valid
+        FROM password_reset_tokens
+        WHERE token_hash = ? AND used_at IS NULL
+        "#,
+    )
+    .bind(hash_token(token))
+    .fetch_optional(pool)
+    .await?;
+    match row {
+        Some((user_id, true)) => Ok(user_id),
+        Some((_, false)) => Err(Err::TokenExpired),
+        None => Err(Err::TokenInvalid),
+    }
+}
+
+// 使用令牌修改密码，成功后该用户所有找回密码令牌失效
+pub async fn reset_password_by_token(
+    pool: &MySqlPool,
+    token: &str,
+    password_hash: &str,
+) -> Result<i64, Err> {
+    let mut conn = pool.begin().await.map_err(|e| {
+        error!("begin transaction failed: {:?}", e);
+        Err::DataBaseError
+    })?;
+    let row = sqlx::query_as::<_, (i64, bool)>(
+        r#"
+        SELECT user_id, expires_at > NOW() AS valid
+        FROM password_reset_tokens
+        WHERE token_hash = ? AND used_at IS NULL
+        FOR UPDATE
+        "#,
+    )
+    .bind(hash_token(token))
+    .fetch_optional(&mut *conn)
+    .await?;
+    let user_id = match row {
+        Some((user_id, true)) => user_id,
+        Some((_, false)) => return Err(Err::TokenExpired),
+        None => return Err(Err::TokenInvalid),
+    };
+    sqlx::query(r#"UPDATE auth_user SET password = ? WHERE id = ?"#)
+        .bind(password_hash)
+        .bind(user_id)
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query(
+        r#"UPDATE password_reset_tokens SET used_at = NOW() WHERE user_id = ? AND used_at IS NULL"#,
+    )
+    .bind(user_id)
+    .execute(&mut *conn)
+    .await?;
+    conn.commit().await.map_err(|e| {
+        error!("commit transaction failed: {:?}", e);
+        Err::DataBaseError
+    })?;
+    debug!("password reset for user {}", user_id);
+    Ok(user_id)
+}
@@ -0,0 +1,91 @@
+use crate::error::Err;
+use crate::mailer::Mail;
+use crate::model::{AppState, AuthedUser};
+use jsonwebtoken::{Algorithm, Validation};
+use serde::{Deserialize, Serialize};
+use sqlx::MySqlPool;
+use tracing::{debug, error};
+
+// 邮箱验证链接使用签名密钥签发，aud 与登录 token 不同，不能互相冒用
+const EMAIL_VERIFICATION_AUDIENCE: &str = "email-verification";
+// 验证链接有效期，秒
+const EMAIL_VERIFICATION_TTL: i64 = 24 * 3600;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EmailVerificationClaims {
+    pub sub: String,
+    pub email: String,
+    pub aud: String,
+    pub iat: i64,
+    pub exp: i64,
+}
+
+pub fn email_verification_token(app_state: &AppState, user: &AuthedUser) -> Result<String, Err> {
+    let now = chrono::Utc::now().timestamp();
+    let claims = EmailVerificationClaims {
+        sub: user.id.to_string(),
+        email: user.email.clone(),
+        aud: EMAIL_VERIFICATION_AUDIENCE.to_string(),
+        iat: now,
+        exp: now + EMAIL_VERIFICATION_TTL,
+    };
+    app_state.keys.sign(&claims)
+}
+
+pub fn check_email_verification_token(
+    app_state: &AppState,
+    token: &str,
+) -> Result<EmailVerificationClaims, Err> {
+    let mut validation = Validation::new(Algorithm::EdDSA);
+    validation.set_audience(&[EMAIL_VERIFICATION_AUDIENCE]);
+    app_state.keys.decode(token, &validation)
+}
+
+pub async fn send_verification_mail(app_state: &AppState, user: &AuthedUser) -> Result<(), Err> {
+    let token = email_verification_token(app_state, user)?;
+    let public_url = &app_state.config.server.public_url;
+    let mail = Mail {
+        to: user.email.clone(),
+        subject: "验证邮箱".to_string(),
+        body: format!(
+            "{}，你好：\n\n请在 24 小时内打开下面的链接完成邮箱验证：\n{}/auth/verify-email?token={}\n",
+            user.username, public_url, token
+        ),
+    };
+    app_state.mailer.send(&mail).await
+}
+
+// 邮箱与签发链接时一致才标记为已验证，修改过邮箱的旧链接失效
+pub async fn mark_email_verified(pool: &MySqlPool, user_id: i64, email: &str) -> Result<(), Err> {
+    let res = sqlx::query(
+        r#"UPDATE auth_user SET email_verified_at = NOW() WHERE id = ? AND email = ? AND email_verified_at IS NULL"#,
+    )
+    .bind(user_id)
+    .bind(email)
+    .execute(pool)
+    .await
+    .map_err(|e| {
+        error!("{}", e);
+        Err::DataBaseError
+    })?;
+    debug!("email verified for user {}: {}", user_id, res.rows_affected());
+    Ok(())
+}
+
+// 管理员手动验证邮箱
+pub async fn verify_email_by_username(pool: &MySqlPool, username: &str) -> Result<(), Err> {
+    let res = sqlx::query(
+        r#"UPDATE auth_user SET email_verified_at = COALESCE(email_verified_at, NOW()) WHERE username = ?"#,
+    )
+    .bind(username)
+    .execute(pool)
+    .await
+    .map_err(|e| {
+        error!("{}", e);
+        Err::DataBaseError
+    })?;
+    match res.rows_affected() {
+        0 => Err(Err::UserNotFound),
+        _ => Ok(()),
+    }
+}
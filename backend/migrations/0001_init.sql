@@ -0,0 +1,71 @@
+-- 博客基础表结构
+-- 文章按月分表 articles_table_YYYY_MM 由程序运行时创建，见 dbs/partition_db.rs
+
+CREATE TABLE IF NOT EXISTS user_table (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    username VARCHAR(255) NOT NULL UNIQUE,
+    email VARCHAR(255) NOT NULL UNIQUE,
+    INDEX (username)
+);
+
+CREATE TABLE IF NOT EXISTS user_detail_table (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    user_id INT NOT NULL,
+    nickname VARCHAR(20) UNIQUE,
+    avatar VARCHAR(255),
+    skills VARCHAR(255), -- python,c,c++,golang
+    bio TEXT,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    INDEX (nickname)
+);
+
+CREATE TABLE IF NOT EXISTS catalogues_table (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    user_detail_id INT,
+    catalogue VARCHAR(50) UNIQUE NOT NULL,
+    info VARCHAR(255),
+    INDEX (user_detail_id)
+);
+
+CREATE TABLE IF NOT EXISTS article_catalogues_table (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    catalogue_id INT NOT NULL,
+    article_id INT NOT NULL,
+    sort_order INT NOT NULL DEFAULT 0 -- 目录内排序
+);
+
+CREATE TABLE IF NOT EXISTS tags_table (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    tag VARCHAR(10) UNIQUE NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS article_tags_table (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    article_id INT NOT NULL,
+    tag_id INT NOT NULL
+);
+
+-- 全局文章 id 以及文章所在的月表
+CREATE TABLE IF NOT EXISTS article_index_table (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    table_name VARCHAR(64) NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    INDEX (table_name)
+);
+
+CREATE TABLE IF NOT EXISTS resume_table (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    user_detail_id INT,
+    content TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS comments_table (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    guest VARCHAR(20) NOT NULL, -- 游客名称
+    article_id INT NOT NULL,
+    parent_id INT NULL, -- 父评论 ID，如果是顶级评论则为 NULL
+    comment VARCHAR(255) NOT NULL, -- 评论内容
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP, -- 评论时间
+    depth INT -- 评论层级
+);
@@ -0,0 +1,2 @@
+-- 用户角色：author 只能管理自己的内容，editor 可以管理所有内容，admin 拥有全部权限
+ALTER TABLE user_detail_table ADD COLUMN role VARCHAR(20) NOT NULL DEFAULT 'author';
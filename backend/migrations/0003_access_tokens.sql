@@ -0,0 +1,14 @@
+-- 个人访问令牌，只保存 sha256 摘要
+CREATE TABLE IF NOT EXISTS access_tokens_table (
+    id INT AUTO_INCREMENT PRIMARY KEY,
+    user_detail_id INT NOT NULL,
+    name VARCHAR(100) NOT NULL,
+    token_prefix VARCHAR(20) NOT NULL,
+    token_hash CHAR(64) NOT NULL UNIQUE,
+    scopes VARCHAR(255) NOT NULL, -- 空格分隔，如 articles:write catalogues:write
+    expires_at TIMESTAMP NOT NULL,
+    last_used_at TIMESTAMP NULL,
+    revoked_at TIMESTAMP NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    INDEX (user_detail_id)
+);
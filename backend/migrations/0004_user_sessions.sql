@@ -0,0 +1,13 @@
+-- 登录 session 登记，id 保存在 session 中，撤销后该 session 不能再使用
+CREATE TABLE IF NOT EXISTS user_sessions_table (
+    id CHAR(32) PRIMARY KEY,
+    user_detail_id INT NOT NULL,
+    user_agent VARCHAR(255),
+    ip VARCHAR(64),
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    last_seen_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    expires_at TIMESTAMP NOT NULL,
+    revoked_at TIMESTAMP NULL,
+    INDEX (user_detail_id),
+    INDEX (last_seen_at)
+);
@@ -0,0 +1,7 @@
+-- tower_sessions 的 session 数据，多个实例共享，重启后不丢失
+CREATE TABLE IF NOT EXISTS tower_sessions_table (
+    id VARCHAR(64) PRIMARY KEY,
+    data MEDIUMTEXT NOT NULL,
+    expiry_date BIGINT NOT NULL,
+    INDEX (expiry_date)
+);
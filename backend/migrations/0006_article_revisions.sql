@@ -0,0 +1,13 @@
+-- 文章修订历史，每次创建或修改文章都保存一份完整内容，只追加不修改
+CREATE TABLE IF NOT EXISTS article_revisions_table (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    article_id INT NOT NULL,
+    revision INT NOT NULL,
+    title VARCHAR(50) NOT NULL,
+    content TEXT NOT NULL,
+    digest VARCHAR(100) NOT NULL,
+    -- 修改人
+    user_detail_id INT,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (article_id, revision)
+);
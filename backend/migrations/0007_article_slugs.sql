@@ -0,0 +1,9 @@
+-- 文章 slug 登记表，包含当前和以前使用过的 slug，旧的 slug 跳转到当前的 slug
+-- slug 一旦被某篇文章使用过就不能再分配给其他文章，保证旧链接不会指向别的文章
+CREATE TABLE IF NOT EXISTS article_slugs_table (
+    slug VARCHAR(100) PRIMARY KEY,
+    article_id INT NOT NULL,
+    is_current BOOL NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    INDEX (article_id)
+);
@@ -0,0 +1,4 @@
+-- 登录时认证服务的会话 id（token 的 sid），认证服务撤销后结束对应的 session
+ALTER TABLE user_sessions_table
+    ADD COLUMN auth_sid CHAR(36) NULL,
+    ADD INDEX idx_user_sessions_auth_sid (auth_sid);
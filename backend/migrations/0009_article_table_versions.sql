@@ -0,0 +1,6 @@
+-- 文章月表的结构版本，月表在运行时按月创建，升级步骤见 src/migrate.rs
+CREATE TABLE IF NOT EXISTS article_table_versions_table (
+    table_name VARCHAR(64) PRIMARY KEY,
+    version INT NOT NULL,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP
+);
@@ -0,0 +1,60 @@
+use crate::dbs::access_token_db::*;
+use crate::dbs::user_db::get_session_user_by_detail_id_db;
+use crate::error::AppError;
+use crate::models::permission::Scope;
+use crate::models::user::UserSession;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD as BASE64_URL;
+use base64::Engine;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use sqlx::MySqlPool;
+use tracing::{debug, error};
+
+// 个人访问令牌的前缀，便于在日志和代码仓库中识别泄露的令牌
+pub const ACCESS_TOKEN_PREFIX: &str = "blog_pat_";
+
+// 生成新的令牌，返回 (令牌, 用于展示的前几位, sha256 摘要)
+pub fn new_access_token() -> (String, String, String) {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    let token = format!("{}{}", ACCESS_TOKEN_PREFIX, BASE64_URL.encode(bytes));
+    let prefix = token[..ACCESS_TOKEN_PREFIX.len() + 6].to_string();
+    let hash = hash_access_token(&token);
+    (token, prefix, hash)
+}
+
+pub fn hash_access_token(token: &str) -> String {
+    Sha256::digest(token.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+pub fn scopes_to_string(scopes: &[Scope]) -> String {
+    scopes
+        .iter()
+        .map(|s| s.as_str())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// 校验 Authorization: Bearer 中的个人访问令牌，返回令牌所属的用户和权限范围
+pub async fn access_token_user(pool: &MySqlPool, token: &str) -> Result<UserSession, AppError> {
+    if !token.starts_with(ACCESS_TOKEN_PREFIX) {
+        return Err(AppError::TokenInvalid);
+    }
+    let (token_id, user_detail_id, scopes) =
+        find_active_access_token_db(pool, &hash_access_token(token))
+            .await?
+            .ok_or_else(|| {
+                debug!("access token not found, revoked or expired");
+                AppError::TokenInvalid
+            })?;
+    if let Err(e) = touch_access_token_db(pool, token_id).await {
+        error!("update access token last used failed: {:?}", e);
+    }
+    let mut user = get_session_user_by_detail_id_db(pool, user_detail_id).await?;
+    // 已经不再支持的范围直接忽略
+    user.scopes = Some(scopes.split_whitespace().filter_map(Scope::parse).collect());
+    Ok(user)
+}
@@ -1,3 +1,4 @@
+use backend::dbs::article_db::post_article_db;
 use backend::models::article::*;
 use backend::models::catalogue::*;
 use backend::models::comment::*;
@@ -120,26 +121,24 @@ async fn create_articles(times: u32, pool: &Pool<MySql>) -> Result<(), sqlx::Err
         let digest = format!("digest{}", i);
         let user_detail_id = rand::thread_rng().gen_range(1..=10);
 
-        // 插入 articles_table_2024_10
-        let res = sqlx::query(r#"INSERT INTO articles_table_2024_10 (title, content,digest, user_detail_id) VALUES (?, ?, ?,?)"#)
-            .bind(&title)
-            .bind(&content)
-            .bind(&digest)
-            .bind(user_detail_id)
-            .execute(pool)
-            .await
-            .expect("insert article_table failed");
-
-        let article_id = res.last_insert_id();
-
-        // 插入 article_tags_table
         let tag_id = rand::thread_rng().gen_range(1..=10);
-        sqlx::query(r#"INSERT INTO article_tags_table (article_id, tag_id) VALUES (?, ?)"#)
-            .bind(article_id)
-            .bind(tag_id)
-            .execute(pool)
+
+        // 通过分表层写入当月文章表，同时登记索引和标签
+        let article = ArticleCreate {
+            title,
+            content,
+            digest,
+            user_detail_id,
+            feature: false,
+            tags_id: vec![tag_id],
+            slug: None,
+            // 测试数据直接发布
+            status: ArticleStatus::Published,
+            publish_at: Some(chrono::Utc::now()),
+        };
+        let article_id = post_article_db(pool, &article)
             .await
-            .expect("insert article_tag_table failed");
+            .expect("insert article failed");
 
         // 插入 article_catalogues_table
         let catalogue_id = rand::thread_rng().gen_range(1..=10);
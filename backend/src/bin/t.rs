@@ -35,7 +35,8 @@ pub async fn get_session_user_by_username_db(
             u.username AS username, 
             u.email AS email, 
             b.nickname AS nickname, 
-            b.avatar AS avatar
+            b.avatar AS avatar,
+            b.role AS role
         FROM 
             user_table u
         JOIN 
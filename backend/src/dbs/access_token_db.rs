@@ -0,0 +1,121 @@
+use crate::error::AppError;
+use crate::models::access_token::*;
+use sqlx::MySqlPool;
+use tracing::{debug, error};
+
+pub async fn create_access_token_db(
+    pool: &MySqlPool,
+    user_detail_id: i64,
+    name: &str,
+    token_prefix: &str,
+    token_hash: &str,
+    scopes: &str,
+    expires_in_days: i64,
+) -> Result<AccessToken, AppError> {
+    let mut conn = pool.begin().await?;
+    let res = sqlx::query(
+        r#"
+        INSERT INTO access_tokens_table (user_detail_id, name, token_prefix, token_hash, scopes, expires_at)
+        VALUES (?, ?, ?, ?, ?, DATE_ADD(NOW(), INTERVAL ? DAY))
+        "#,
+    )
+    .bind(user_detail_id)
+    .bind(name)
+    .bind(token_prefix)
+    .bind(token_hash)
+    .bind(scopes)
+    .bind(expires_in_days)
+    .execute(&mut *conn)
+    .await
+    .map_err(|e| {
+        error!("create access token failed: {:?}", e);
+        AppError::InternalError
+    })?;
+    let token = sqlx::query_as::<_, AccessToken>(
+        r#"
+        SELECT id, name, token_prefix, scopes, expires_at, last_used_at, revoked_at, created_at
+        FROM access_tokens_table WHERE id = ?
+        "#,
+    )
+    .bind(res.last_insert_id())
+    .fetch_one(&mut *conn)
+    .await?;
+    conn.commit().await?;
+    debug!("access token {} created", token.id);
+    Ok(token)
+}
+
+pub async fn list_access_tokens_db(
+    pool: &MySqlPool,
+    user_detail_id: i64,
+) -> Result<Vec<AccessToken>, AppError> {
+    let tokens = sqlx::query_as::<_, AccessToken>(
+        r#"
+        SELECT id, name, token_prefix, scopes, expires_at, last_used_at, revoked_at, created_at
+        FROM access_tokens_table
+        WHERE user_detail_id = ?
+        ORDER BY id DESC
+        "#,
+    )
+    .bind(user_detail_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| {
+        error!("list access tokens failed: {:?}", e);
+        AppError::InternalError
+    })?;
+    Ok(tokens)
+}
+
+// 只能撤销自己的令牌
+pub async fn revoke_access_token_db(
+    pool: &MySqlPool,
+    user_detail_id: i64,
+    token_id: i64,
+) -> Result<(), AppError> {
+    let res = sqlx::query(
+        r#"
+        UPDATE access_tokens_table SET revoked_at = NOW()
+        WHERE id = ? AND user_detail_id = ? AND revoked_at IS NULL
+        "#,
+    )
+    .bind(token_id)
+    .bind(user_detail_id)
+    .execute(pool)
+    .await?;
+    match res.rows_affected() {
+        0 => Err(AppError::RequestNotFound),
+        _ => Ok(()),
+    }
+}
+
+// 未撤销且未过期的令牌，返回 (id, user_detail_id, scopes)
+pub async fn find_active_access_token_db(
+    pool: &MySqlPool,
+    token_hash: &str,
+) -> Result<Option<(i64, i64, String)>, AppError> {
+    let token = sqlx::query_as::<_, (i64, i64, String)>(
+        r#"
+        SELECT id, user_detail_id, scopes FROM access_tokens_table
+        WHERE token_hash = ? AND revoked_at IS NULL AND expires_at > NOW()
+        "#,
+    )
+    .bind(token_hash)
+    .fetch_optional(pool)
+    .await?;
+    Ok(token)
+}
+
+// 记录最后使用时间，一分钟内重复使用不再更新
+pub async fn touch_access_token_db(pool: &MySqlPool, token_id: i64) -> Result<(), AppError> {
+    sqlx::query(
+        r#"
+        UPDATE access_tokens_table SET last_used_at = NOW()
+        WHERE id = ? AND (last_used_at IS NULL OR last_used_at < DATE_SUB(NOW(), INTERVAL 1 MINUTE))
+        "#,
+    )
+    .bind(token_id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
@@ -2,42 +2,55 @@ use crate::handles::{article, user};
 use crate::models::article::*;
 use crate::models::parameter::*;
 use crate::models::user::*;
-use crate::utils::get_table_name;
+use crate::dbs::partition_db::*;
+use crate::dbs::revision_db::*;
+use crate::dbs::slug_db::claim_slug_db;
+use crate::markdown::{render_markdown, RenderedMarkdown, RENDER_VERSION};
+use crate::slug::slugify;
 use crate::{error::AppError, models::user::*};
 use chrono::{format, Utc};
 use sqlx::MySqlPool;
 use sqlx::Row;
 use tracing::{debug, error, info};
-pub async fn post_article_db(pool: &MySqlPool, article: &ArticleCreate) -> Result<(), AppError> {
+pub async fn post_article_db(pool: &MySqlPool, article: &ArticleCreate) -> Result<i64, AppError> {
+    // 写入当前月份的表，建表语句会隐式提交，需要在事务之前执行
+    let article_table_name = ensure_current_article_table(pool).await?;
     let mut conn = pool.begin().await.map_err({
         |e| {
             error!("begin transaction failed: {:?}", e);
             AppError::InternalError
         }
     })?;
-    let article_table_name = get_table_name().await;
+    let article_id = allocate_article_id(&mut conn, &article_table_name).await?;
+    // 保存时渲染 Markdown，查看详情时直接使用
+    let rendered = render_markdown(&article.content);
     let query = format!(
-        r#"INSERT INTO {} (title, content,digest, user_detail_id,feature) VALUES (?, ?, ?,?,?)"#,
+        r#"INSERT INTO {} (id, title, content,content_html,toc,render_version,digest, user_detail_id,feature,status,publish_at) VALUES (?, ?, ?, ?, ?, ?, ?,?,?,?,?)"#,
         article_table_name
     );
     let article_res = sqlx::query(&query)
+        .bind(article_id)
         .bind(&article.title)
         .bind(&article.content)
+        .bind(&rendered.html)
+        .bind(toc_json(&rendered)?)
+        .bind(RENDER_VERSION)
         .bind(&article.digest)
         .bind(&article.user_detail_id)
         .bind(&article.feature)
+        .bind(article.status.as_str())
+        .bind(article.publish_at)
         .execute(&mut *conn)
         .await;
     match article_res {
-        Ok(result) => {
-            let last_insert_id = result.last_insert_id();
-            // debug!("post article success, id: {}", last_insert_id);
+        Ok(_) => {
+            debug!("post article success, id: {}", article_id);
 
             for tag_id in &article.tags_id {
                 let res = sqlx::query(
                     r#"INSERT INTO article_tags_table (article_id, tag_id) VALUES (?, ?)"#,
                 )
-                .bind(last_insert_id)
+                .bind(article_id)
                 .bind(tag_id)
                 .execute(&mut *conn)
                 .await;
@@ -54,12 +67,23 @@ pub async fn post_article_db(pool: &MySqlPool, article: &ArticleCreate) -> Resul
                     debug!("post article tag success");
                 }
             }
+            // 手动填写的 slug 已被占用时返回错误，根据标题生成的 slug 自动加上后缀
+            match &article.slug {
+                Some(slug) => claim_slug_db(&mut conn, &article_table_name, article_id, slug, true).await?,
+                None => {
+                    let slug = slugify(&article.title);
+                    claim_slug_db(&mut conn, &article_table_name, article_id, &slug, false).await?
+                }
+            };
+            // 第一个修订，出错时事务在 drop 时回滚
+            insert_revision_db(&mut conn, &article_table_name, article_id, article.user_detail_id)
+                .await?;
             // 提交事务
             conn.commit().await.map_err(|e| {
                 error!("commit transaction failed: {:?}", e);
                 AppError::InternalError
             })?;
-            Ok(())
+            Ok(article_id)
         }
         Err(e) => {
             error!("post article failed: {:?}", e);
@@ -72,9 +96,52 @@ pub async fn post_article_db(pool: &MySqlPool, article: &ArticleCreate) -> Resul
         }
     }
 }
+fn toc_json(rendered: &RenderedMarkdown) -> Result<String, AppError> {
+    serde_json::to_string(&rendered.toc).map_err(|e| {
+        error!("serialize toc failed: {:?}", e);
+        AppError::InternalError
+    })
+}
+
+// 文章详情加上渲染结果，没有缓存的旧文章渲染后写回
+pub async fn render_article_db(pool: &MySqlPool, article: Article) -> Result<ArticleDetail, AppError> {
+    let cached = match (&article.content_html, &article.toc) {
+        (Some(html), Some(toc)) if article.render_version == RENDER_VERSION => {
+            serde_json::from_str(toc).ok().map(|toc| (html.clone(), toc))
+        }
+        _ => None,
+    };
+    let (html, toc) = match cached {
+        Some(cached) => cached,
+        None => {
+            let rendered = render_markdown(&article.content);
+            let article_table_name = article_table_by_id(pool, article.id).await?;
+            // 显式写入 updated_at，避免被 ON UPDATE 改成当前时间
+            let query = format!(
+                r#"UPDATE {} SET content_html = ?, toc = ?, render_version = ?, updated_at = updated_at WHERE id = ?"#,
+                article_table_name
+            );
+            sqlx::query(&query)
+                .bind(&rendered.html)
+                .bind(toc_json(&rendered)?)
+                .bind(RENDER_VERSION)
+                .bind(article.id)
+                .execute(pool)
+                .await
+                .map_err(|e| {
+                    error!("save rendered article failed: {:?}", e);
+                    AppError::InternalError
+                })?;
+            debug!("rendered article {} cached", article.id);
+            (rendered.html, rendered.toc)
+        }
+    };
+    Ok(ArticleDetail { article, html, toc })
+}
+
 pub async fn get_article_detail_db(pool: &MySqlPool, article_id: i64) -> Result<Article, AppError> {
-    // 返回类型修改为包含单个文章和总页数
-    let article_table_name = get_table_name().await;
+    // 通过索引表找到文章所在的月表
+    let article_table_name = article_table_by_id(pool, article_id).await?;
     let query = format!(r#"select * from {} where id = ?"#, article_table_name);
     let article = sqlx::query_as::<_, Article>(&query)
         .bind(article_id)
@@ -84,12 +151,211 @@ pub async fn get_article_detail_db(pool: &MySqlPool, article_id: i64) -> Result<
     Ok(article)
 }
 
+// 获取文章作者的 user_detail_id
+pub async fn get_article_owner_db(pool: &MySqlPool, article_id: i64) -> Result<i64, AppError> {
+    let article_table_name = article_table_by_id(pool, article_id).await?;
+    let query = format!(r#"SELECT user_detail_id FROM {} WHERE id = ?"#, article_table_name);
+    let owner = sqlx::query_scalar::<_, Option<i64>>(&query)
+        .bind(article_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| {
+            error!("get article owner failed: {:?}", e);
+            AppError::InternalError
+        })?;
+
+    match owner {
+        // 没有作者的文章只有 editor 以上可以管理
+        Some(owner) => Ok(owner.unwrap_or_default()),
+        None => Err(AppError::ArticleNotFound),
+    }
+}
+
+// 文章的作者和当前状态，修改状态前用于权限判断
+pub async fn get_article_state_db(
+    pool: &MySqlPool,
+    article_id: i64,
+) -> Result<(i64, ArticleStatus), AppError> {
+    let article_table_name = article_table_by_id(pool, article_id).await?;
+    let query = format!(
+        r#"SELECT user_detail_id, status FROM {} WHERE id = ?"#,
+        article_table_name
+    );
+    let row = sqlx::query(&query)
+        .bind(article_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| {
+            error!("get article state failed: {:?}", e);
+            AppError::InternalError
+        })?
+        .ok_or(AppError::ArticleNotFound)?;
+    let owner: Option<i64> = row.get("user_detail_id");
+    let status: String = row.get("status");
+    let status = ArticleStatus::parse(&status).ok_or_else(|| {
+        error!("unknown article status {} of article {}", status, article_id);
+        AppError::InternalError
+    })?;
+    Ok((owner.unwrap_or_default(), status))
+}
+
+pub async fn update_article_status_db(
+    pool: &MySqlPool,
+    article_id: i64,
+    status: ArticleStatus,
+    publish_at: Option<chrono::DateTime<Utc>>,
+) -> Result<(), AppError> {
+    let article_table_name = article_table_by_id(pool, article_id).await?;
+    let query = format!(
+        r#"UPDATE {} SET status = ?, publish_at = ? WHERE id = ?"#,
+        article_table_name
+    );
+    sqlx::query(&query)
+        .bind(status.as_str())
+        .bind(publish_at)
+        .bind(article_id)
+        .execute(pool)
+        .await
+        .map_err(|e| {
+            error!("update article status failed: {:?}", e);
+            AppError::InternalError
+        })?;
+    info!("article {} status changed to {}", article_id, status.as_str());
+    Ok(())
+}
+
+// 按状态查询文章，owner 为 None 时查询所有作者的文章；返回结果包含 status 和 publish_at
+pub async fn get_articles_by_status_db(
+    pool: &MySqlPool,
+    owner: Option<i64>,
+    status: Option<ArticleStatus>,
+    param: &PageParams,
+) -> Result<Vec<ArticleDisplay>, AppError> {
+    let article_table_name = articles_source(pool).await?;
+    let (limit, offset) = param.paginate();
+    let owner_filter = if owner.is_some() { "b.user_detail_id = ?" } else { "1 = 1" };
+    let status_filter = if status.is_some() { "b.status = ?" } else { "1 = 1" };
+
+    let total_count_query = format!(
+        r#"SELECT COUNT(*) AS total FROM {} b WHERE {} AND {}"#,
+        article_table_name, owner_filter, status_filter
+    );
+    let mut total_count = sqlx::query(&total_count_query);
+    if let Some(owner) = owner {
+        total_count = total_count.bind(owner);
+    }
+    if let Some(status) = status {
+        total_count = total_count.bind(status.as_str());
+    }
+    let total_count: i64 = total_count.fetch_one(pool).await?.get("total");
+    let total_page = (total_count as f64 / limit as f64).ceil() as i64; // 计算总页数
+
+    let query = format!(
+        r#"
+        SELECT
+            b.id AS article_id,
+            b.title AS article_title,
+            b.digest AS article_digest,
+            b.feature AS article_feature,
+            b.slug AS article_slug,
+            b.status AS article_status,
+            b.publish_at AS article_publish_at,
+            b.created_at AS article_created_at,
+            b.updated_at AS article_updated_at,
+            u.id AS author_id,
+            u.nickname AS author_nickname,
+            u.avatar AS author_avatar,
+            (SELECT GROUP_CONCAT(t.tag ORDER BY t.tag SEPARATOR ', ')
+                FROM article_tags_table bt JOIN tags_table t ON bt.tag_id = t.id
+                WHERE bt.article_id = b.id) AS tags
+        FROM
+            {} b
+        LEFT JOIN
+            user_detail_table u ON b.user_detail_id = u.id
+        WHERE
+            {} AND {}
+        ORDER BY b.updated_at DESC
+        LIMIT ? OFFSET ?;
+        "#,
+        article_table_name, owner_filter, status_filter
+    );
+    let mut rows = sqlx::query(&query);
+    if let Some(owner) = owner {
+        rows = rows.bind(owner);
+    }
+    if let Some(status) = status {
+        rows = rows.bind(status.as_str());
+    }
+    let rows = rows
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| {
+            error!("get articles by status failed: {:?}", e);
+            AppError::InternalError
+        })?;
+
+    let articles_info: Vec<ArticleDisplay> = rows
+        .into_iter()
+        .map(|row| {
+            let tags_str: Option<String> = row.get("tags");
+            let tags: Vec<String> = tags_str
+                .unwrap_or_default()
+                .split(", ")
+                .filter(|t| !t.is_empty())
+                .map(String::from)
+                .collect();
+            ArticleDisplay {
+                id: row.get::<i64, _>("article_id"),
+                title: row.get::<String, _>("article_title"),
+                digest: row.get::<String, _>("article_digest"),
+                feature: row.get::<bool, _>("article_feature"),
+                slug: row.get::<Option<String>, _>("article_slug"),
+                created_at: row.get::<chrono::DateTime<Utc>, _>("article_created_at"),
+                updated_at: row.get::<chrono::DateTime<Utc>, _>("article_updated_at"),
+                tags,
+                author: UserInfo {
+                    id: row.get::<Option<i64>, _>("author_id").unwrap_or_default(),
+                    nickname: row.get::<Option<String>, _>("author_nickname"),
+                    avatar: row.get::<Option<String>, _>("author_avatar"),
+                },
+                total_page: Some(total_page),
+                status: Some(row.get::<String, _>("article_status")),
+                publish_at: row.get::<Option<chrono::DateTime<Utc>>, _>("article_publish_at"),
+            }
+        })
+        .collect();
+    Ok(articles_info)
+}
+
+// 发布到期的定时文章，返回发布的数量
+pub async fn publish_due_articles_db(pool: &MySqlPool) -> Result<u64, AppError> {
+    let mut published = 0;
+    for table in list_article_tables(pool).await? {
+        let query = format!(
+            r#"UPDATE {} SET status = 'published' WHERE status = 'scheduled' AND publish_at <= ?"#,
+            table
+        );
+        let res = sqlx::query(&query)
+            .bind(Utc::now())
+            .execute(pool)
+            .await
+            .map_err(|e| {
+                error!("publish scheduled articles in {} failed: {:?}", table, e);
+                AppError::InternalError
+            })?;
+        published += res.rows_affected();
+    }
+    Ok(published)
+}
+
 pub async fn get_article_info_by_userid(
     pool: &MySqlPool,
     tag_id: i64,
     param: &PageParams,
 ) -> Result<Vec<ArticleDisplay>, AppError> {
-    let article_table_name = get_table_name().await;
+    let article_table_name = articles_source(pool).await?;
     let page = param.page.unwrap_or(1);
     let limit = param.limit.unwrap_or(10);
     let offset = (page - 1) * limit;
@@ -100,7 +366,7 @@ pub async fn get_article_info_by_userid(
           FROM
               {} b
           WHERE
-              b.user_detail_id = ?;  -- 添加对 user_detail_id 的过滤
+              b.user_detail_id = ? AND b.status = 'published';  -- 添加对 user_detail_id 的过滤
           "#,
         article_table_name
     );
@@ -122,24 +388,21 @@ pub async fn get_article_info_by_userid(
             b.title AS article_title,
             b.digest AS article_digest,
             b.feature AS article_feature,
+            b.slug AS article_slug,
             b.created_at AS article_created_at,
             b.updated_at AS article_updated_at,
             u.id AS author_id,
             u.nickname AS author_nickname,
             u.avatar AS author_avatar,
-            GROUP_CONCAT(t.tag ORDER BY t.tag SEPARATOR ', ') AS tags
+            (SELECT GROUP_CONCAT(t.tag ORDER BY t.tag SEPARATOR ', ')
+                FROM article_tags_table bt JOIN tags_table t ON bt.tag_id = t.id
+                WHERE bt.article_id = b.id) AS tags
         FROM
             {} b
         LEFT JOIN
             user_detail_table u ON b.user_detail_id = u.id
-        LEFT JOIN
-            article_tags_table bt ON b.id = bt.article_id
-        LEFT JOIN
-            tags_table t ON bt.tag_id = t.id
         WHERE
-            u.id = ?  -- 过滤出与指定 tag_id 相关的文章
-        GROUP BY
-            b.id, u.id
+            u.id = ? AND b.status = 'published'  -- 过滤出与指定 tag_id 相关的文章
         LIMIT ? OFFSET ?;  -- 使用 LIMIT 和 OFFSET 实现分页
         "#,
         article_table_name
@@ -170,6 +433,7 @@ pub async fn get_article_info_by_userid(
                 // content: row.get::<String, _>("article_content"),
                 digest: row.get::<String, _>("article_digest"),
                 feature: row.get::<bool, _>("article_feature"),
+                slug: row.get::<Option<String>, _>("article_slug"),
                 created_at: row.get::<chrono::DateTime<Utc>, _>("article_created_at"),
                 updated_at: row.get::<chrono::DateTime<Utc>, _>("article_updated_at"),
                 tags, // 包含标签名称
@@ -179,6 +443,8 @@ pub async fn get_article_info_by_userid(
                     avatar: Some(row.get::<String, _>("author_avatar")),
                 },
                 total_page: Some(total_page), // 包含总页数
+                status: None,
+                publish_at: None,
             }
         })
         .collect();
@@ -191,7 +457,7 @@ pub async fn get_article_info_by_tagid(
     param: &PageParams,
 ) -> Result<Vec<ArticleDisplay>, AppError> {
     // 修改返回类型以包含总页数
-    let article_table_name = get_table_name().await;
+    let article_table_name = articles_source(pool).await?;
     let page = param.page.unwrap_or(1);
     let limit = param.limit.unwrap_or(10);
     let offset = (page - 1) * limit;
@@ -204,7 +470,7 @@ pub async fn get_article_info_by_tagid(
         LEFT JOIN
             article_tags_table bt ON b.id = bt.article_id
         WHERE
-            bt.tag_id = ?;  -- 过滤出与指定 tag_id 相关的文章
+            bt.tag_id = ? AND b.status = 'published';  -- 过滤出与指定 tag_id 相关的文章
         "#,
         article_table_name
     );
@@ -226,24 +492,22 @@ pub async fn get_article_info_by_tagid(
             b.title AS article_title,
             b.digest AS article_digest,
             b.feature AS article_feature,
+            b.slug AS article_slug,
             b.created_at AS article_created_at,
             b.updated_at AS article_updated_at,
             u.id AS author_id,
             u.nickname AS author_nickname,
             u.avatar AS author_avatar,
-            GROUP_CONCAT(t.tag ORDER BY t.tag SEPARATOR ', ') AS tags
+            (SELECT GROUP_CONCAT(t.tag ORDER BY t.tag SEPARATOR ', ')
+                FROM article_tags_table bt JOIN tags_table t ON bt.tag_id = t.id
+                WHERE bt.article_id = b.id) AS tags
         FROM
             {} b
         LEFT JOIN
             user_detail_table u ON b.user_detail_id = u.id
-        LEFT JOIN
-            article_tags_table bt ON b.id = bt.article_id
-        LEFT JOIN
-            tags_table t ON bt.tag_id = t.id
         WHERE
-            bt.tag_id = ?  -- 过滤出与指定 tag_id 相关的文章
-        GROUP BY
-            b.id, u.id
+            EXISTS (SELECT 1 FROM article_tags_table bt WHERE bt.article_id = b.id AND bt.tag_id = ?)
+            AND b.status = 'published'  -- 过滤出与指定 tag_id 相关的文章
         LIMIT ? OFFSET ?;  -- 使用 LIMIT 和 OFFSET 实现分页
         "#,
         article_table_name
@@ -274,6 +538,7 @@ pub async fn get_article_info_by_tagid(
                 // content: row.get::<String, _>("article_content"),
                 digest: row.get::<String, _>("article_digest"),
                 feature: row.get::<bool, _>("article_feature"),
+                slug: row.get::<Option<String>, _>("article_slug"),
                 created_at: row.get::<chrono::DateTime<Utc>, _>("article_created_at"),
                 updated_at: row.get::<chrono::DateTime<Utc>, _>("article_updated_at"),
                 tags, // 包含标签名称
@@ -283,6 +548,8 @@ pub async fn get_article_info_by_tagid(
                     avatar: Some(row.get::<String, _>("author_avatar")),
                 },
                 total_page: Some(total_page), // 包含总页数
+                status: None,
+                publish_at: None,
             }
         })
         .collect();
@@ -296,7 +563,7 @@ pub async fn delete_article_db(pool: &MySqlPool, article_id: i64) -> Result<(),
         AppError::InternalError
     })?;
 
-    let article_table_name = get_table_name().await;
+    let article_table_name = article_table_by_id(pool, article_id).await?;
 
     // Delete the article from the specified table
     let res = sqlx::query(&format!(
@@ -317,6 +584,35 @@ pub async fn delete_article_db(pool: &MySqlPool, article_id: i64) -> Result<(),
                 .execute(&mut *conn)
                 .await;
 
+            let res = match res {
+                Ok(_) => {
+                    sqlx::query(r#"DELETE FROM article_index_table WHERE id = ?"#)
+                        .bind(article_id)
+                        .execute(&mut *conn)
+                        .await
+                }
+                Err(e) => Err(e),
+            };
+            // 修订历史和 slug 随文章一起删除
+            let res = match res {
+                Ok(_) => {
+                    sqlx::query(r#"DELETE FROM article_revisions_table WHERE article_id = ?"#)
+                        .bind(article_id)
+                        .execute(&mut *conn)
+                        .await
+                }
+                Err(e) => Err(e),
+            };
+            let res = match res {
+                Ok(_) => {
+                    sqlx::query(r#"DELETE FROM article_slugs_table WHERE article_id = ?"#)
+                        .bind(article_id)
+                        .execute(&mut *conn)
+                        .await
+                }
+                Err(e) => Err(e),
+            };
+
             // Check if the delete operation for article_tags_table was successful
             if res.is_ok() {
                 debug!("delete article_tags_table success");
@@ -349,112 +645,115 @@ pub async fn delete_article_db(pool: &MySqlPool, article_id: i64) -> Result<(),
     }
 }
 
+// 修改文章，修改标题、正文或摘要时保存一个新的修订，editor_id 为修改人
 pub async fn update_article_db(
     pool: &MySqlPool,
     article_id: i64,
     article: &ArticleUpdate,
+    editor_id: i64,
 ) -> Result<(), AppError> {
-    let article_table_name = get_table_name().await;
+    let article_table_name = article_table_by_id(pool, article_id).await?;
 
     // 创建一个可变的查询构建器
     let mut query = format!("UPDATE {} SET ", article_table_name);
     let mut updates = Vec::new(); // 用于存储字段更新的片段
 
     // 检查每个字段是否为空，如果不为空则添加到更新语句中
-    if let Some(ref title) = article.title {
+    if article.title.is_some() {
         updates.push("title = ?".to_string());
     }
-    if let Some(ref content) = article.content {
+    if article.content.is_some() {
         updates.push("content = ?".to_string());
+        updates.push("content_html = ?".to_string());
+        updates.push("toc = ?".to_string());
+        updates.push("render_version = ?".to_string());
     }
-    if let Some(ref digest) = article.digest {
+    if article.digest.is_some() {
         updates.push("digest = ?".to_string());
     }
-    if let Some(ref feature) = article.feature {
+    if article.feature.is_some() {
         updates.push("feature = ?".to_string());
     }
+    let text_changed =
+        article.title.is_some() || article.content.is_some() || article.digest.is_some();
 
     // 检查是否有任何字段需要更新
-    if updates.is_empty() {
+    if updates.is_empty() && article.tags_id.is_none() {
         debug!("No fields to update for article_id: {}", article_id);
         return Ok(()); // 没有字段需要更新，直接返回成功
     }
 
-    // 将更新的字段组合成完整的查询语句
-    query += &updates.join(", "); // 以逗号连接各个更新片段
-    query += " WHERE id = ?"; // 添加条件
-
-    // 创建查询并绑定值
-    let mut sql_query = sqlx::query(&query); // 使用不同的变量名
-    if let Some(ref title) = article.title {
-        sql_query = sql_query.bind(title);
-    }
-    if let Some(ref content) = article.content {
-        sql_query = sql_query.bind(content);
-    }
-    if let Some(ref digest) = article.digest {
-        sql_query = sql_query.bind(digest);
-    }
-    if let Some(ref feature) = article.feature {
-        sql_query = sql_query.bind(feature);
-    }
-    sql_query = sql_query.bind(article_id); // 绑定 article_id
     let mut conn = pool.begin().await.map_err({
         |e| {
             error!("begin transaction failed: {:?}", e);
             AppError::InternalError
         }
     })?;
-    // 执行查询
-    let res = sql_query.execute(pool).await;
+    if text_changed {
+        // 旧文章先保存原内容，修改后的内容在下面保存为新的修订
+        ensure_base_revision_db(&mut conn, &article_table_name, article_id).await?;
+    }
 
-    match res {
-        Ok(r) => {
-            debug!("update article success");
-            if let Some(tags_vec) = &article.tags_id {
-                for tag_id in tags_vec {
-                    let res = sqlx::query(
-                        r#"INSERT INTO article_tags_table (article_id, tag_id) VALUES (?, ?)"#,
-                    )
-                    .bind(article_id)
-                    .bind(tag_id)
-                    .execute(&mut *conn)
-                    .await;
-
-                    if let Err(e) = res {
-                        error!("post article tag failed: {:?}", e);
-                        // 回滚事务
-                        conn.rollback().await.map_err(|e| {
-                            error!("rollback transaction failed: {:?}", e);
-                            AppError::InternalError
-                        })?;
-                        return Err(AppError::InternalError);
-                    } else {
-                        debug!("post article tag success");
-                    }
-                }
-                // 提交事务
-                conn.commit().await.map_err(|e| {
-                    error!("commit transaction failed: {:?}", e);
-                    AppError::InternalError
-                })?;
-            }
+    if !updates.is_empty() {
+        // 将更新的字段组合成完整的查询语句
+        query += &updates.join(", "); // 以逗号连接各个更新片段
+        query += " WHERE id = ?"; // 添加条件
 
-            Ok(())
+        // 创建查询并绑定值
+        let mut sql_query = sqlx::query(&query); // 使用不同的变量名
+        if let Some(ref title) = article.title {
+            sql_query = sql_query.bind(title);
         }
-        Err(e) => {
-            error!("post article failed: {:?}", e);
-            // 回滚事务
-            conn.rollback().await.map_err(|e| {
-                error!("rollback transaction failed: {:?}", e);
-                AppError::InternalError
-            })?;
-            Err(AppError::InternalError)
+        if let Some(ref content) = article.content {
+            let rendered = render_markdown(content);
+            sql_query = sql_query
+                .bind(content)
+                .bind(rendered.html.clone())
+                .bind(toc_json(&rendered)?)
+                .bind(RENDER_VERSION);
         }
+        if let Some(ref digest) = article.digest {
+            sql_query = sql_query.bind(digest);
+        }
+        if let Some(ref feature) = article.feature {
+            sql_query = sql_query.bind(feature);
+        }
+        sql_query = sql_query.bind(article_id); // 绑定 article_id
+        // 执行查询，出错时事务在 drop 时回滚
+        sql_query.execute(&mut *conn).await.map_err(|e| {
+            error!("update article failed: {:?}", e);
+            AppError::InternalError
+        })?;
+        debug!("update article success");
     }
+
+    if let Some(tags_vec) = &article.tags_id {
+        for tag_id in tags_vec {
+            sqlx::query(r#"INSERT INTO article_tags_table (article_id, tag_id) VALUES (?, ?)"#)
+                .bind(article_id)
+                .bind(tag_id)
+                .execute(&mut *conn)
+                .await
+                .map_err(|e| {
+                    error!("post article tag failed: {:?}", e);
+                    AppError::InternalError
+                })?;
+        }
+        debug!("post article tag success");
+    }
+
+    if text_changed {
+        insert_revision_db(&mut conn, &article_table_name, article_id, editor_id).await?;
+    }
+    // 提交事务
+    conn.commit().await.map_err(|e| {
+        error!("commit transaction failed: {:?}", e);
+        AppError::InternalError
+    })?;
+    Ok(())
 }
 pub async fn get_featured_article_info(pool: &MySqlPool) -> Result<Vec<ArticleDisplay>, AppError> {
-    let article_table_name = get_table_name().await;
+    let article_table_name = articles_source(pool).await?;
 
     // 查询文章信息，随机排序并限制返回数量为 6
     let query = format!(
@@ -464,24 +763,21 @@ pub async fn get_featured_article_info(pool: &MySqlPool) -> Result<Vec<ArticleDi
             b.title AS article_title,
             b.digest AS article_digest,
             b.feature AS article_feature,
+            b.slug AS article_slug,
             b.created_at AS article_created_at,
             b.updated_at AS article_updated_at,
             u.id AS author_id,
             u.nickname AS author_nickname,
             u.avatar AS author_avatar,
-            GROUP_CONCAT(t.tag ORDER BY t.tag SEPARATOR ', ') AS tags
+            (SELECT GROUP_CONCAT(t.tag ORDER BY t.tag SEPARATOR ', ')
+                FROM article_tags_table bt JOIN tags_table t ON bt.tag_id = t.id
+                WHERE bt.article_id = b.id) AS tags
         FROM
             {} b
         LEFT JOIN
             user_detail_table u ON b.user_detail_id = u.id
-        LEFT JOIN
-            article_tags_table bt ON b.id = bt.article_id
-        LEFT JOIN
-            tags_table t ON bt.tag_id = t.id
         WHERE
-            b.feature = true  -- 过滤出 feature 为 true 的文章
-        GROUP BY
-            b.id, u.id
+            b.feature = true AND b.status = 'published'  -- 过滤出 feature 为 true 的已发布文章
         ORDER BY RAND()  -- 随机排序
         LIMIT 6;  -- 限制返回数量为 6
         "#,
@@ -507,6 +803,7 @@ pub async fn get_featured_article_info(pool: &MySqlPool) -> Result<Vec<ArticleDi
                 title: row.get::<String, _>("article_title"),
                 digest: row.get::<String, _>("article_digest"),
                 feature: row.get::<bool, _>("article_feature"),
+                slug: row.get::<Option<String>, _>("article_slug"),
                 created_at: row.get::<chrono::DateTime<Utc>, _>("article_created_at"),
                 updated_at: row.get::<chrono::DateTime<Utc>, _>("article_updated_at"),
                 tags, // 包含标签名称
@@ -516,6 +813,8 @@ pub async fn get_featured_article_info(pool: &MySqlPool) -> Result<Vec<ArticleDi
                     avatar: Some(row.get::<String, _>("author_avatar")),
                 },
                 total_page: None, // 设置 total_page 为 None
+                status: None,
+                publish_at: None,
             }
         })
         .collect();
@@ -527,7 +826,7 @@ pub async fn get_articles_last_db(
     pool: &MySqlPool,
     param: &PageParams,
 ) -> Result<Vec<ArticleDisplay>, AppError> {
-    let article_table_name = get_table_name().await;
+    let article_table_name = articles_source(pool).await?;
     let page = param.page.unwrap_or(1);
     let limit = param.limit.unwrap_or(10);
     let offset = (page - 1) * limit;
@@ -537,7 +836,9 @@ pub async fn get_articles_last_db(
         r#"
         SELECT COUNT(*) AS total
         FROM
-            {} b;  
+            {} b
+        WHERE
+            b.status = 'published';
         "#,
         article_table_name
     );
@@ -553,23 +854,22 @@ pub async fn get_articles_last_db(
             b.title AS article_title,
             b.digest AS article_digest,
             b.feature AS article_feature,
+            b.slug AS article_slug,
             b.created_at AS article_created_at,
             b.updated_at AS article_updated_at,
             u.id AS author_id,
             u.nickname AS author_nickname,
             u.avatar AS author_avatar,
-            GROUP_CONCAT(t.tag ORDER BY t.tag SEPARATOR ', ') AS tags
+            (SELECT GROUP_CONCAT(t.tag ORDER BY t.tag SEPARATOR ', ')
+                FROM article_tags_table bt JOIN tags_table t ON bt.tag_id = t.id
+                WHERE bt.article_id = b.id) AS tags
         FROM
               {} b
         LEFT JOIN
             user_detail_table u ON b.user_detail_id = u.id
-        LEFT JOIN
-            article_tags_table bt ON b.id = bt.article_id
-        LEFT JOIN
-            tags_table t ON bt.tag_id = t.id
-        GROUP BY
-            b.id, u.id,b.created_at
-        ORDER BY b.created_at DESC 
+        WHERE
+            b.status = 'published'
+        ORDER BY b.publish_at DESC 
         LIMIT ? OFFSET ?;  -- 使用 LIMIT 和 OFFSET 实现分页
         "#,
         article_table_name
@@ -599,6 +899,7 @@ pub async fn get_articles_last_db(
                 title: row.get::<String, _>("article_title"),
                 digest: row.get::<String, _>("article_digest"),
                 feature: row.get::<bool, _>("article_feature"),
+                slug: row.get::<Option<String>, _>("article_slug"),
                 created_at: row.get::<chrono::DateTime<Utc>, _>("article_created_at"),
                 updated_at: row.get::<chrono::DateTime<Utc>, _>("article_updated_at"),
                 tags, // 包含标签名称
@@ -608,6 +909,8 @@ pub async fn get_articles_last_db(
                     avatar: Some(row.get::<String, _>("author_avatar")),
                 },
                 total_page: Some(total_page), // 包含总页数
+                status: None,
+                publish_at: None,
             }
         })
         .collect();
@@ -616,7 +919,12 @@ pub async fn get_articles_last_db(
 }
 
 pub async fn get_articles_titles(pool: &MySqlPool) -> Result<Vec<ArticleTitle>, AppError> {
-    let res = sqlx::query_as::<_, ArticleTitle>(r#"SELECT id,title FROM articles_table_2024_10"#)
+    let article_table_name = articles_source(pool).await?;
+    let query = format!(
+        r#"SELECT b.id, b.title FROM {} b WHERE b.status = 'published' ORDER BY b.id"#,
+        article_table_name
+    );
+    let res = sqlx::query_as::<_, ArticleTitle>(&query)
         .fetch_all(pool)
         .await;
 
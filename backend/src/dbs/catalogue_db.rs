@@ -1,3 +1,4 @@
+use crate::dbs::partition_db::articles_source;
 use crate::models::article::ArticleTitle;
 use crate::models::catalogue::*;
 use crate::models::parameter::*;
@@ -134,19 +135,41 @@ pub async fn get_catalogue_by_id_db(
     }
 }
 
+// 获取目录创建者的 user_detail_id
+pub async fn get_catalogue_owner_db(pool: &MySqlPool, catalogue_id: i64) -> Result<i64, AppError> {
+    let owner = sqlx::query_scalar::<_, Option<i64>>(
+        r#"SELECT user_detail_id FROM catalogues_table WHERE id = ?"#,
+    )
+    .bind(catalogue_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| {
+        error!("get catalogue owner failed: {:?}", e);
+        AppError::InternalError
+    })?;
+
+    match owner {
+        Some(owner) => Ok(owner.unwrap_or_default()),
+        None => Err(AppError::CatalogueNotFound),
+    }
+}
+
 pub async fn get_catalogue_article_titles_db(
     pool: &MySqlPool,
     catalogue_id: i64,
 ) -> Result<Vec<CatalogueArticleTitles>, AppError> {
-    let vec = sqlx::query_as::<_, CatalogueArticleTitles>(
+    let article_table_name = articles_source(pool).await?;
+    let query = format!(
         r#"
 SELECT a.id as article_id,a.title,a.digest,ac.sort_order
-FROM articles_table_2024_10 AS a
+FROM {} AS a
 JOIN article_catalogues_table AS ac ON a.id = ac.article_id
-WHERE ac.catalogue_id = ?
+WHERE ac.catalogue_id = ? AND a.status = 'published'
 ORDER BY ac.sort_order;
         "#,
-    )
+        article_table_name
+    );
+    let vec = sqlx::query_as::<_, CatalogueArticleTitles>(&query)
     .bind(catalogue_id)
     .fetch_all(pool)
     .await
@@ -183,26 +206,39 @@ pub async fn delete_catalogue_article_one_by_id(
     }
 }
 
-//移除目录下的所有文章
+//移除目录下的所有文章，返回被移除的文章 id
 pub async fn delete_catalogue_article_all_by_id(
     pool: &MySqlPool,
     catalogue_id: i64,
-) -> Result<(), AppError> {
-    let res = sqlx::query(r#"DELETE FROM article_catalogues_table WHERE catalogue_id = ?"#)
+) -> Result<Vec<i64>, AppError> {
+    let mut conn = pool.begin().await.map_err(|e| {
+        error!("begin transaction failed: {:?}", e);
+        AppError::InternalError
+    })?;
+    let article_ids = sqlx::query_scalar::<_, i64>(
+        r#"SELECT article_id FROM article_catalogues_table WHERE catalogue_id = ? FOR UPDATE"#,
+    )
+    .bind(catalogue_id)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|e| {
+        error!("get catalogue articles failed: {:?}", e);
+        AppError::InternalError
+    })?;
+    sqlx::query(r#"DELETE FROM article_catalogues_table WHERE catalogue_id = ?"#)
         .bind(catalogue_id)
-        .execute(pool)
-        .await;
-
-    match res {
-        Ok(_) => {
-            debug!("delete catalogue article success");
-            Ok(())
-        }
-        Err(e) => {
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| {
             error!("delete catalogue article failed: {:?}", e);
-            Err(AppError::InternalError)
-        }
-    }
+            AppError::InternalError
+        })?;
+    conn.commit().await.map_err(|e| {
+        error!("commit transaction failed: {:?}", e);
+        AppError::InternalError
+    })?;
+    debug!("delete catalogue article success");
+    Ok(article_ids)
 }
 
 //添加文章到目录
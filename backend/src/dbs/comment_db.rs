@@ -14,8 +14,10 @@ use sqlx::Row;
 use tracing::{debug, error, info};
 
 // 嵌套结构返回评论
+// guest 为登录用户的用户名，不使用请求中的值
 pub async fn post_comment_db(
     pool: &MySqlPool,
+    guest: &str,
     comment_create: &CommentCreate,
 ) -> Result<(), AppError> {
     // 通过查询一次获取 parent_depth
@@ -43,7 +45,7 @@ pub async fn post_comment_db(
 
     // 插入评论
     let res = sqlx::query(r#"INSERT INTO comments_table (guest, article_id, parent_id, comment, depth) VALUES (?, ?, ?, ?, ?)"#)
-        .bind(&comment_create.guest)
+        .bind(guest)
         .bind(&comment_create.article_id)
         .bind(parent_id)
         .bind(&comment_create.comment)
@@ -100,3 +102,63 @@ pub async fn get_comments_db_by_article_id(
 
     Ok(comments_display)
 }
+
+// 评论所属的文章
+pub async fn get_comment_article_db(pool: &MySqlPool, comment_id: i32) -> Result<i64, AppError> {
+    sqlx::query_scalar::<_, i32>(r#"SELECT article_id FROM comments_table WHERE id = ?"#)
+        .bind(comment_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| {
+            error!("get comment failed: {:?}", e);
+            AppError::InternalError
+        })?
+        .map(i64::from)
+        .ok_or(AppError::RequestNotFound)
+}
+
+// 删除评论及其所有回复，返回删除的条数
+pub async fn delete_comment_db(pool: &MySqlPool, comment_id: i32) -> Result<u64, AppError> {
+    let mut tx = pool.begin().await.map_err(|e| {
+        error!("begin transaction failed: {:?}", e);
+        AppError::InternalError
+    })?;
+    let mut ids = vec![comment_id];
+    let mut parents = vec![comment_id];
+    // 按层查找回复，评论层级不深
+    while !parents.is_empty() {
+        let placeholders = vec!["?"; parents.len()].join(", ");
+        let query = format!(
+            r#"SELECT id FROM comments_table WHERE parent_id IN ({})"#,
+            placeholders
+        );
+        let mut q = sqlx::query_scalar::<_, i32>(&query);
+        for id in &parents {
+            q = q.bind(id);
+        }
+        parents = q.fetch_all(&mut *tx).await.map_err(|e| {
+            error!("get comment replies failed: {:?}", e);
+            AppError::InternalError
+        })?;
+        ids.extend(&parents);
+    }
+    let placeholders = vec!["?"; ids.len()].join(", ");
+    let query = format!(r#"DELETE FROM comments_table WHERE id IN ({})"#, placeholders);
+    let mut q = sqlx::query(&query);
+    for id in &ids {
+        q = q.bind(id);
+    }
+    let deleted = q
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| {
+            error!("delete comment failed: {:?}", e);
+            AppError::InternalError
+        })?
+        .rows_affected();
+    tx.commit().await.map_err(|e| {
+        error!("commit transaction failed: {:?}", e);
+        AppError::InternalError
+    })?;
+    Ok(deleted)
+}
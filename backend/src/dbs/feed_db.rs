@@ -0,0 +1,147 @@
+use crate::dbs::partition_db::articles_source;
+use crate::error::AppError;
+use crate::markdown::RENDER_VERSION;
+use crate::models::feed::*;
+use sqlx::{MySqlPool, Row};
+use tracing::error;
+
+// 文章的标签，按名称排序后用逗号连接
+const TAGS_COLUMN: &str = r#"(SELECT GROUP_CONCAT(t.tag ORDER BY t.tag SEPARATOR ', ')
+                FROM article_tags_table bt JOIN tags_table t ON bt.tag_id = t.id
+                WHERE bt.article_id = b.id)"#;
+
+// 订阅范围对应的查询条件和参数
+fn feed_condition(filter: FeedFilter) -> (&'static str, Option<i64>) {
+    match filter {
+        FeedFilter::All => ("", None),
+        FeedFilter::User(id) => ("AND b.user_detail_id = ?", Some(id)),
+        FeedFilter::Tag(id) => (
+            "AND EXISTS (SELECT 1 FROM article_tags_table bt WHERE bt.article_id = b.id AND bt.tag_id = ?)",
+            Some(id),
+        ),
+        FeedFilter::Catalogue(id) => (
+            "AND EXISTS (SELECT 1 FROM article_catalogues_table ac WHERE ac.article_id = b.id AND ac.catalogue_id = ?)",
+            Some(id),
+        ),
+    }
+}
+
+// 订阅范围内已发布文章最新的 updated_at、数量，以及每篇文章 slug、作者和标签的校验和，不读取正文
+pub async fn get_feed_version_db(pool: &MySqlPool, filter: FeedFilter) -> Result<FeedVersion, AppError> {
+    let article_table_name = articles_source(pool).await?;
+    let (condition, id) = feed_condition(filter);
+    let query = format!(
+        r#"
+        SELECT MAX(b.updated_at) AS updated, COUNT(*) AS total,
+            CAST(COALESCE(SUM(CRC32(CONCAT_WS('\n', b.id, COALESCE(b.slug, ''),
+                COALESCE(u.nickname, ''), COALESCE({}, '')))), 0) AS SIGNED) AS checksum
+        FROM {} b
+        LEFT JOIN user_detail_table u ON b.user_detail_id = u.id
+        WHERE b.status = 'published' {}
+        "#,
+        TAGS_COLUMN, article_table_name, condition
+    );
+    let mut query = sqlx::query(&query);
+    if let Some(id) = id {
+        query = query.bind(id);
+    }
+    let row = query.fetch_one(pool).await.map_err(|e| {
+        error!("get feed version failed: {:?}", e);
+        AppError::InternalError
+    })?;
+    Ok(FeedVersion {
+        updated: row.get("updated"),
+        count: row.get("total"),
+        checksum: row.get("checksum"),
+    })
+}
+
+// 最新发布的文章，与 get_articles_last_db 使用相同的数据
+// full 为 true 时包含正文和缓存的渲染结果，渲染规则变化前的缓存不使用
+pub async fn get_feed_articles_db(
+    pool: &MySqlPool,
+    filter: FeedFilter,
+    limit: i32,
+    full: bool,
+) -> Result<Vec<FeedArticle>, AppError> {
+    let article_table_name = articles_source(pool).await?;
+    let (condition, id) = feed_condition(filter);
+    let content_columns = if full {
+        "b.content, CASE WHEN b.render_version = ? THEN b.content_html END AS content_html"
+    } else {
+        "'' AS content, NULL AS content_html"
+    };
+    let query = format!(
+        r#"
+        SELECT
+            b.id, b.title, b.slug, b.digest, {}, b.publish_at, b.updated_at,
+            u.nickname AS author_nickname, {} AS tags
+        FROM {} b
+        LEFT JOIN user_detail_table u ON b.user_detail_id = u.id
+        WHERE b.status = 'published' {}
+        ORDER BY b.publish_at DESC
+        LIMIT ?
+        "#,
+        content_columns, TAGS_COLUMN, article_table_name, condition
+    );
+    let mut query = sqlx::query(&query);
+    if full {
+        query = query.bind(RENDER_VERSION);
+    }
+    if let Some(id) = id {
+        query = query.bind(id);
+    }
+    let rows = query.bind(limit).fetch_all(pool).await.map_err(|e| {
+        error!("get feed articles failed: {:?}", e);
+        AppError::InternalError
+    })?;
+    let articles = rows
+        .into_iter()
+        .map(|row| {
+            let tags_str: Option<String> = row.get("tags");
+            FeedArticle {
+                id: row.get("id"),
+                title: row.get("title"),
+                slug: row.get("slug"),
+                digest: row.get("digest"),
+                content: row.get("content"),
+                content_html: row.get("content_html"),
+                author: row.get("author_nickname"),
+                tags: tags_str
+                    .unwrap_or_default()
+                    .split(", ")
+                    .filter(|t| !t.is_empty())
+                    .map(String::from)
+                    .collect(),
+                publish_at: row.get("publish_at"),
+                updated_at: row.get("updated_at"),
+            }
+        })
+        .collect();
+    Ok(articles)
+}
+
+// 作者、标签或目录的名称，用于订阅标题；不存在时返回 None
+pub async fn get_feed_name_db(
+    pool: &MySqlPool,
+    filter: FeedFilter,
+) -> Result<Option<String>, AppError> {
+    let (query, id) = match filter {
+        FeedFilter::All => return Ok(None),
+        FeedFilter::User(id) => (r#"SELECT nickname FROM user_detail_table WHERE id = ?"#, id),
+        FeedFilter::Tag(id) => (r#"SELECT tag FROM tags_table WHERE id = ?"#, id),
+        FeedFilter::Catalogue(id) => (r#"SELECT catalogue FROM catalogues_table WHERE id = ?"#, id),
+    };
+    let name = sqlx::query_scalar::<_, Option<String>>(query)
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| {
+            error!("get feed name failed: {:?}", e);
+            AppError::InternalError
+        })?;
+    match name {
+        Some(name) => Ok(Some(name.unwrap_or_default())),
+        None => Err(AppError::RequestNotFound),
+    }
+}
@@ -1,5 +1,13 @@
+pub mod access_token_db;
 pub mod article_db;
 pub mod catalogue_db;
 pub mod comment_db;
+pub mod feed_db;
+pub mod partition_db;
+pub mod revision_db;
+pub mod search_db;
+pub mod session_db;
+pub mod sitemap_db;
+pub mod slug_db;
 pub mod tag_db;
 pub mod user_db;
@@ -0,0 +1,180 @@
+use crate::error::AppError;
+use crate::migrate::{record_article_table, ARTICLE_TABLE_VERSION};
+use crate::utils::get_table_name;
+use sqlx::{MySql, MySqlPool, Transaction};
+use tracing::{debug, error, info};
+
+// 文章按月分表，articles_table_YYYY_MM
+// article_index_table 记录全局唯一的文章 id 以及文章所在的月表
+pub const ARTICLE_TABLE_PREFIX: &str = "articles_table_";
+pub const ARTICLE_COLUMNS: &str =
+    "id, title, slug, content, content_html, render_version, digest, user_detail_id, feature, status, publish_at, created_at, updated_at";
+
+fn article_table_ddl(table_name: &str) -> String {
+    format!(
+        r#"
+        CREATE TABLE IF NOT EXISTS {} (
+            id INT AUTO_INCREMENT PRIMARY KEY,
+            title VARCHAR(50) UNIQUE NOT NULL,
+            slug VARCHAR(100) NULL,
+            content TEXT NOT NULL,
+            content_html MEDIUMTEXT NULL,
+            toc TEXT NULL,
+            render_version INT NOT NULL DEFAULT 0,
+            digest VARCHAR(100) NOT NULL,
+            user_detail_id INT,
+            feature BOOL DEFAULT FALSE,
+            status VARCHAR(16) NOT NULL DEFAULT 'draft',
+            publish_at TIMESTAMP NULL,
+            created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+            updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+            INDEX (status, publish_at),
+            FULLTEXT INDEX ft_article (title, digest, content) WITH PARSER ngram,
+            FULLTEXT INDEX ft_article_title (title) WITH PARSER ngram
+        )
+        "#,
+        table_name
+    )
+}
+
+// 月表名只能是 articles_table_YYYY_MM，防止拼接进 SQL 的表名被注入
+pub fn is_article_table_name(name: &str) -> bool {
+    match name.strip_prefix(ARTICLE_TABLE_PREFIX) {
+        Some(suffix) => {
+            let bytes = suffix.as_bytes();
+            bytes.len() == 7
+                && bytes[4] == b'_'
+                && bytes
+                    .iter()
+                    .enumerate()
+                    .all(|(i, b)| i == 4 || b.is_ascii_digit())
+        }
+        None => false,
+    }
+}
+
+// 确保当前月份的文章表存在，返回表名
+// 注意 DDL 会隐式提交事务，必须在事务开始之前调用
+// 先登记为最新版本再建表，表已经存在时启动时已经登记过，不会被覆盖
+pub async fn ensure_current_article_table(pool: &MySqlPool) -> Result<String, AppError> {
+    let table_name = get_table_name().await;
+    record_article_table(pool, &table_name, ARTICLE_TABLE_VERSION).await?;
+    sqlx::query(&article_table_ddl(&table_name))
+        .execute(pool)
+        .await
+        .map_err(|e| {
+            error!("create article table {} failed: {:?}", table_name, e);
+            AppError::DataBaseError
+        })?;
+    Ok(table_name)
+}
+
+// 获取数据库中所有的文章月表，按月份排序
+pub async fn list_article_tables(pool: &MySqlPool) -> Result<Vec<String>, AppError> {
+    let tables = sqlx::query_scalar::<_, String>(
+        r#"
+        SELECT CAST(table_name AS CHAR) FROM information_schema.tables
+        WHERE table_schema = DATABASE() AND table_name LIKE 'articles\_table\_%'
+        ORDER BY table_name
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| {
+        error!("list article tables failed: {:?}", e);
+        AppError::DataBaseError
+    })?;
+
+    Ok(tables
+        .into_iter()
+        .filter(|t| is_article_table_name(t))
+        .collect())
+}
+
+// 把已有月表中的文章补登记到索引表中
+pub async fn sync_article_index(pool: &MySqlPool) -> Result<(), AppError> {
+    for table_name in list_article_tables(pool).await? {
+        let res = sqlx::query(&format!(
+            r#"INSERT IGNORE INTO article_index_table (id, table_name, created_at) SELECT id, ?, created_at FROM {}"#,
+            table_name
+        ))
+        .bind(&table_name)
+        .execute(pool)
+        .await
+        .map_err(|e| {
+            error!("sync article index for {} failed: {:?}", table_name, e);
+            AppError::DataBaseError
+        })?;
+        if res.rows_affected() > 0 {
+            info!(
+                "indexed {} articles from {}",
+                res.rows_affected(),
+                table_name
+            );
+        }
+    }
+    Ok(())
+}
+
+// 启动时初始化分表：当前月表、补登记旧文章
+// article_index_table 由迁移创建，旧月表的升级见 migrate::migrate_article_tables
+pub async fn init_article_partitions(pool: &MySqlPool) -> Result<(), AppError> {
+    ensure_current_article_table(pool).await?;
+    sync_article_index(pool).await?;
+    Ok(())
+}
+
+// 在索引表中分配新的全局文章 id
+pub async fn allocate_article_id(
+    conn: &mut Transaction<'_, MySql>,
+    table_name: &str,
+) -> Result<i64, AppError> {
+    let res = sqlx::query(r#"INSERT INTO article_index_table (table_name) VALUES (?)"#)
+        .bind(table_name)
+        .execute(&mut **conn)
+        .await
+        .map_err(|e| {
+            error!("allocate article id failed: {:?}", e);
+            AppError::DataBaseError
+        })?;
+    Ok(res.last_insert_id() as i64)
+}
+
+// 根据文章 id 查找文章所在的月表
+pub async fn article_table_by_id(pool: &MySqlPool, article_id: i64) -> Result<String, AppError> {
+    let table_name = sqlx::query_scalar::<_, String>(
+        r#"SELECT table_name FROM article_index_table WHERE id = ?"#,
+    )
+    .bind(article_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| {
+        error!("get article table failed: {:?}", e);
+        AppError::DataBaseError
+    })?;
+
+    match table_name {
+        Some(table_name) if is_article_table_name(&table_name) => Ok(table_name),
+        Some(table_name) => {
+            error!("invalid article table name in index: {}", table_name);
+            Err(AppError::InternalError)
+        }
+        None => {
+            debug!("article {} not found in index", article_id);
+            Err(AppError::ArticleNotFound)
+        }
+    }
+}
+
+// 返回所有月表合并后的子查询，可以直接放在 FROM 后面使用
+pub async fn articles_source(pool: &MySqlPool) -> Result<String, AppError> {
+    let mut tables = list_article_tables(pool).await?;
+    if tables.is_empty() {
+        tables.push(ensure_current_article_table(pool).await?);
+    }
+    let selects: Vec<String> = tables
+        .iter()
+        .map(|t| format!("SELECT {} FROM {}", ARTICLE_COLUMNS, t))
+        .collect();
+    Ok(format!("({})", selects.join(" UNION ALL ")))
+}
@@ -0,0 +1,140 @@
+use crate::error::AppError;
+use crate::models::revision::*;
+use sqlx::{MySqlConnection, MySqlPool};
+use tracing::{debug, error};
+
+// 保存文章当前内容为新的修订，需要在修改文章的事务中调用
+pub async fn insert_revision_db(
+    conn: &mut MySqlConnection,
+    article_table_name: &str,
+    article_id: i64,
+    user_detail_id: i64,
+) -> Result<(), AppError> {
+    lock_article_db(conn, article_table_name, article_id).await?;
+    let revision = sqlx::query_scalar::<_, i32>(
+        r#"SELECT COALESCE(MAX(revision), 0) + 1 FROM article_revisions_table WHERE article_id = ?"#,
+    )
+    .bind(article_id)
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(|e| {
+        error!("get next article revision failed: {:?}", e);
+        AppError::InternalError
+    })?;
+    let query = format!(
+        r#"
+        INSERT INTO article_revisions_table (article_id, revision, title, content, digest, user_detail_id)
+        SELECT a.id, ?, a.title, a.content, a.digest, ?
+        FROM {} a
+        WHERE a.id = ?
+        "#,
+        article_table_name
+    );
+    sqlx::query(&query)
+        .bind(revision)
+        .bind(user_detail_id)
+        .bind(article_id)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| {
+            error!("insert article revision failed: {:?}", e);
+            AppError::InternalError
+        })?;
+    debug!("revision {} saved for article {}", revision, article_id);
+    Ok(())
+}
+
+// 锁住文章所在的行直到事务结束，同一篇文章的修订号按顺序分配，并发修改不会取到相同的修订号
+async fn lock_article_db(
+    conn: &mut MySqlConnection,
+    article_table_name: &str,
+    article_id: i64,
+) -> Result<(), AppError> {
+    let query = format!(r#"SELECT id FROM {} WHERE id = ? FOR UPDATE"#, article_table_name);
+    sqlx::query(&query)
+        .bind(article_id)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(|e| {
+            error!("lock article failed: {:?}", e);
+            AppError::InternalError
+        })?
+        .ok_or(AppError::ArticleNotFound)?;
+    Ok(())
+}
+
+// 修订功能上线前创建的文章没有修订，修改前先把原内容保存为第一个修订
+pub async fn ensure_base_revision_db(
+    conn: &mut MySqlConnection,
+    article_table_name: &str,
+    article_id: i64,
+) -> Result<(), AppError> {
+    lock_article_db(conn, article_table_name, article_id).await?;
+    let query = format!(
+        r#"
+        INSERT INTO article_revisions_table (article_id, revision, title, content, digest, user_detail_id, created_at)
+        SELECT a.id, 1, a.title, a.content, a.digest, a.user_detail_id, a.updated_at
+        FROM {} a
+        WHERE a.id = ?
+            AND NOT EXISTS (SELECT 1 FROM article_revisions_table r WHERE r.article_id = a.id)
+        "#,
+        article_table_name
+    );
+    sqlx::query(&query)
+        .bind(article_id)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| {
+            error!("insert base article revision failed: {:?}", e);
+            AppError::InternalError
+        })?;
+    Ok(())
+}
+
+pub async fn list_revisions_db(
+    pool: &MySqlPool,
+    article_id: i64,
+) -> Result<Vec<ArticleRevisionInfo>, AppError> {
+    let revisions = sqlx::query_as::<_, ArticleRevisionInfo>(
+        r#"
+        SELECT r.revision, r.title, r.user_detail_id, u.nickname, r.created_at
+        FROM article_revisions_table r
+        LEFT JOIN user_detail_table u ON r.user_detail_id = u.id
+        WHERE r.article_id = ?
+        ORDER BY r.revision DESC
+        "#,
+    )
+    .bind(article_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| {
+        error!("list article revisions failed: {:?}", e);
+        AppError::InternalError
+    })?;
+    Ok(revisions)
+}
+
+pub async fn get_revision_db(
+    pool: &MySqlPool,
+    article_id: i64,
+    revision: i32,
+) -> Result<ArticleRevision, AppError> {
+    sqlx::query_as::<_, ArticleRevision>(
+        r#"
+        SELECT r.article_id, r.revision, r.title, r.content, r.digest, r.user_detail_id,
+            u.nickname, r.created_at
+        FROM article_revisions_table r
+        LEFT JOIN user_detail_table u ON r.user_detail_id = u.id
+        WHERE r.article_id = ? AND r.revision = ?
+        "#,
+    )
+    .bind(article_id)
+    .bind(revision)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| {
+        error!("get article revision failed: {:?}", e);
+        AppError::InternalError
+    })?
+    .ok_or(AppError::RequestNotFound)
+}
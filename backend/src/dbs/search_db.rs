@@ -0,0 +1,276 @@
+use crate::dbs::partition_db::list_article_tables;
+use crate::error::AppError;
+use crate::markdown::plain_text;
+use crate::models::catalogue::Catalogue;
+use crate::models::parameter::ArticleQueryParams;
+use crate::models::search::*;
+use crate::models::tag::Tag;
+use crate::models::user::UserInfo;
+use crate::search::highlight;
+use sqlx::mysql::MySqlArguments;
+use sqlx::query::Query;
+use sqlx::{MySql, MySqlPool, Row};
+use std::collections::HashMap;
+use tracing::error;
+
+// 摘要长度，字符数
+const SNIPPET_CHARS: usize = 120;
+
+// 每个月表的全文检索条件
+fn search_filters(table_name: &str, param: &ArticleQueryParams) -> String {
+    let mut query = format!(
+        r#"
+        FROM {} a
+        WHERE a.status = 'published'
+            AND MATCH(a.title, a.digest, a.content) AGAINST (? IN NATURAL LANGUAGE MODE)
+        "#,
+        table_name
+    );
+    if param.user_id.is_some() {
+        query += " AND a.user_detail_id = ?";
+    }
+    if param.tag_id.is_some() {
+        query += " AND EXISTS (SELECT 1 FROM article_tags_table bt WHERE bt.article_id = a.id AND bt.tag_id = ?)";
+    }
+    if param.catalogue_id.is_some() {
+        query += " AND EXISTS (SELECT 1 FROM article_catalogues_table ac WHERE ac.article_id = a.id AND ac.catalogue_id = ?)";
+    }
+    if param.from.is_some() {
+        query += " AND a.publish_at >= ?";
+    }
+    if param.to.is_some() {
+        query += " AND a.publish_at <= ?";
+    }
+    query
+}
+
+// 每个月表只取排在前面的 limit 条，不取正文，标题命中的权重更高
+fn search_subquery(table_name: &str, param: &ArticleQueryParams) -> String {
+    format!(
+        r#"
+        (SELECT a.id, a.title, a.slug, a.digest, a.user_detail_id, a.publish_at, a.updated_at,
+            '{}' AS table_name,
+            MATCH(a.title, a.digest, a.content) AGAINST (? IN NATURAL LANGUAGE MODE)
+                + 2 * MATCH(a.title) AGAINST (? IN NATURAL LANGUAGE MODE) AS score
+        {}
+        ORDER BY score DESC, a.publish_at DESC
+        LIMIT ?)
+        "#,
+        table_name,
+        search_filters(table_name, param)
+    )
+}
+
+// 按 search_filters 中占位符的顺序绑定参数，每个月表一组
+// branch_limit 为 None 时是计数查询，没有相关度和 LIMIT 的占位符
+fn bind_search<'q>(
+    mut query: Query<'q, MySql, MySqlArguments>,
+    param: &'q ArticleQueryParams,
+    tables: usize,
+    branch_limit: Option<i64>,
+) -> Query<'q, MySql, MySqlArguments> {
+    for _ in 0..tables {
+        if branch_limit.is_some() {
+            query = query.bind(&param.q).bind(&param.q);
+        }
+        query = query.bind(&param.q);
+        if let Some(user_id) = param.user_id {
+            query = query.bind(user_id);
+        }
+        if let Some(tag_id) = param.tag_id {
+            query = query.bind(tag_id);
+        }
+        if let Some(catalogue_id) = param.catalogue_id {
+            query = query.bind(catalogue_id);
+        }
+        if let Some(from) = param.from {
+            query = query.bind(from);
+        }
+        if let Some(to) = param.to {
+            query = query.bind(to);
+        }
+        if let Some(limit) = branch_limit {
+            query = query.bind(limit);
+        }
+    }
+    query
+}
+
+// 当前页文章的正文，按所在月表分组查询，只用于生成摘要
+async fn article_contents(
+    pool: &MySqlPool,
+    ids_by_table: &HashMap<String, Vec<i64>>,
+) -> Result<HashMap<i64, String>, AppError> {
+    let mut contents = HashMap::new();
+    for (table_name, ids) in ids_by_table {
+        let placeholders = vec!["?"; ids.len()].join(", ");
+        let query = format!(
+            r#"SELECT id, content FROM {} WHERE id IN ({})"#,
+            table_name, placeholders
+        );
+        let mut query = sqlx::query(&query);
+        for id in ids {
+            query = query.bind(id);
+        }
+        let rows = query.fetch_all(pool).await.map_err(|e| {
+            error!("get search result contents failed: {:?}", e);
+            AppError::InternalError
+        })?;
+        for row in rows {
+            contents.insert(row.get::<i64, _>("id"), row.get::<String, _>("content"));
+        }
+    }
+    Ok(contents)
+}
+
+// 全文检索已发布的文章，返回总数和当前页，按相关度排序
+pub async fn search_articles_db(
+    pool: &MySqlPool,
+    param: &ArticleQueryParams,
+    terms: &[String],
+    limit: i32,
+    offset: i64,
+) -> Result<(i64, Vec<ArticleSearchHit>), AppError> {
+    let tables = list_article_tables(pool).await?;
+    if tables.is_empty() {
+        return Ok((0, Vec::new()));
+    }
+
+    let count_source = tables
+        .iter()
+        .map(|t| format!("SELECT a.id {}", search_filters(t, param)))
+        .collect::<Vec<_>>()
+        .join(" UNION ALL ");
+    let count_query = format!(r#"SELECT COUNT(*) AS total FROM ({}) s"#, count_source);
+    let total: i64 = bind_search(sqlx::query(&count_query), param, tables.len(), None)
+        .fetch_one(pool)
+        .await
+        .map_err(|e| {
+            error!("count search results failed: {:?}", e);
+            AppError::InternalError
+        })?
+        .get("total");
+    if offset >= total {
+        return Ok((total, Vec::new()));
+    }
+
+    // 当前页的文章一定在某个月表的前 offset + limit 条中
+    let source = tables
+        .iter()
+        .map(|t| search_subquery(t, param))
+        .collect::<Vec<_>>()
+        .join(" UNION ALL ");
+    let query = format!(
+        r#"
+        SELECT
+            s.id, s.title, s.slug, s.digest, s.publish_at, s.updated_at, s.table_name, s.score,
+            u.id AS author_id,
+            u.nickname AS author_nickname,
+            u.avatar AS author_avatar,
+            (SELECT GROUP_CONCAT(t.tag ORDER BY t.tag SEPARATOR ', ')
+                FROM article_tags_table bt JOIN tags_table t ON bt.tag_id = t.id
+                WHERE bt.article_id = s.id) AS tags
+        FROM ({}) s
+        LEFT JOIN user_detail_table u ON s.user_detail_id = u.id
+        ORDER BY s.score DESC, s.publish_at DESC
+        LIMIT ? OFFSET ?
+        "#,
+        source
+    );
+    let rows = bind_search(sqlx::query(&query), param, tables.len(), Some(offset + limit as i64))
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| {
+            error!("search articles failed: {:?}", e);
+            AppError::InternalError
+        })?;
+
+    let mut ids_by_table: HashMap<String, Vec<i64>> = HashMap::new();
+    for row in &rows {
+        ids_by_table
+            .entry(row.get::<String, _>("table_name"))
+            .or_default()
+            .push(row.get::<i64, _>("id"));
+    }
+    let contents = article_contents(pool, &ids_by_table).await?;
+
+    let hits = rows
+        .into_iter()
+        .map(|row| {
+            let id: i64 = row.get("id");
+            let title: String = row.get("title");
+            let tags_str: Option<String> = row.get("tags");
+            let content = contents.get(&id).map(String::as_str).unwrap_or_default();
+            ArticleSearchHit {
+                id,
+                title_highlight: highlight(&title, terms, usize::MAX),
+                title,
+                slug: row.get::<Option<String>, _>("slug"),
+                digest: row.get::<String, _>("digest"),
+                snippet: highlight(&plain_text(content), terms, SNIPPET_CHARS),
+                score: row.get::<f64, _>("score"),
+                tags: tags_str
+                    .unwrap_or_default()
+                    .split(", ")
+                    .filter(|t| !t.is_empty())
+                    .map(String::from)
+                    .collect(),
+                author: UserInfo {
+                    id: row.get::<Option<i64>, _>("author_id").unwrap_or_default(),
+                    nickname: row.get::<Option<String>, _>("author_nickname"),
+                    avatar: row.get::<Option<String>, _>("author_avatar"),
+                },
+                publish_at: row.get("publish_at"),
+                updated_at: row.get("updated_at"),
+            }
+        })
+        .collect();
+    Ok((total, hits))
+}
+
+// LIKE 中的 % 和 _ 按普通字符匹配
+fn like_pattern(q: &str) -> String {
+    let escaped = q
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{}%", escaped)
+}
+
+pub async fn search_tags_db(pool: &MySqlPool, q: &str) -> Result<Vec<Tag>, AppError> {
+    let tags = sqlx::query_as::<_, Tag>(
+        r#"SELECT id, tag FROM tags_table WHERE tag LIKE ? ORDER BY CHAR_LENGTH(tag), id LIMIT 10"#,
+    )
+    .bind(like_pattern(q))
+    .fetch_all(pool)
+    .await
+    .map_err(|e| {
+        error!("search tags failed: {:?}", e);
+        AppError::InternalError
+    })?;
+    Ok(tags)
+}
+
+pub async fn search_catalogues_db(pool: &MySqlPool, q: &str) -> Result<Vec<Catalogue>, AppError> {
+    let pattern = like_pattern(q);
+    let catalogues = sqlx::query_as::<_, Catalogue>(
+        r#"
+        SELECT id, catalogue, info FROM catalogues_table
+        WHERE catalogue LIKE ? OR info LIKE ?
+        ORDER BY catalogue LIKE ? DESC, id
+        LIMIT 10
+        "#,
+    )
+    .bind(&pattern)
+    .bind(&pattern)
+    .bind(&pattern)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| {
+        error!("search catalogues failed: {:?}", e);
+        AppError::InternalError
+    })?;
+    Ok(catalogues)
+}
@@ -0,0 +1,192 @@
+use crate::error::AppError;
+use crate::models::session::*;
+use sqlx::MySqlPool;
+use tracing::{debug, error};
+
+// 同一个 session 重复登录时更新原来的记录，不再新增
+pub async fn upsert_session_db(
+    pool: &MySqlPool,
+    id: &str,
+    user_detail_id: i64,
+    user_agent: Option<&str>,
+    ip: &str,
+    auth_sid: Option<&str>,
+    timeout_hours: i64,
+) -> Result<(), AppError> {
+    sqlx::query(
+        r#"
+        INSERT INTO user_sessions_table (id, user_detail_id, user_agent, ip, auth_sid, expires_at)
+        VALUES (?, ?, ?, ?, ?, DATE_ADD(NOW(), INTERVAL ? HOUR))
+        ON DUPLICATE KEY UPDATE
+            user_agent = VALUES(user_agent),
+            ip = VALUES(ip),
+            auth_sid = VALUES(auth_sid),
+            expires_at = VALUES(expires_at),
+            last_seen_at = NOW()
+        "#,
+    )
+    .bind(id)
+    .bind(user_detail_id)
+    .bind(user_agent)
+    .bind(ip)
+    .bind(auth_sid)
+    .bind(timeout_hours)
+    .execute(pool)
+    .await
+    .map_err(|e| {
+        error!("create session failed: {:?}", e);
+        AppError::InternalError
+    })?;
+    debug!("session registered for user {}", user_detail_id);
+    Ok(())
+}
+
+// 未撤销且未过期的 session 对应的用户
+pub async fn get_active_session_user_db(
+    pool: &MySqlPool,
+    id: &str,
+) -> Result<Option<i64>, AppError> {
+    let user_detail_id = sqlx::query_scalar::<_, i64>(
+        r#"
+        SELECT user_detail_id FROM user_sessions_table
+        WHERE id = ? AND revoked_at IS NULL AND expires_at > NOW()
+        "#,
+    )
+    .bind(id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(user_detail_id)
+}
+
+// 更新最后活动时间并延长有效期，一分钟内的请求不重复更新
+pub async fn touch_session_db(pool: &MySqlPool, id: &str, timeout_hours: i64) -> Result<(), AppError> {
+    sqlx::query(
+        r#"
+        UPDATE user_sessions_table
+        SET last_seen_at = NOW(), expires_at = DATE_ADD(NOW(), INTERVAL ? HOUR)
+        WHERE id = ? AND last_seen_at < DATE_SUB(NOW(), INTERVAL 1 MINUTE)
+        "#,
+    )
+    .bind(timeout_hours)
+    .bind(id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn list_sessions_db(
+    pool: &MySqlPool,
+    user_detail_id: i64,
+) -> Result<Vec<UserSessionRecord>, AppError> {
+    let sessions = sqlx::query_as::<_, UserSessionRecord>(
+        r#"
+        SELECT id, user_agent, ip, created_at, last_seen_at
+        FROM user_sessions_table
+        WHERE user_detail_id = ? AND revoked_at IS NULL AND expires_at > NOW()
+        ORDER BY last_seen_at DESC
+        "#,
+    )
+    .bind(user_detail_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| {
+        error!("list sessions failed: {:?}", e);
+        AppError::InternalError
+    })?;
+    Ok(sessions)
+}
+
+// 只能撤销自己的 session
+pub async fn revoke_session_db(
+    pool: &MySqlPool,
+    user_detail_id: i64,
+    id: &str,
+) -> Result<(), AppError> {
+    let res = sqlx::query(
+        r#"
+        UPDATE user_sessions_table SET revoked_at = NOW()
+        WHERE id = ? AND user_detail_id = ? AND revoked_at IS NULL
+        "#,
+    )
+    .bind(id)
+    .bind(user_detail_id)
+    .execute(pool)
+    .await?;
+    match res.rows_affected() {
+        0 => Err(AppError::RequestNotFound),
+        _ => Ok(()),
+    }
+}
+
+// 认证服务上已撤销的登录对应的 session
+pub async fn revoke_auth_sessions_db(pool: &MySqlPool, auth_sids: &[String]) -> Result<u64, AppError> {
+    if auth_sids.is_empty() {
+        return Ok(0);
+    }
+    let query = format!(
+        r#"
+        UPDATE user_sessions_table SET revoked_at = NOW()
+        WHERE auth_sid IN ({}) AND revoked_at IS NULL
+        "#,
+        vec!["?"; auth_sids.len()].join(", ")
+    );
+    let mut query = sqlx::query(&query);
+    for sid in auth_sids {
+        query = query.bind(sid);
+    }
+    let res = query.execute(pool).await.map_err(|e| {
+        error!("revoke auth sessions failed: {:?}", e);
+        AppError::InternalError
+    })?;
+    Ok(res.rows_affected())
+}
+
+// 删除已过期或已撤销超过 keep_days 天的登记，返回删除的条数
+pub async fn delete_stale_sessions_db(pool: &MySqlPool, keep_days: i64) -> Result<u64, AppError> {
+    let res = sqlx::query(
+        r#"
+        DELETE FROM user_sessions_table
+        WHERE expires_at < DATE_SUB(NOW(), INTERVAL ? DAY)
+            OR revoked_at < DATE_SUB(NOW(), INTERVAL ? DAY)
+        "#,
+    )
+    .bind(keep_days)
+    .bind(keep_days)
+    .execute(pool)
+    .await
+    .map_err(|e| {
+        error!("delete stale sessions failed: {:?}", e);
+        AppError::InternalError
+    })?;
+    Ok(res.rows_affected())
+}
+
+// 最近 minutes 分钟内有活动的 session
+pub async fn online_users_db(pool: &MySqlPool, minutes: i64) -> Result<Vec<OnlineUser>, AppError> {
+    let users = sqlx::query_as::<_, OnlineUser>(
+        r#"
+        SELECT
+            s.id AS session_id,
+            s.user_detail_id AS user_detail_id,
+            u.username AS username,
+            b.nickname AS nickname,
+            s.user_agent AS user_agent,
+            s.ip AS ip,
+            s.last_seen_at AS last_seen_at
+        FROM user_sessions_table s
+        JOIN user_detail_table b ON b.id = s.user_detail_id
+        JOIN user_table u ON u.id = b.user_id
+        WHERE s.revoked_at IS NULL AND s.expires_at > NOW()
+            AND s.last_seen_at > DATE_SUB(NOW(), INTERVAL ? MINUTE)
+        ORDER BY s.last_seen_at DESC
+        "#,
+    )
+    .bind(minutes)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| {
+        error!("get online users failed: {:?}", e);
+        AppError::InternalError
+    })?;
+    Ok(users)
+}
@@ -0,0 +1,65 @@
+use crate::dbs::partition_db::articles_source;
+use crate::error::AppError;
+use crate::models::sitemap::SitemapArticle;
+use chrono::{DateTime, Utc};
+use sqlx::{MySqlPool, Row};
+use tracing::error;
+
+fn parse_ids(ids: Option<String>) -> Vec<i64> {
+    ids.unwrap_or_default()
+        .split(',')
+        .filter_map(|id| id.trim().parse().ok())
+        .collect()
+}
+
+// since 为空时查询全部已发布的文章；否则查询 since 之后修改过的文章以及 ids 中的文章，包含未发布的
+pub async fn get_sitemap_articles_db(
+    pool: &MySqlPool,
+    since: Option<DateTime<Utc>>,
+    ids: &[i64],
+) -> Result<Vec<SitemapArticle>, AppError> {
+    let article_table_name = articles_source(pool).await?;
+    let condition = match since {
+        None => "b.status = 'published'".to_string(),
+        Some(_) if ids.is_empty() => "b.updated_at >= ?".to_string(),
+        Some(_) => format!(
+            "(b.updated_at >= ? OR b.id IN ({}))",
+            vec!["?"; ids.len()].join(", ")
+        ),
+    };
+    let query = format!(
+        r#"
+        SELECT
+            b.id, b.slug, b.status, b.updated_at, b.user_detail_id,
+            (SELECT GROUP_CONCAT(bt.tag_id) FROM article_tags_table bt WHERE bt.article_id = b.id) AS tag_ids,
+            (SELECT GROUP_CONCAT(ac.catalogue_id) FROM article_catalogues_table ac WHERE ac.article_id = b.id) AS catalogue_ids
+        FROM {} b
+        WHERE {}
+        "#,
+        article_table_name, condition
+    );
+    let mut query = sqlx::query(&query);
+    if let Some(since) = since {
+        query = query.bind(since);
+        for id in ids {
+            query = query.bind(id);
+        }
+    }
+    let rows = query.fetch_all(pool).await.map_err(|e| {
+        error!("get sitemap articles failed: {:?}", e);
+        AppError::InternalError
+    })?;
+    let articles = rows
+        .into_iter()
+        .map(|row| SitemapArticle {
+            id: row.get("id"),
+            slug: row.get("slug"),
+            published: row.get::<String, _>("status") == "published",
+            updated_at: row.get("updated_at"),
+            user_detail_id: row.get("user_detail_id"),
+            tag_ids: parse_ids(row.get("tag_ids")),
+            catalogue_ids: parse_ids(row.get("catalogue_ids")),
+        })
+        .collect();
+    Ok(articles)
+}
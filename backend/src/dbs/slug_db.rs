@@ -0,0 +1,176 @@
+use crate::dbs::partition_db::{article_table_by_id, list_article_tables};
+use crate::error::AppError;
+use crate::models::article::ArticleStatus;
+use crate::slug::slugify;
+use sqlx::{MySqlConnection, MySqlPool, Row};
+use tracing::{debug, error, info};
+
+// 同一个 slug 最多尝试的后缀数量
+const MAX_SLUG_SUFFIX: u32 = 1000;
+
+fn db_error(action: &str, e: sqlx::Error) -> AppError {
+    error!("{} failed: {:?}", action, e);
+    AppError::InternalError
+}
+
+// 把 slug 设为文章当前的 slug，已发布文章之前的 slug 保留用于跳转
+// 未发布的文章没有对外的链接，之前的 slug 直接释放，避免草稿反复改标题占用 slug
+// exact 为 false 时 slug 被其他文章占用则依次尝试 slug-2、slug-3
+pub async fn claim_slug_db(
+    conn: &mut MySqlConnection,
+    article_table_name: &str,
+    article_id: i64,
+    base: &str,
+    exact: bool,
+) -> Result<String, AppError> {
+    for n in 1..=MAX_SLUG_SUFFIX {
+        let slug = if n == 1 {
+            base.to_string()
+        } else {
+            format!("{}-{}", base, n)
+        };
+        let res = sqlx::query(
+            r#"INSERT IGNORE INTO article_slugs_table (slug, article_id, is_current) VALUES (?, ?, FALSE)"#,
+        )
+        .bind(&slug)
+        .bind(article_id)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| db_error("insert article slug", e))?;
+        if res.rows_affected() == 0 {
+            // 已登记过：属于这篇文章时重新启用，否则换下一个
+            let owner = sqlx::query_scalar::<_, i64>(
+                r#"SELECT article_id FROM article_slugs_table WHERE slug = ?"#,
+            )
+            .bind(&slug)
+            .fetch_one(&mut *conn)
+            .await
+            .map_err(|e| db_error("get article slug owner", e))?;
+            if owner != article_id {
+                if exact {
+                    return Err(AppError::SlugAlreadyExist);
+                }
+                continue;
+            }
+        }
+        let query = format!(r#"SELECT status FROM {} WHERE id = ?"#, article_table_name);
+        let status = sqlx::query_scalar::<_, String>(&query)
+            .bind(article_id)
+            .fetch_optional(&mut *conn)
+            .await
+            .map_err(|e| db_error("get article status", e))?
+            .ok_or(AppError::ArticleNotFound)?;
+        if status != ArticleStatus::Published.as_str() {
+            sqlx::query(
+                r#"DELETE FROM article_slugs_table WHERE article_id = ? AND is_current = TRUE AND slug <> ?"#,
+            )
+            .bind(article_id)
+            .bind(&slug)
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| db_error("release article slug", e))?;
+        }
+        sqlx::query(r#"UPDATE article_slugs_table SET is_current = (slug = ?) WHERE article_id = ?"#)
+            .bind(&slug)
+            .bind(article_id)
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| db_error("update current article slug", e))?;
+        // 显式写入 updated_at，修改 slug 不算修改文章内容
+        let query = format!(
+            r#"UPDATE {} SET slug = ?, updated_at = updated_at WHERE id = ?"#,
+            article_table_name
+        );
+        sqlx::query(&query)
+            .bind(&slug)
+            .bind(article_id)
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| db_error("update article slug", e))?;
+        debug!("article {} slug set to {}", article_id, slug);
+        return Ok(slug);
+    }
+    error!("no free slug for article {} from {}", article_id, base);
+    Err(AppError::InternalError)
+}
+
+pub async fn set_article_slug_db(
+    pool: &MySqlPool,
+    article_id: i64,
+    slug: &str,
+    exact: bool,
+) -> Result<String, AppError> {
+    let article_table_name = article_table_by_id(pool, article_id).await?;
+    let mut conn = pool
+        .begin()
+        .await
+        .map_err(|e| db_error("begin transaction", e))?;
+    let slug = claim_slug_db(&mut conn, &article_table_name, article_id, slug, exact).await?;
+    conn.commit()
+        .await
+        .map_err(|e| db_error("commit transaction", e))?;
+    Ok(slug)
+}
+
+// 标题修改后，未发布的文章重新生成 slug；已发布的文章保持不变，避免链接变化
+pub async fn refresh_draft_slug_db(pool: &MySqlPool, article_id: i64) -> Result<(), AppError> {
+    let article_table_name = article_table_by_id(pool, article_id).await?;
+    let query = format!(
+        r#"SELECT title, status FROM {} WHERE id = ?"#,
+        article_table_name
+    );
+    let row = sqlx::query(&query)
+        .bind(article_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| db_error("get article title", e))?
+        .ok_or(AppError::ArticleNotFound)?;
+    let status: String = row.get("status");
+    if status != "published" {
+        let title: String = row.get("title");
+        set_article_slug_db(pool, article_id, &slugify(&title), false).await?;
+    }
+    Ok(())
+}
+
+// slug 对应的文章 id 以及是否为当前的 slug
+pub async fn find_slug_db(pool: &MySqlPool, slug: &str) -> Result<(i64, bool), AppError> {
+    let row = sqlx::query(r#"SELECT article_id, is_current FROM article_slugs_table WHERE slug = ?"#)
+        .bind(slug)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| db_error("find article slug", e))?
+        .ok_or(AppError::ArticleNotFound)?;
+    Ok((row.get("article_id"), row.get("is_current")))
+}
+
+pub async fn get_current_slug_db(pool: &MySqlPool, article_id: i64) -> Result<String, AppError> {
+    sqlx::query_scalar::<_, String>(
+        r#"SELECT slug FROM article_slugs_table WHERE article_id = ? AND is_current = TRUE"#,
+    )
+    .bind(article_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| db_error("get current article slug", e))?
+    .ok_or(AppError::ArticleNotFound)
+}
+
+// 启动时为还没有 slug 的文章生成 slug
+pub async fn backfill_article_slugs_db(pool: &MySqlPool) -> Result<(), AppError> {
+    for table_name in list_article_tables(pool).await? {
+        let query = format!(r#"SELECT id, title FROM {} WHERE slug IS NULL"#, table_name);
+        let rows = sqlx::query(&query)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| db_error("list articles without slug", e))?;
+        for row in &rows {
+            let article_id: i64 = row.get("id");
+            let title: String = row.get("title");
+            set_article_slug_db(pool, article_id, &slugify(&title), false).await?;
+        }
+        if !rows.is_empty() {
+            info!("generated slugs for {} articles in {}", rows.len(), table_name);
+        }
+    }
+    Ok(())
+}
@@ -163,7 +163,8 @@ pub async fn get_session_user_by_username_db(
             u.username AS username, 
             u.email AS email, 
             b.nickname AS nickname, 
-            b.avatar AS avatar
+            b.avatar AS avatar,
+            b.role AS role
         FROM 
             user_table u
         JOIN 
@@ -190,6 +191,34 @@ pub async fn get_session_user_by_username_db(
     }
 }
 
+pub async fn get_session_user_by_detail_id_db(
+    pool: &MySqlPool,
+    user_detail_id: i64,
+) -> Result<UserSession, AppError> {
+    sqlx::query_as::<_, UserSession>(
+        r#"
+        SELECT
+            b.id AS user_detail_id,
+            u.username AS username,
+            u.email AS email,
+            b.nickname AS nickname,
+            b.avatar AS avatar,
+            b.role AS role
+        FROM user_table u
+        JOIN user_detail_table b ON u.id = b.user_id
+        WHERE b.id = ?
+        "#,
+    )
+    .bind(user_detail_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| {
+        error!("get session user failed: {:?}", e);
+        AppError::InternalError
+    })?
+    .ok_or(AppError::UserNotFound)
+}
+
 pub async fn get_resume_by_userid_db(
     pool: &MySqlPool,
     user_detail_id: i64,
@@ -324,3 +353,27 @@ pub async fn update_userdetail_db(
         }
     }
 }
+
+pub async fn update_user_role_db(
+    pool: &MySqlPool,
+    user_detail_id: i64,
+    role: &str,
+) -> Result<(), AppError> {
+    let res = sqlx::query(r#"UPDATE user_detail_table SET role = ? WHERE id = ?"#)
+        .bind(role)
+        .bind(user_detail_id)
+        .execute(pool)
+        .await
+        .map_err(|e| {
+            error!("update user role failed: {:?}", e);
+            AppError::InternalError
+        })?;
+
+    match res.rows_affected() {
+        0 => Err(AppError::UserNotFound),
+        _ => {
+            debug!("update user role success");
+            Ok(())
+        }
+    }
+}
@@ -36,6 +36,16 @@ pub enum AppError {
     CatalogueNotFound,
     #[error("Catalogue cannot delete, it has articles")]
     CatalogueCannotDelete,
+    #[error("Article not found")]
+    ArticleNotFound,
+    #[error("Permission denied")]
+    PermissionDenied,
+    #[error("Invalid request")]
+    InvalidRequest,
+    #[error("Invalid status transition")]
+    InvalidStatusTransition,
+    #[error("Slug already exist")]
+    SlugAlreadyExist,
 }
 
 impl IntoResponse for AppError {
@@ -66,6 +76,13 @@ impl IntoResponse for AppError {
                 StatusCode::FORBIDDEN,
                 "Catalogue cannot delete, it has articles",
             ),
+            AppError::ArticleNotFound => (StatusCode::NOT_FOUND, "Article not found"),
+            AppError::PermissionDenied => (StatusCode::FORBIDDEN, "Permission denied"),
+            AppError::InvalidRequest => (StatusCode::BAD_REQUEST, "Invalid request"),
+            AppError::InvalidStatusTransition => {
+                (StatusCode::CONFLICT, "Invalid status transition")
+            }
+            AppError::SlugAlreadyExist => (StatusCode::CONFLICT, "Slug already exist"),
         };
 
         let body = Json(json!({ "error": error_message }));
@@ -0,0 +1,220 @@
+use crate::markdown::render_markdown;
+use crate::models::feed::FeedArticle;
+use crate::utils::escape_markup;
+use chrono::{DateTime, SecondsFormat, Utc};
+use std::borrow::Cow;
+use std::fmt::Write;
+
+// 订阅的基本信息，link 为前端对应的页面
+pub struct FeedChannel {
+    pub title: String,
+    pub description: String,
+    pub link: String,
+    pub updated: DateTime<Utc>,
+}
+
+// 前端的文章页面，使用文章的 slug，还没有 slug 的文章使用 id
+pub fn article_url(site: &str, article_id: i64, slug: Option<&str>) -> String {
+    let site = site.trim_end_matches('/');
+    match slug {
+        Some(slug) => format!("{}/posts/{}", site, slug),
+        None => format!("{}/posts/{}", site, article_id),
+    }
+}
+
+// 全文订阅的正文，优先使用缓存的渲染结果
+fn article_html(article: &FeedArticle) -> Cow<'_, str> {
+    match &article.content_html {
+        Some(html) => Cow::Borrowed(html),
+        None => Cow::Owned(render_markdown(&article.content).html),
+    }
+}
+
+fn rfc3339(time: &DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+// RSS 2.0，全文模式下正文放在 content:encoded 中
+pub fn render_rss(channel: &FeedChannel, articles: &[FeedArticle], site: &str, full: bool) -> String {
+    let mut xml = String::new();
+    xml.push_str(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    xml.push('\n');
+    xml.push_str(r#"<rss version="2.0" xmlns:content="http://purl.org/rss/1.0/modules/content/" xmlns:dc="http://purl.org/dc/elements/1.1/">"#);
+    xml.push_str("<channel>");
+    let _ = write!(
+        xml,
+        "<title>{}</title><link>{}</link><description>{}</description><lastBuildDate>{}</lastBuildDate>",
+        escape_markup(&channel.title),
+        escape_markup(&channel.link),
+        escape_markup(&channel.description),
+        channel.updated.to_rfc2822()
+    );
+    for article in articles {
+        let url = escape_markup(&article_url(site, article.id, article.slug.as_deref()));
+        let _ = write!(
+            xml,
+            "<item><title>{}</title><link>{}</link><guid isPermaLink=\"true\">{}</guid>",
+            escape_markup(&article.title),
+            url,
+            url
+        );
+        if let Some(publish_at) = &article.publish_at {
+            let _ = write!(xml, "<pubDate>{}</pubDate>", publish_at.to_rfc2822());
+        }
+        if let Some(author) = &article.author {
+            let _ = write!(xml, "<dc:creator>{}</dc:creator>", escape_markup(author));
+        }
+        for tag in &article.tags {
+            let _ = write!(xml, "<category>{}</category>", escape_markup(tag));
+        }
+        let _ = write!(xml, "<description>{}</description>", escape_markup(&article.digest));
+        if full {
+            let _ = write!(
+                xml,
+                "<content:encoded>{}</content:encoded>",
+                escape_markup(&article_html(article))
+            );
+        }
+        xml.push_str("</item>");
+    }
+    xml.push_str("</channel></rss>\n");
+    xml
+}
+
+// Atom 1.0，全文模式下正文放在 content 中，否则只有 summary
+pub fn render_atom(channel: &FeedChannel, articles: &[FeedArticle], site: &str, full: bool) -> String {
+    let mut xml = String::new();
+    xml.push_str(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    xml.push('\n');
+    xml.push_str(r#"<feed xmlns="http://www.w3.org/2005/Atom">"#);
+    let _ = write!(
+        xml,
+        "<title>{}</title><link rel=\"alternate\" href=\"{}\"/><id>{}</id><updated>{}</updated>",
+        escape_markup(&channel.title),
+        escape_markup(&channel.link),
+        escape_markup(&channel.link),
+        rfc3339(&channel.updated)
+    );
+    if !channel.description.is_empty() {
+        let _ = write!(xml, "<subtitle>{}</subtitle>", escape_markup(&channel.description));
+    }
+    // 没有作者的文章使用订阅的作者
+    let _ = write!(xml, "<author><name>{}</name></author>", escape_markup(&channel.title));
+    for article in articles {
+        let url = escape_markup(&article_url(site, article.id, article.slug.as_deref()));
+        let _ = write!(
+            xml,
+            "<entry><title>{}</title><link rel=\"alternate\" href=\"{}\"/><id>{}</id><updated>{}</updated>",
+            escape_markup(&article.title),
+            url,
+            url,
+            rfc3339(&article.updated_at)
+        );
+        if let Some(publish_at) = &article.publish_at {
+            let _ = write!(xml, "<published>{}</published>", rfc3339(publish_at));
+        }
+        if let Some(author) = &article.author {
+            let _ = write!(xml, "<author><name>{}</name></author>", escape_markup(author));
+        }
+        for tag in &article.tags {
+            let _ = write!(xml, "<category term=\"{}\"/>", escape_markup(tag));
+        }
+        let _ = write!(xml, "<summary>{}</summary>", escape_markup(&article.digest));
+        if full {
+            let _ = write!(
+                xml,
+                "<content type=\"html\">{}</content>",
+                escape_markup(&article_html(article))
+            );
+        }
+        xml.push_str("</entry>");
+    }
+    xml.push_str("</feed>\n");
+    xml
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn channel() -> FeedChannel {
+        FeedChannel {
+            title: "Blog & Notes".to_string(),
+            description: "<描述>".to_string(),
+            link: "https://example.com/".to_string(),
+            updated: Utc.with_ymd_and_hms(2024, 5, 2, 8, 0, 0).unwrap(),
+        }
+    }
+
+    fn article(content_html: Option<&str>) -> FeedArticle {
+        FeedArticle {
+            id: 7,
+            title: "Rust <异步>".to_string(),
+            slug: Some("rust-yi-bu".to_string()),
+            digest: "摘要 & more".to_string(),
+            content: "# 标题\n\n**正文**".to_string(),
+            content_html: content_html.map(String::from),
+            author: Some("Tom".to_string()),
+            tags: vec!["rust".to_string(), "c++".to_string()],
+            publish_at: Some(Utc.with_ymd_and_hms(2024, 5, 1, 12, 30, 0).unwrap()),
+            updated_at: Utc.with_ymd_and_hms(2024, 5, 2, 8, 0, 0).unwrap(),
+        }
+    }
+
+    #[test]
+    fn article_url_prefers_slug() {
+        assert_eq!(
+            article_url("https://example.com/", 7, Some("rust-yi-bu")),
+            "https://example.com/posts/rust-yi-bu"
+        );
+        assert_eq!(article_url("https://example.com", 7, None), "https://example.com/posts/7");
+    }
+
+    #[test]
+    fn rss_digest() {
+        let xml = render_rss(&channel(), &[article(None)], "https://example.com", false);
+        assert!(xml.starts_with(r#"<?xml version="1.0" encoding="UTF-8"?>"#));
+        assert!(xml.contains("<title>Blog &amp; Notes</title>"));
+        assert!(xml.contains("<description>&lt;描述&gt;</description>"));
+        assert!(xml.contains("<lastBuildDate>Thu, 2 May 2024 08:00:00 +0000</lastBuildDate>"));
+        assert!(xml.contains("<title>Rust &lt;异步&gt;</title>"));
+        assert!(xml.contains(
+            r#"<guid isPermaLink="true">https://example.com/posts/rust-yi-bu</guid>"#
+        ));
+        assert!(xml.contains("<pubDate>Wed, 1 May 2024 12:30:00 +0000</pubDate>"));
+        assert!(xml.contains("<dc:creator>Tom</dc:creator>"));
+        assert!(xml.contains("<category>c++</category>"));
+        assert!(xml.contains("<description>摘要 &amp; more</description>"));
+        assert!(!xml.contains("content:encoded>"));
+        assert!(xml.ends_with("</channel></rss>\n"));
+    }
+
+    #[test]
+    fn rss_full_uses_cached_html() {
+        let xml = render_rss(&channel(), &[article(Some("<p>cached</p>"))], "https://example.com", true);
+        assert!(xml.contains("<content:encoded>&lt;p&gt;cached&lt;/p&gt;</content:encoded>"));
+    }
+
+    #[test]
+    fn atom_full_renders_without_cache() {
+        let xml = render_atom(&channel(), &[article(None)], "https://example.com", true);
+        assert!(xml.contains(r#"<feed xmlns="http://www.w3.org/2005/Atom">"#));
+        assert!(xml.contains("<updated>2024-05-02T08:00:00Z</updated>"));
+        assert!(xml.contains("<subtitle>&lt;描述&gt;</subtitle>"));
+        assert!(xml.contains(
+            r#"<link rel="alternate" href="https://example.com/posts/rust-yi-bu"/><id>https://example.com/posts/rust-yi-bu</id>"#
+        ));
+        assert!(xml.contains("<published>2024-05-01T12:30:00Z</published>"));
+        assert!(xml.contains(r#"<category term="rust"/>"#));
+        assert!(xml.contains("<summary>摘要 &amp; more</summary>"));
+        assert!(xml.contains("&lt;strong&gt;正文&lt;/strong&gt;"));
+        assert!(xml.ends_with("</feed>\n"));
+    }
+
+    #[test]
+    fn atom_digest_without_content() {
+        let xml = render_atom(&channel(), &[article(Some("<p>cached</p>"))], "https://example.com", false);
+        assert!(!xml.contains("<content"));
+    }
+}
@@ -0,0 +1,81 @@
+use crate::access_token::{new_access_token, scopes_to_string};
+use crate::dbs::access_token_db::*;
+use crate::error::*;
+use crate::models::access_token::*;
+use crate::models::state::AppState;
+use crate::models::user::UserSession;
+use axum::extract::Path;
+use axum::extract::State;
+use axum::Extension;
+use axum::Json;
+use axum::{http::StatusCode, response::IntoResponse};
+use std::sync::Arc;
+use tracing::info;
+
+// 令牌只能在浏览器登录后管理，不能用令牌创建新的令牌
+fn require_browser_session(user: &UserSession) -> Result<(), AppError> {
+    if user.is_access_token() {
+        return Err(AppError::PermissionDenied);
+    }
+    Ok(())
+}
+
+// 当前用户的个人访问令牌
+pub async fn get_access_tokens(
+    app_state: State<Arc<AppState>>,
+    Extension(user): Extension<UserSession>,
+) -> Result<impl IntoResponse, AppError> {
+    require_browser_session(&user)?;
+    let tokens = list_access_tokens_db(&app_state.pool, user.user_detail_id).await?;
+    Ok((StatusCode::OK, Json(tokens)))
+}
+
+// 创建个人访问令牌，令牌只在这里返回一次
+pub async fn post_access_token(
+    app_state: State<Arc<AppState>>,
+    Extension(user): Extension<UserSession>,
+    Json(create): Json<AccessTokenCreate>,
+) -> Result<impl IntoResponse, AppError> {
+    require_browser_session(&user)?;
+    let name = create.name.trim();
+    let expires_in_days = create.expires_in_days.unwrap_or(30);
+    if name.is_empty()
+        || name.chars().count() > 100
+        || create.scopes.is_empty()
+        || !(1..=365).contains(&expires_in_days)
+    {
+        return Err(AppError::InvalidRequest);
+    }
+    let (token, prefix, hash) = new_access_token();
+    let created = create_access_token_db(
+        &app_state.pool,
+        user.user_detail_id,
+        name,
+        &prefix,
+        &hash,
+        &scopes_to_string(&create.scopes),
+        expires_in_days,
+    )
+    .await?;
+    info!("access token {} created by {}", created.id, user.username);
+    let res = AccessTokenCreated {
+        id: created.id,
+        name: created.name,
+        token,
+        scopes: create.scopes,
+        expires_at: created.expires_at,
+    };
+    Ok((StatusCode::CREATED, Json(res)))
+}
+
+// 撤销个人访问令牌
+pub async fn delete_access_token(
+    app_state: State<Arc<AppState>>,
+    Extension(user): Extension<UserSession>,
+    Path(token_id): Path<i64>,
+) -> Result<impl IntoResponse, AppError> {
+    require_browser_session(&user)?;
+    revoke_access_token_db(&app_state.pool, user.user_detail_id, token_id).await?;
+    info!("access token {} revoked by {}", token_id, user.username);
+    Ok(StatusCode::NO_CONTENT)
+}
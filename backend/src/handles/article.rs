@@ -1,42 +1,227 @@
 use crate::dbs::article_db::*;
+use crate::dbs::slug_db::*;
 use crate::error::*;
 use crate::models::article::*;
 use crate::models::parameter::*;
+use crate::models::permission::{Role, Scope};
 use crate::models::state::*;
+use crate::models::user::UserSession;
+use crate::slug::is_valid_slug;
 use axum::extract::Path;
 use axum::extract::Query;
 use axum::extract::State;
+use axum::response::Redirect;
+use axum::Extension;
 use axum::Json;
 use axum::{http::StatusCode, response::IntoResponse, Router};
+use chrono::{DateTime, Utc};
 use std::sync::Arc;
 use tracing::{debug, error, info};
 
+// 检查状态变更是否允许，只有 editor 以上才能执行的变更返回 403
+fn check_transition(
+    user: &UserSession,
+    from: ArticleStatus,
+    to: ArticleStatus,
+) -> Result<(), AppError> {
+    let is_editor = user.role() >= Role::Editor;
+    if from.can_transition(to, is_editor) {
+        Ok(())
+    } else if from.can_transition(to, true) {
+        Err(AppError::PermissionDenied)
+    } else {
+        Err(AppError::InvalidStatusTransition)
+    }
+}
+
+// 新状态对应的发布时间：定时发布必须是将来的时间，立即发布使用当前时间
+fn status_publish_at(
+    status: ArticleStatus,
+    publish_at: Option<DateTime<Utc>>,
+) -> Result<Option<DateTime<Utc>>, AppError> {
+    match status {
+        ArticleStatus::Scheduled => match publish_at {
+            Some(at) if at > Utc::now() => Ok(Some(at)),
+            _ => Err(AppError::InvalidRequest),
+        },
+        ArticleStatus::Published => Ok(Some(Utc::now())),
+        _ => Ok(None),
+    }
+}
+
 // 创建新文章
 pub async fn post_article(
     app_state: State<Arc<AppState>>,
-    Json(article_create): Json<ArticleCreate>,
+    Extension(user): Extension<UserSession>,
+    Json(mut article_create): Json<ArticleCreate>,
 ) -> Result<impl IntoResponse, AppError> {
+    // 作者总是当前登录用户，不信任请求体中的 user_detail_id
+    user.require_scope(Scope::ArticlesWrite)?;
+    article_create.user_detail_id = user.user_detail_id;
+    if let Some(slug) = &article_create.slug {
+        if !is_valid_slug(slug) {
+            return Err(AppError::InvalidRequest);
+        }
+    }
+    // 新文章视为从草稿开始
+    if article_create.status != ArticleStatus::Draft {
+        check_transition(&user, ArticleStatus::Draft, article_create.status)?;
+    }
+    article_create.publish_at = status_publish_at(article_create.status, article_create.publish_at)?;
     debug!("article_create: {:?}", article_create);
-    post_article_db(&app_state.pool, &article_create).await?;
+    let article_id = post_article_db(&app_state.pool, &article_create).await?;
+    app_state.sitemap.article_changed(article_id);
     Ok(StatusCode::OK)
 }
 // 更新指定文章
 pub async fn put_article(
     app_state: State<Arc<AppState>>,
+    Extension(user): Extension<UserSession>,
     Path(article_id): Path<i64>,
     Json(article): Json<ArticleUpdate>,
 ) -> Result<impl IntoResponse, AppError> {
-    update_article_db(&app_state.pool, article_id, &article).await?;
+    user.require_scope(Scope::ArticlesWrite)?;
+    user.require_content_owner(get_article_owner_db(&app_state.pool, article_id).await?)?;
+    update_article_db(&app_state.pool, article_id, &article, user.user_detail_id).await?;
+    if article.title.is_some() {
+        refresh_draft_slug_db(&app_state.pool, article_id).await?;
+    }
+    app_state.sitemap.article_changed(article_id);
     Ok(StatusCode::OK)
 }
 
+// 已发布文章的详情，未发布的文章只能通过预览接口查看
+async fn published_article_detail(
+    app_state: &AppState,
+    article_id: i64,
+) -> Result<ArticleDetail, AppError> {
+    let article = get_article_detail_db(&app_state.pool, article_id).await?;
+    if article.status != ArticleStatus::Published.as_str() {
+        return Err(AppError::ArticleNotFound);
+    }
+    render_article_db(&app_state.pool, article).await
+}
+
 pub async fn get_article_detail(
     app_state: State<Arc<AppState>>,
     Path(article_id): Path<i64>,
 ) -> Result<impl IntoResponse, AppError> {
-    let articles_vec = get_article_detail_db(&app_state.pool, article_id).await?;
+    let detail = published_article_detail(&app_state, article_id).await?;
+    Ok((StatusCode::OK, Json(detail)))
+}
+
+// 按 slug 查询文章详情，旧的 slug 永久跳转到当前的 slug
+// 未发布的文章不跳转，避免通过旧链接得到草稿的 slug
+pub async fn get_article_by_slug(
+    app_state: State<Arc<AppState>>,
+    Path(slug): Path<String>,
+) -> Result<axum::response::Response, AppError> {
+    let (article_id, is_current) = find_slug_db(&app_state.pool, &slug).await?;
+    if !is_current {
+        let current = published_slug(&app_state, article_id).await?;
+        return Ok(Redirect::permanent(&format!("/api/articles/slug/{}", current)).into_response());
+    }
+    let detail = published_article_detail(&app_state, article_id).await?;
+    Ok((StatusCode::OK, Json(detail)).into_response())
+}
+
+// 已发布文章当前的 slug，未发布的文章按不存在处理
+async fn published_slug(app_state: &AppState, article_id: i64) -> Result<String, AppError> {
+    let (_, status) = get_article_state_db(&app_state.pool, article_id).await?;
+    if status != ArticleStatus::Published {
+        return Err(AppError::ArticleNotFound);
+    }
+    get_current_slug_db(&app_state.pool, article_id).await
+}
+
+// 文章 id 的永久链接，跳转到当前的 slug
+pub async fn get_article_permalink(
+    app_state: State<Arc<AppState>>,
+    Path(article_id): Path<i64>,
+) -> Result<impl IntoResponse, AppError> {
+    let slug = published_slug(&app_state, article_id).await?;
+    Ok(Redirect::permanent(&format!("/api/articles/slug/{}", slug)))
+}
+
+// 修改 slug，旧的 slug 继续跳转到新的 slug
+pub async fn post_article_slug(
+    app_state: State<Arc<AppState>>,
+    Extension(user): Extension<UserSession>,
+    Path(article_id): Path<i64>,
+    Json(update): Json<ArticleSlugUpdate>,
+) -> Result<impl IntoResponse, AppError> {
+    user.require_scope(Scope::ArticlesWrite)?;
+    user.require_content_owner(get_article_owner_db(&app_state.pool, article_id).await?)?;
+    if !is_valid_slug(&update.slug) {
+        return Err(AppError::InvalidRequest);
+    }
+    let slug = set_article_slug_db(&app_state.pool, article_id, &update.slug, true).await?;
+    info!("article {} slug changed to {} by {}", article_id, slug, user.username);
+    app_state.sitemap.article_changed(article_id);
+    Ok((StatusCode::OK, Json(ArticleSlugUpdate { slug })))
+}
+
+// 预览未发布的文章，作者本人或 editor 以上
+pub async fn get_article_preview(
+    app_state: State<Arc<AppState>>,
+    Extension(user): Extension<UserSession>,
+    Path(article_id): Path<i64>,
+) -> Result<impl IntoResponse, AppError> {
+    user.require_content_owner(get_article_owner_db(&app_state.pool, article_id).await?)?;
+    let article = get_article_detail_db(&app_state.pool, article_id).await?;
+    let detail = render_article_db(&app_state.pool, article).await?;
+    Ok((StatusCode::OK, Json(detail)))
+}
+
+// 当前用户的文章，可以按状态过滤
+pub async fn get_my_articles(
+    app_state: State<Arc<AppState>>,
+    Extension(user): Extension<UserSession>,
+    Query(param): Query<ArticleStatusParams>,
+) -> Result<impl IntoResponse, AppError> {
+    let page = PageParams {
+        page: param.page,
+        limit: param.limit,
+    };
+    let articles_vec =
+        get_articles_by_status_db(&app_state.pool, Some(user.user_detail_id), param.status, &page)
+            .await?;
     Ok((StatusCode::OK, Json(articles_vec)))
 }
+
+// 待审核的文章，editor 以上
+pub async fn get_review_articles(
+    app_state: State<Arc<AppState>>,
+    Extension(user): Extension<UserSession>,
+    Query(param): Query<PageParams>,
+) -> Result<impl IntoResponse, AppError> {
+    user.require_role(Role::Editor)?;
+    let articles_vec = get_articles_by_status_db(
+        &app_state.pool,
+        None,
+        Some(ArticleStatus::InReview),
+        &param,
+    )
+    .await?;
+    Ok((StatusCode::OK, Json(articles_vec)))
+}
+
+// 修改文章状态：提交审核、发布、定时发布、撤回、归档
+pub async fn post_article_status(
+    app_state: State<Arc<AppState>>,
+    Extension(user): Extension<UserSession>,
+    Path(article_id): Path<i64>,
+    Json(update): Json<ArticleStatusUpdate>,
+) -> Result<impl IntoResponse, AppError> {
+    user.require_scope(Scope::ArticlesWrite)?;
+    let (owner, status) = get_article_state_db(&app_state.pool, article_id).await?;
+    user.require_content_owner(owner)?;
+    check_transition(&user, status, update.status)?;
+    let publish_at = status_publish_at(update.status, update.publish_at)?;
+    update_article_status_db(&app_state.pool, article_id, update.status, publish_at).await?;
+    app_state.sitemap.article_changed(article_id);
+    Ok(StatusCode::OK)
+}
 // 获取用户的指定文章
 pub async fn get_user_article(
     app_state: State<Arc<AppState>>,
@@ -58,18 +243,29 @@ pub async fn get_tag_articles_info(
 // 删除指定文章
 pub async fn delete_article(
     app_state: State<Arc<AppState>>,
+    Extension(user): Extension<UserSession>,
     Path(article_id): Path<i64>,
 ) -> Result<impl IntoResponse, AppError> {
+    user.require_scope(Scope::ArticlesWrite)?;
+    user.require_content_owner(get_article_owner_db(&app_state.pool, article_id).await?)?;
     delete_article_db(&app_state.pool, article_id).await?;
+    app_state.sitemap.article_changed(article_id);
     Ok(StatusCode::OK)
 }
 
 pub async fn update_article(
     app_state: State<Arc<AppState>>,
+    Extension(user): Extension<UserSession>,
     Path(article_id): Path<i64>,
     Json(article_update): Json<ArticleUpdate>,
 ) -> Result<impl IntoResponse, AppError> {
-    update_article_db(&app_state.pool, article_id, &article_update).await?;
+    user.require_scope(Scope::ArticlesWrite)?;
+    user.require_content_owner(get_article_owner_db(&app_state.pool, article_id).await?)?;
+    update_article_db(&app_state.pool, article_id, &article_update, user.user_detail_id).await?;
+    if article_update.title.is_some() {
+        refresh_draft_slug_db(&app_state.pool, article_id).await?;
+    }
+    app_state.sitemap.article_changed(article_id);
     Ok(StatusCode::OK)
 }
 
@@ -1,12 +1,16 @@
 use crate::dbs::article_db::*;
 use crate::dbs::catalogue_db::*;
 use crate::error::*;
+use crate::models::article::ArticleStatus;
 use crate::models::catalogue::*;
 use crate::models::parameter::*;
+use crate::models::permission::Scope;
 use crate::models::state::AppState;
+use crate::models::user::UserSession;
 use axum::extract::Path;
 use axum::extract::Query;
 use axum::extract::State;
+use axum::Extension;
 use axum::Json;
 use axum::{http::StatusCode, response::IntoResponse, Router};
 use std::sync::Arc;
@@ -15,25 +19,34 @@ use tracing::{debug, error, info};
 // 创建新目录
 pub async fn post_catalogue(
     app_state: State<Arc<AppState>>,
-    Json(catalogue_create): Json<CatalogueCreate>,
+    Extension(user): Extension<UserSession>,
+    Json(mut catalogue_create): Json<CatalogueCreate>,
 ) -> Result<impl IntoResponse, AppError> {
+    user.require_scope(Scope::CataloguesWrite)?;
+    catalogue_create.user_detail_id = user.user_detail_id;
     post_catalogue_db(&app_state.pool, catalogue_create).await?;
     Ok(StatusCode::OK)
 }
 // 更新目录
 pub async fn post_update_catalogue(
     app_state: State<Arc<AppState>>,
+    Extension(user): Extension<UserSession>,
     Path(catalogue_id): Path<i64>,
     Json(catalogue_update): Json<CatalogueUpdate>,
 ) -> Result<impl IntoResponse, AppError> {
+    user.require_scope(Scope::CataloguesWrite)?;
+    user.require_content_owner(get_catalogue_owner_db(&app_state.pool, catalogue_id).await?)?;
     post_update_catalogue_db(&app_state.pool, catalogue_id, catalogue_update).await?;
     Ok(StatusCode::OK)
 }
 // 删除目录
 pub async fn delete_catalogue(
     app_state: State<Arc<AppState>>,
+    Extension(user): Extension<UserSession>,
     Path(catalogue_id): Path<i64>,
 ) -> Result<impl IntoResponse, AppError> {
+    user.require_scope(Scope::CataloguesWrite)?;
+    user.require_content_owner(get_catalogue_owner_db(&app_state.pool, catalogue_id).await?)?;
     delete_catalogue_db(&app_state.pool, catalogue_id).await?;
     Ok(StatusCode::OK)
 }
@@ -63,37 +76,59 @@ pub async fn get_catalogue_article_titles(
 //移除目录下的文章
 pub async fn delete_catalogue_article_by_id(
     app_state: State<Arc<AppState>>,
+    Extension(user): Extension<UserSession>,
     Path((catalogue_id, article_id)): Path<(i64, i64)>,
 ) -> Result<impl IntoResponse, AppError> {
+    user.require_scope(Scope::CataloguesWrite)?;
+    user.require_content_owner(get_catalogue_owner_db(&app_state.pool, catalogue_id).await?)?;
     delete_catalogue_article_one_by_id(&app_state.pool, article_id, catalogue_id).await?;
+    app_state.sitemap.article_changed(article_id);
     Ok(StatusCode::OK)
 }
 
 //移除目录下的所有文章
 pub async fn delete_catalogue_all_articles(
     app_state: State<Arc<AppState>>,
+    Extension(user): Extension<UserSession>,
     Path(catalogue_id): Path<i64>,
 ) -> Result<impl IntoResponse, AppError> {
-    delete_catalogue_article_all_by_id(&app_state.pool, catalogue_id).await?;
+    user.require_scope(Scope::CataloguesWrite)?;
+    user.require_content_owner(get_catalogue_owner_db(&app_state.pool, catalogue_id).await?)?;
+    for article_id in delete_catalogue_article_all_by_id(&app_state.pool, catalogue_id).await? {
+        app_state.sitemap.article_changed(article_id);
+    }
     Ok(StatusCode::OK)
 }
 
 //添加文章到目录
 pub async fn post_catalogue_article(
     app_state: State<Arc<AppState>>,
+    Extension(user): Extension<UserSession>,
     Json(parameter): Json<AddCatalogueArticle>,
-
 ) -> Result<impl IntoResponse, AppError> {
+    user.require_scope(Scope::CataloguesWrite)?;
+    let catalogue_id = parameter.catalogue_id as i64;
+    user.require_content_owner(get_catalogue_owner_db(&app_state.pool, catalogue_id).await?)?;
+    // 只能加入已发布的文章或自己的文章，否则会暴露别人未发布的文章
+    let (owner, status) = get_article_state_db(&app_state.pool, parameter.article_id as i64).await?;
+    if status != ArticleStatus::Published {
+        user.require_content_owner(owner)?;
+    }
     debug!("{:?}",parameter);
     post_article_to_catalogue(&app_state.pool, &parameter).await?;
+    app_state.sitemap.article_changed(parameter.article_id as i64);
     Ok(StatusCode::OK)
 }
 
 //更新目录下文章的排序
 pub async fn post_catalogue_article_sort(
     app_state: State<Arc<AppState>>,
+    Extension(user): Extension<UserSession>,
     Json(parameter): Json<AddCatalogueArticle>,
 ) -> Result<impl IntoResponse, AppError> {
+    user.require_scope(Scope::CataloguesWrite)?;
+    let catalogue_id = parameter.catalogue_id as i64;
+    user.require_content_owner(get_catalogue_owner_db(&app_state.pool, catalogue_id).await?)?;
     update_catalogue_article_sort_order_by_id(&app_state.pool, &parameter).await?;
     Ok(StatusCode::OK)
 }
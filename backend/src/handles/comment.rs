@@ -3,20 +3,48 @@ use crate::dbs::comment_db::*;
 use crate::error::*;
 use crate::models::comment::*;
 use crate::models::parameter::*;
+use crate::models::permission::Scope;
 use crate::models::state::AppState;
+use crate::models::user::UserSession;
 use axum::extract::Path;
 use axum::extract::Query;
 use axum::extract::State;
+use axum::Extension;
 use axum::Json;
 use axum::{http::StatusCode, response::IntoResponse, Router};
 use std::sync::Arc;
 use tracing::{debug, error, info};
 
+// 评论者为当前登录用户，令牌需要 comments:write
 pub async fn post_comment(
     app_state: State<Arc<AppState>>,
+    Extension(user): Extension<UserSession>,
     Json(comment_create): Json<CommentCreate>,
 ) -> Result<impl IntoResponse, AppError> {
-    post_comment_db(&app_state.pool, &comment_create).await?;
+    user.require_scope(Scope::CommentsWrite)?;
+    post_comment_db(&app_state.pool, &user.username, &comment_create).await?;
+    Ok(StatusCode::OK)
+}
+
+// 删除评论及其回复：文章作者或 editor 以上，令牌需要 comments:moderate
+pub async fn delete_comment(
+    app_state: State<Arc<AppState>>,
+    Extension(user): Extension<UserSession>,
+    Path((article_id, comment_id)): Path<(i64, i32)>,
+) -> Result<impl IntoResponse, AppError> {
+    user.require_scope(Scope::CommentsModerate)?;
+    if get_comment_article_db(&app_state.pool, comment_id).await? != article_id {
+        return Err(AppError::RequestNotFound);
+    }
+    let (owner, _) = get_article_state_db(&app_state.pool, article_id).await?;
+    user.require_content_owner(owner)?;
+    let deleted = delete_comment_db(&app_state.pool, comment_id).await?;
+    info!(
+        "comment {} and {} replies deleted by {}",
+        comment_id,
+        deleted.saturating_sub(1),
+        user.username
+    );
     Ok(StatusCode::OK)
 }
 
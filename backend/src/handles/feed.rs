@@ -0,0 +1,157 @@
+use crate::dbs::feed_db::*;
+use crate::error::*;
+use crate::feed::*;
+use crate::models::feed::*;
+use crate::models::state::*;
+use axum::extract::Path;
+use axum::extract::Query;
+use axum::extract::State;
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+
+fn http_date(time: &DateTime<Utc>) -> String {
+    time.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
+
+// If-None-Match 优先，没有时比较 If-Modified-Since
+fn not_modified(headers: &HeaderMap, etag: &str, last_modified: &DateTime<Utc>) -> bool {
+    if let Some(value) = headers.get(header::IF_NONE_MATCH) {
+        return value
+            .to_str()
+            .map(|v| v.split(',').any(|t| t.trim() == etag || t.trim() == "*"))
+            .unwrap_or(false);
+    }
+    headers
+        .get(header::IF_MODIFIED_SINCE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| DateTime::parse_from_rfc2822(v).ok())
+        .map(|since| last_modified.timestamp() <= since.timestamp())
+        .unwrap_or(false)
+}
+
+// 生成订阅，支持 ETag / Last-Modified 条件请求
+// ETag 由订阅的设置、名称和文章的版本计算，与正文的渲染结果无关
+async fn feed_response(
+    app_state: &AppState,
+    headers: &HeaderMap,
+    filter: FeedFilter,
+    format: &str,
+    param: &FeedParams,
+) -> Result<Response, AppError> {
+    let atom = match format.trim_end_matches(".xml") {
+        "rss" => false,
+        "atom" => true,
+        _ => return Err(AppError::RequestNotFound),
+    };
+    let full = match param.content.as_deref() {
+        Some("full") => true,
+        Some("digest") => false,
+        Some(_) => return Err(AppError::InvalidRequest),
+        None => app_state.config.feed.content == "full",
+    };
+    let config = &app_state.config.feed;
+    let site = app_state.config.server.frontend_url.trim_end_matches('/');
+    let name = get_feed_name_db(&app_state.pool, filter).await?;
+
+    let (title, link) = match (filter, name) {
+        (FeedFilter::User(id), Some(name)) => (format!("{} - {}", config.title, name), format!("{}/users/{}", site, id)),
+        (FeedFilter::Tag(id), Some(name)) => (format!("{} - {}", config.title, name), format!("{}/tags/{}", site, id)),
+        (FeedFilter::Catalogue(id), Some(name)) => (
+            format!("{} - {}", config.title, name),
+            format!("{}/catalogues/{}", site, id),
+        ),
+        _ => (config.title.clone(), format!("{}/", site)),
+    };
+    // 在查询和渲染文章之前根据版本判断条件请求，内容没有变化时直接返回 304
+    let version = get_feed_version_db(&app_state.pool, filter).await?;
+    let updated = version.updated.unwrap_or(DateTime::UNIX_EPOCH);
+    let etag = format!(
+        "\"{:x}\"",
+        Sha256::digest(
+            format!(
+                "{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}",
+                atom,
+                full,
+                config.limit,
+                title,
+                link,
+                config.description,
+                updated.timestamp_micros(),
+                version.count,
+                version.checksum
+            )
+            .as_bytes()
+        )
+    );
+    let last_modified = http_date(&updated);
+    let mut response = if not_modified(headers, &etag, &updated) {
+        StatusCode::NOT_MODIFIED.into_response()
+    } else {
+        let articles = get_feed_articles_db(&app_state.pool, filter, config.limit, full).await?;
+        let channel = FeedChannel {
+            title,
+            description: config.description.clone(),
+            link,
+            updated,
+        };
+        let (body, content_type) = if atom {
+            (render_atom(&channel, &articles, site, full), "application/atom+xml; charset=utf-8")
+        } else {
+            (render_rss(&channel, &articles, site, full), "application/rss+xml; charset=utf-8")
+        };
+        let mut response = body.into_response();
+        response
+            .headers_mut()
+            .insert(header::CONTENT_TYPE, HeaderValue::from_static(content_type));
+        response
+    };
+    let response_headers = response.headers_mut();
+    response_headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("public, max-age=300"));
+    if let Ok(value) = HeaderValue::from_str(&etag) {
+        response_headers.insert(header::ETAG, value);
+    }
+    if let Ok(value) = HeaderValue::from_str(&last_modified) {
+        response_headers.insert(header::LAST_MODIFIED, value);
+    }
+    Ok(response)
+}
+
+// 全站订阅，format 为 rss 或 atom
+pub async fn get_site_feed(
+    app_state: State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(format): Path<String>,
+    Query(param): Query<FeedParams>,
+) -> Result<Response, AppError> {
+    feed_response(&app_state, &headers, FeedFilter::All, &format, &param).await
+}
+
+pub async fn get_user_feed(
+    app_state: State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path((user_id, format)): Path<(i64, String)>,
+    Query(param): Query<FeedParams>,
+) -> Result<Response, AppError> {
+    feed_response(&app_state, &headers, FeedFilter::User(user_id), &format, &param).await
+}
+
+pub async fn get_tag_feed(
+    app_state: State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path((tag_id, format)): Path<(i64, String)>,
+    Query(param): Query<FeedParams>,
+) -> Result<Response, AppError> {
+    feed_response(&app_state, &headers, FeedFilter::Tag(tag_id), &format, &param).await
+}
+
+pub async fn get_catalogue_feed(
+    app_state: State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path((catalogue_id, format)): Path<(i64, String)>,
+    Query(param): Query<FeedParams>,
+) -> Result<Response, AppError> {
+    feed_response(&app_state, &headers, FeedFilter::Catalogue(catalogue_id), &format, &param).await
+}
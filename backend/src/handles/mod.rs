@@ -1,5 +1,11 @@
+pub mod access_token;
 pub mod article;
 pub mod catalogue;
 pub mod comment;
+pub mod feed;
+pub mod revision;
+pub mod search;
+pub mod session;
+pub mod sitemap;
 pub mod tag;
 pub mod user;
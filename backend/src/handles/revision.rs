@@ -0,0 +1,77 @@
+use crate::dbs::article_db::*;
+use crate::dbs::revision_db::*;
+use crate::dbs::slug_db::refresh_draft_slug_db;
+use crate::error::*;
+use crate::models::article::ArticleUpdate;
+use crate::models::permission::Scope;
+use crate::models::revision::*;
+use crate::models::state::*;
+use crate::models::user::UserSession;
+use axum::extract::Path;
+use axum::extract::Query;
+use axum::extract::State;
+use axum::Extension;
+use axum::Json;
+use axum::{http::StatusCode, response::IntoResponse};
+use std::sync::Arc;
+use tracing::info;
+
+// 修订历史可能包含未发布的内容，只有作者本人或 editor 以上可以查看
+pub async fn get_article_revisions(
+    app_state: State<Arc<AppState>>,
+    Extension(user): Extension<UserSession>,
+    Path(article_id): Path<i64>,
+) -> Result<impl IntoResponse, AppError> {
+    user.require_content_owner(get_article_owner_db(&app_state.pool, article_id).await?)?;
+    let revisions = list_revisions_db(&app_state.pool, article_id).await?;
+    Ok((StatusCode::OK, Json(revisions)))
+}
+
+pub async fn get_article_revision(
+    app_state: State<Arc<AppState>>,
+    Extension(user): Extension<UserSession>,
+    Path((article_id, revision)): Path<(i64, i32)>,
+) -> Result<impl IntoResponse, AppError> {
+    user.require_content_owner(get_article_owner_db(&app_state.pool, article_id).await?)?;
+    let revision = get_revision_db(&app_state.pool, article_id, revision).await?;
+    Ok((StatusCode::OK, Json(revision)))
+}
+
+// 比较两个修订
+pub async fn get_article_revision_diff(
+    app_state: State<Arc<AppState>>,
+    Extension(user): Extension<UserSession>,
+    Path(article_id): Path<i64>,
+    Query(param): Query<RevisionDiffParams>,
+) -> Result<impl IntoResponse, AppError> {
+    user.require_content_owner(get_article_owner_db(&app_state.pool, article_id).await?)?;
+    let from = get_revision_db(&app_state.pool, article_id, param.from).await?;
+    let to = get_revision_db(&app_state.pool, article_id, param.to).await?;
+    Ok((StatusCode::OK, Json(ArticleRevisionDiff::between(&from, &to))))
+}
+
+// 恢复旧的修订，恢复后的内容保存为新的修订，历史不会被覆盖
+pub async fn restore_article_revision(
+    app_state: State<Arc<AppState>>,
+    Extension(user): Extension<UserSession>,
+    Path((article_id, revision)): Path<(i64, i32)>,
+) -> Result<impl IntoResponse, AppError> {
+    user.require_scope(Scope::ArticlesWrite)?;
+    user.require_content_owner(get_article_owner_db(&app_state.pool, article_id).await?)?;
+    let old = get_revision_db(&app_state.pool, article_id, revision).await?;
+    let update = ArticleUpdate {
+        title: Some(old.title),
+        content: Some(old.content),
+        digest: Some(old.digest),
+        feature: None,
+        tags_id: None,
+    };
+    update_article_db(&app_state.pool, article_id, &update, user.user_detail_id).await?;
+    refresh_draft_slug_db(&app_state.pool, article_id).await?;
+    app_state.sitemap.article_changed(article_id);
+    info!(
+        "article {} restored to revision {} by {}",
+        article_id, revision, user.username
+    );
+    Ok(StatusCode::OK)
+}
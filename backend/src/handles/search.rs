@@ -0,0 +1,38 @@
+use crate::dbs::search_db::*;
+use crate::error::*;
+use crate::models::parameter::{paginate, ArticleQueryParams};
+use crate::models::search::SearchResult;
+use crate::models::state::*;
+use crate::search::search_terms;
+use axum::extract::Query;
+use axum::extract::State;
+use axum::Json;
+use axum::{http::StatusCode, response::IntoResponse};
+use std::sync::Arc;
+
+// 每页最多返回的文章数
+const MAX_LIMIT: i32 = 50;
+
+// 搜索已发布的文章，以及名称匹配的标签和目录
+pub async fn search(
+    app_state: State<Arc<AppState>>,
+    Query(mut param): Query<ArticleQueryParams>,
+) -> Result<impl IntoResponse, AppError> {
+    param.q = param.q.trim().to_string();
+    let terms = search_terms(&param.q);
+    if terms.is_empty() || param.q.chars().count() > 100 {
+        return Err(AppError::InvalidRequest);
+    }
+    let (limit, offset) = paginate(param.page, param.limit, 10, MAX_LIMIT);
+
+    let (total, articles) =
+        search_articles_db(&app_state.pool, &param, &terms, limit, offset).await?;
+    let result = SearchResult {
+        articles,
+        total,
+        total_page: (total as f64 / limit as f64).ceil() as i64,
+        tags: search_tags_db(&app_state.pool, &param.q).await?,
+        catalogues: search_catalogues_db(&app_state.pool, &param.q).await?,
+    };
+    Ok((StatusCode::OK, Json(result)))
+}
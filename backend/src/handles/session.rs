@@ -0,0 +1,56 @@
+use crate::dbs::session_db::*;
+use crate::error::*;
+use crate::models::permission::{Role, Scope};
+use crate::models::state::AppState;
+use crate::models::user::UserSession;
+use crate::session::{current_session_id, ONLINE_MINUTES};
+use axum::extract::Path;
+use axum::extract::State;
+use axum::Extension;
+use axum::Json;
+use axum::{http::StatusCode, response::IntoResponse};
+use std::sync::Arc;
+use tower_sessions::Session;
+use tracing::info;
+
+// 当前用户已登录的设备
+pub async fn get_sessions(
+    app_state: State<Arc<AppState>>,
+    session: Session,
+    Extension(user): Extension<UserSession>,
+) -> Result<impl IntoResponse, AppError> {
+    if user.is_access_token() {
+        return Err(AppError::PermissionDenied);
+    }
+    let current = current_session_id(&session).await?;
+    let mut sessions = list_sessions_db(&app_state.pool, user.user_detail_id).await?;
+    for s in sessions.iter_mut() {
+        s.current = current.as_deref() == Some(s.id.as_str());
+    }
+    Ok((StatusCode::OK, Json(sessions)))
+}
+
+// 撤销某个设备的登录，该设备的下一次请求需要重新登录
+pub async fn delete_session(
+    app_state: State<Arc<AppState>>,
+    Extension(user): Extension<UserSession>,
+    Path(session_id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    if user.is_access_token() {
+        return Err(AppError::PermissionDenied);
+    }
+    revoke_session_db(&app_state.pool, user.user_detail_id, &session_id).await?;
+    info!("session {} revoked by {}", session_id, user.username);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// 在线用户，仅 admin
+pub async fn get_online_users(
+    app_state: State<Arc<AppState>>,
+    Extension(user): Extension<UserSession>,
+) -> Result<impl IntoResponse, AppError> {
+    user.require_scope(Scope::UsersManage)?;
+    user.require_role(Role::Admin)?;
+    let users = online_users_db(&app_state.pool, ONLINE_MINUTES).await?;
+    Ok((StatusCode::OK, Json(users)))
+}
@@ -0,0 +1,79 @@
+use crate::error::*;
+use crate::models::sitemap::SitemapUrl;
+use crate::models::state::*;
+use crate::sitemap::*;
+use axum::extract::Path;
+use axum::extract::State;
+use axum::http::{header, HeaderMap, HeaderValue};
+use axum::response::{IntoResponse, Response};
+use std::sync::Arc;
+
+// 本服务对外的地址，见 sitemap::public_base
+fn public_base_url(app_state: &AppState, headers: &HeaderMap) -> String {
+    let config = &app_state.config;
+    public_base(
+        config.sitemap.public_url.as_deref(),
+        &config.sitemap.allowed_hosts,
+        headers.get(header::HOST).and_then(|v| v.to_str().ok()),
+        &config.server.frontend_url,
+    )
+}
+
+fn text_response(body: String, content_type: &'static str) -> Response {
+    let mut response = body.into_response();
+    let response_headers = response.headers_mut();
+    response_headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(content_type));
+    response_headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("public, max-age=300"));
+    response
+}
+
+async fn sitemap_urls(app_state: &AppState) -> Result<Vec<SitemapUrl>, AppError> {
+    app_state
+        .sitemap
+        .refresh(&app_state.pool, app_state.config.sitemap.refresh_seconds)
+        .await?;
+    Ok(app_state.sitemap.urls(&app_state.config.server.frontend_url))
+}
+
+// 地址不超过 50000 个时直接返回 urlset，否则返回指向 /sitemaps/{n}.xml 的索引
+pub async fn get_sitemap(
+    app_state: State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let urls = sitemap_urls(&app_state).await?;
+    let body = if urls.len() <= MAX_SITEMAP_URLS {
+        render_urlset(&urls)
+    } else {
+        render_index(&public_base_url(&app_state, &headers), &urls)
+    };
+    Ok(text_response(body, "application/xml; charset=utf-8"))
+}
+
+// 索引中的第 n 个 sitemap，file 为 {n}.xml，n 从 1 开始
+pub async fn get_sitemap_page(
+    app_state: State<Arc<AppState>>,
+    Path(file): Path<String>,
+) -> Result<Response, AppError> {
+    let page = file
+        .strip_suffix(".xml")
+        .and_then(|n| n.parse::<usize>().ok())
+        .filter(|n| *n >= 1)
+        .ok_or(AppError::RequestNotFound)?;
+    let urls = sitemap_urls(&app_state).await?;
+    let chunk = urls
+        .chunks(MAX_SITEMAP_URLS)
+        .nth(page - 1)
+        .ok_or(AppError::RequestNotFound)?;
+    Ok(text_response(render_urlset(chunk), "application/xml; charset=utf-8"))
+}
+
+pub async fn get_robots(
+    app_state: State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let body = render_robots(
+        &public_base_url(&app_state, &headers),
+        &app_state.config.robots.disallow,
+    );
+    Ok(text_response(body, "text/plain; charset=utf-8"))
+}
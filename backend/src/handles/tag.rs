@@ -3,27 +3,36 @@ use crate::dbs::tag_db::*;
 use crate::error::*;
 use crate::models::parameter::*;
 use crate::models::state::*;
+use crate::models::permission::{Role, Scope};
 use crate::models::tag::*;
+use crate::models::user::UserSession;
 use axum::extract::Path;
 use axum::extract::State;
+use axum::Extension;
 use axum::Json;
 use axum::{http::StatusCode, response::IntoResponse, Router};
 use std::sync::Arc;
 use tracing::{debug, error, info};
 
-// 创建新标签
+// 创建新标签，登录用户都可以创建，令牌需要 tags:write
 pub async fn creata_tag(
     app_state: State<Arc<AppState>>,
+    Extension(user): Extension<UserSession>,
     Path(tag_name): Path<String>,
 ) -> Result<impl IntoResponse, AppError> {
+    user.require_scope(Scope::TagsWrite)?;
     post_tag_db(&app_state.pool, &tag_name).await?;
     Ok(StatusCode::OK)
 }
 // 删除指定标签
 pub async fn delete_tag(
     app_state: State<Arc<AppState>>,
+    Extension(user): Extension<UserSession>,
     Path(tag_id): Path<i64>,
 ) -> Result<impl IntoResponse, AppError> {
+    // 标签为全站共享，只有 editor 以上可以删除
+    user.require_scope(Scope::TagsWrite)?;
+    user.require_role(Role::Editor)?;
     delete_tag_db(&app_state.pool, tag_id).await?;
     Ok(StatusCode::OK)
 }
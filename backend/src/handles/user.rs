@@ -1,20 +1,30 @@
 use crate::dbs::user_db::*;
 use crate::error::*;
+use crate::models::permission::*;
 use crate::models::state::AppState;
 use crate::models::user::*;
-use crate::utils::get_auth;
+use crate::oauth::{CallbackQuery, PendingLogin};
+use crate::session::{end_session, register_session, session_user};
 use axum::body::Body;
+use axum::extract::ConnectInfo;
 use axum::extract::Path;
 use axum::extract::Query;
 use axum::extract::Request;
 use axum::extract::State;
+use axum::response::Redirect;
+use axum::http::HeaderMap;
+use axum::Extension;
 use axum::Json;
 use axum::{http::StatusCode, response::IntoResponse, Router};
 use core::error;
+use std::net::SocketAddr;
 use std::sync::Arc;
 use tower_sessions::Session;
 use tracing::{debug, error, info};
 
+// session 中保存登录中的 state 和 verifier
+const OAUTH_LOGIN_KEY: &str = "oauth_login";
+
 // // 更新当前用户信息
 // pub async fn put_users(
 //     app_state: State<Arc<AppState>>,
@@ -35,18 +45,13 @@ use tracing::{debug, error, info};
 pub async fn delete_user_logout(
     app_state: State<Arc<AppState>>,
     session: Session,
+    Extension(user): Extension<UserSession>,
 ) -> Result<impl IntoResponse, AppError> {
-    let user_get_username = session.get::<String>("user").await.unwrap().unwrap();
-    session.clear().await;
-    {
-        let mut vec = app_state.user_vec.lock().unwrap();
-        if let Some(pos) = vec
-            .iter()
-            .position(|user| user.username == user_get_username)
-        {
-            vec.remove(pos);
-        }
+    // 个人访问令牌没有 session，需要撤销令牌
+    if user.is_access_token() {
+        return Err(AppError::PermissionDenied);
     }
+    end_session(&app_state, &session, &user).await?;
     Ok(StatusCode::OK)
 }
 // 获取所有用户信息
@@ -65,9 +70,80 @@ pub async fn get_user_by_id(
     Ok((StatusCode::OK, Json(user)))
 }
 
+// 认证通过后登记 session，第一次登录时保存用户信息
+async fn start_user_session(
+    app_state: &AppState,
+    session: &Session,
+    user: &User,
+    headers: &HeaderMap,
+    addr: SocketAddr,
+) -> Result<UserSession, AppError> {
+    if get_user_by_username_db(&app_state.pool, &user.username)
+        .await
+        .is_err()
+    {
+        debug!("user not found, storage user info");
+        storage_auth_user(&app_state.pool, user).await?;
+    }
+    debug!("username: {}", user.username);
+    let session_user = get_session_user_by_username_db(&app_state.pool, &user.username).await?;
+    register_session(app_state, session, &session_user, user.sid.as_deref(), headers, addr).await?;
+    Ok(session_user)
+}
+
+// 跳转到认证服务登录，state 和 PKCE verifier 保存在 session 中
+pub async fn oauth_login(
+    app_state: State<Arc<AppState>>,
+    session: Session,
+) -> Result<impl IntoResponse, AppError> {
+    let (pending, url) = app_state.oauth_client.start_login()?;
+    session
+        .insert(OAUTH_LOGIN_KEY, &pending)
+        .await
+        .map_err(|e| {
+            error!("session insert error: {:?}", e);
+            AppError::InternalError
+        })?;
+    Ok(Redirect::to(&url))
+}
+
+// 认证服务登录后的回调，使用授权码换取 token 并建立 session
+pub async fn oauth_callback(
+    app_state: State<Arc<AppState>>,
+    session: Session,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Query(query): Query<CallbackQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    // state 只能使用一次
+    let pending = session
+        .remove::<PendingLogin>(OAUTH_LOGIN_KEY)
+        .await
+        .map_err(|e| {
+            error!("session get error: {:?}", e);
+            AppError::InternalError
+        })?
+        .ok_or(AppError::TokenInvalid)?;
+    if pending.state != query.state {
+        error!("oauth state mismatch");
+        return Err(AppError::TokenInvalid);
+    }
+    let token = app_state
+        .oauth_client
+        .exchange_code(&query.code, &pending.code_verifier)
+        .await?;
+    let user = app_state.token_verifier.verify(&token).await?;
+    start_user_session(&app_state, &session, &user, &headers, addr).await?;
+    Ok(Redirect::to(&format!(
+        "{}/?login=success",
+        app_state.oauth_client.frontend_url
+    )))
+}
+
 pub async fn auth_user(
     app_state: State<Arc<AppState>>,
     session: Session,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
     req: Request<Body>,
 ) -> Result<impl IntoResponse, AppError> {
     //从header 获取token
@@ -76,26 +152,9 @@ pub async fn auth_user(
             if let Some(token) = auth_str.strip_prefix("Bearer ") {
                 debug!("token: {}", token);
 
-                let auth_token_url = std::env::var("AUTH_TOKEN_URL").map_err(|e| {
-                    error!("missed auth_token_url");
-                    AppError::InternalError
-                })?;
-                let user = get_auth(token, &auth_token_url).await?;
-                // cookies.add(Cookie::new("user", user.username.clone()));
-                let _ = session.insert("user", &user.username).await.map_err(|e| {
-                    error!("session insert error: {:?}", e);
-                    AppError::InternalError
-                })?;
-                {
-                    let mut vec = app_state.user_vec.lock().unwrap();
-                    vec.push(user.clone());
-                }
-                if let Err(e) = get_user_by_username_db(&app_state.pool, &user.username).await {
-                    debug!("user not found, storage user info");
-                    storage_auth_user(&app_state.pool, &user).await?;
-                }
-                debug!("username: {}", user.username);
-                let user = get_session_user_by_username_db(&app_state.pool, &user.username).await?;
+                let user = app_state.token_verifier.verify(token).await?;
+                let headers = req.headers();
+                let user = start_user_session(&app_state, &session, &user, headers, addr).await?;
                 return Ok((StatusCode::OK, Json(user)));
             }
         }
@@ -110,18 +169,8 @@ pub async fn is_login(
     app_state: State<Arc<AppState>>,
     session: Session,
 ) -> Result<impl IntoResponse, AppError> {
-    let user_username_option = session.get::<String>("user").await.map_err({
-        |e| {
-            error!("session get error: {:?}", e);
-            AppError::InternalError
-        }
-    })?;
-    if let Some(user_username) = user_username_option {
-        let user = get_session_user_by_username_db(&app_state.pool, &user_username).await?;
-        return Ok((StatusCode::OK, Json(user)));
-    } else {
-        return Err(AppError::UserUnLogin);
-    }
+    let user = session_user(&app_state, &session).await?;
+    Ok((StatusCode::OK, Json(user)))
 }
 
 pub async fn get_user_resume(
@@ -141,19 +190,36 @@ pub async fn get_user_resume(
 #[axum::debug_handler]
 pub async fn post_resume(
     app_state: State<Arc<AppState>>,
-    session: Session,
+    Extension(user): Extension<UserSession>,
     Path(user_id): Path<i64>,
     Json(resume): Json<ResumeCreate>,
 ) -> Result<impl IntoResponse, AppError> {
+    user.require_scope(Scope::ProfileWrite)?;
+    user.require_user_owner(user_id)?;
     let _ = save_or_update_resume_db(&app_state.pool, &resume, user_id).await?;
     Ok(StatusCode::OK)
 }
 #[axum::debug_handler]
 pub async fn update_user(
     app_state: State<Arc<AppState>>,
+    Extension(user): Extension<UserSession>,
     Path(user_detail_id): Path<i64>,
     Json(user_update): Json<UserDetailUpdate>,
 ) -> Result<impl IntoResponse, AppError> {
+    user.require_scope(Scope::ProfileWrite)?;
+    user.require_user_owner(user_detail_id)?;
     update_userdetail_db(&app_state.pool, &user_update, user_detail_id).await?;
     Ok(())
 }
+// 修改用户角色，仅 admin
+pub async fn update_user_role(
+    app_state: State<Arc<AppState>>,
+    Extension(user): Extension<UserSession>,
+    Path(user_detail_id): Path<i64>,
+    Json(role_update): Json<RoleUpdate>,
+) -> Result<impl IntoResponse, AppError> {
+    user.require_scope(Scope::UsersManage)?;
+    user.require_role(Role::Admin)?;
+    update_user_role_db(&app_state.pool, user_detail_id, role_update.role.as_str()).await?;
+    Ok(StatusCode::OK)
+}
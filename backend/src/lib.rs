@@ -1,6 +1,19 @@
+pub mod access_token;
+pub mod config;
 pub mod dbs;
 pub mod error;
+pub mod feed;
 pub mod handles;
+pub mod markdown;
 pub mod middleware;
+pub mod migrate;
 pub mod models;
+pub mod oauth;
+pub mod publisher;
+pub mod search;
+pub mod session;
+pub mod session_store;
+pub mod sitemap;
+pub mod slug;
+pub mod token;
 pub mod utils;
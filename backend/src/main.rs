@@ -1,59 +1,104 @@
-use anyhow::Context;
 use axum::body::Body;
 use axum::extract::Request;
 use axum::middleware::from_fn_with_state;
 use axum::routing::{delete, get, post};
 use axum::Router;
-use axum_session::SameSite;
-use backend::handles::{article::*, tag::*, user::*};
-use backend::handles::{catalogue::*, comment::*};
+use backend::config::{Cli, Config};
+use backend::dbs::partition_db::init_article_partitions;
+use backend::dbs::slug_db::backfill_article_slugs_db;
+use backend::handles::{access_token::*, article::*, tag::*, user::*};
+use backend::handles::{catalogue::*, comment::*, revision::*, session::*};
+use backend::handles::{feed::*, search::search, sitemap::*};
 use backend::middleware::require_login;
+use backend::migrate::{migrate_article_tables, MIGRATOR};
+use common::migrate::{is_migrate_command, run_migrations};
 use backend::models::state::AppState;
+use backend::oauth::OAuthClient;
+use backend::publisher::spawn_publisher;
+use backend::sitemap::SitemapCache;
+use backend::session_store::AppSessionStore;
+use backend::token::{spawn_revocation_sync, TokenVerifier};
 use dotenv::dotenv;
 use reqwest::header::HeaderValue;
 use reqwest::header::AUTHORIZATION;
 use reqwest::header::CONTENT_TYPE;
 use sqlx::mysql::MySqlPoolOptions;
-use std::env;
-use std::sync::{Arc, Mutex};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
 use tower_http::cors::CorsLayer;
 use tower_http::trace::{DefaultOnResponse, TraceLayer};
-use tower_sessions::{Expiry, MemoryStore, SessionManagerLayer};
+use tower_sessions::{Expiry, SessionManagerLayer};
 use tracing::{debug, error, info, trace};
+use tracing_subscriber::EnvFilter;
 #[tokio::main]
 async fn main() {
-    //// 从环境变量中读取日志级别，默认级别为 INFO
-    // let env_filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
-    // tracing_subscriber::fmt().with_env_filter(env_filter).init();
-    // env RUST_LOG=debug
+    dotenv().ok(); // 加载 .env 文件
+    // 配置错误时列出所有问题后退出
+    let (cli, config) = load_config().unwrap_or_else(|e| {
+        eprintln!("{:#}", e);
+        std::process::exit(1);
+    });
+    if cli.print_config {
+        print!("{}", config.to_toml().unwrap());
+        return;
+    }
+    // 日志级别由 log.level 或 RUST_LOG 设置，默认为 warn
     tracing_subscriber::fmt()
-        .with_max_level(tracing::Level::WARN)
+        .with_env_filter(EnvFilter::new(&config.log.level))
         .init();
 
-    dotenv().ok(); // 加载 .env 文件
+    run(cli, config).await.unwrap();
+}
 
-    run().await.unwrap();
+// 默认值 < 配置文件 < 环境变量 < 命令行参数
+fn load_config() -> anyhow::Result<(Cli, Config)> {
+    let cli = Cli::parse()?;
+    let config = Config::load(&cli)?;
+    Ok((cli, config))
 }
 
-async fn run() -> anyhow::Result<()> {
-    let database_url = env::var("DATABASE_URL").with_context(|| "database not set")?;
-    let addr = env::var("BIND_ADDR").unwrap_or("0.0.0.0:8002".to_string());
-    let timemout = env::var("TIMEOUT").unwrap_or("2".to_string());
+async fn run(cli: Cli, config: Config) -> anyhow::Result<()> {
+    let addr = config.server.bind_addr.clone();
     // 创建 MySQL 连接池
     let pool = MySqlPoolOptions::new()
-        .max_connections(10)
-        .connect(&database_url)
+        .max_connections(config.database.max_connections)
+        .min_connections(config.database.min_connections)
+        .acquire_timeout(Duration::from_secs(config.database.acquire_timeout_seconds))
+        .connect(&config.database.url)
         .await?;
-    let session_store = MemoryStore::default();
-    let session_layer = SessionManagerLayer::new(session_store)
-        .with_same_site(SameSite::None) // 在生产环境中应保持为 true，以确保 Cookie 只能通过 HTTPS 发送
-        .with_secure(true)
+    // 执行数据库迁移
+    run_migrations(&MIGRATOR, &pool).await?;
+    migrate_article_tables(&pool).await?;
+    if is_migrate_command(cli.command()) {
+        return Ok(());
+    }
+    // 初始化文章分表与索引
+    init_article_partitions(&pool).await?;
+    backfill_article_slugs_db(&pool).await?;
+    // 定时发布到期的文章
+    spawn_publisher(pool.clone(), config.article.publish_interval_seconds);
+    // session 默认保存在数据库，重启后不丢失，多个实例共享
+    let session_store = AppSessionStore::from_config(&config.session, &pool);
+    session_store.spawn_cleanup(pool.clone(), config.session.cleanup_seconds);
+    let mut session_layer = SessionManagerLayer::new(session_store)
+        .with_same_site(config.cookie.same_site())
+        .with_secure(config.cookie.secure)
         .with_http_only(true)
         .with_expiry(Expiry::OnInactivity(
-            tower_sessions::cookie::time::Duration::hours(timemout.parse::<i64>()?),
+            tower_sessions::cookie::time::Duration::hours(config.session.timeout_hours),
         ));
+    if let Some(domain) = &config.cookie.domain {
+        session_layer = session_layer.with_domain(domain.clone());
+    }
 
     //配置cors
+    let origins = config
+        .cors
+        .allowed_origins
+        .iter()
+        .map(|origin| HeaderValue::from_str(origin))
+        .collect::<Result<Vec<_>, _>>()?;
     let cors = CorsLayer::new()
         .allow_methods(
             vec!["GET", "POST", "PUT", "DELETE"]
@@ -62,18 +107,16 @@ async fn run() -> anyhow::Result<()> {
                 .collect::<Vec<_>>(),
         )
         .allow_headers(vec![CONTENT_TYPE, AUTHORIZATION])
-        .allow_origin(vec![
-            HeaderValue::from_static("http://localhost:3000"),
-            HeaderValue::from_static("http://127.0.0.1:3000"),
-            HeaderValue::from_static("http://127.0.0.1:8001"),
-            HeaderValue::from_static("http://localhost:8001"),
-            HeaderValue::from_static("http://127.0.0.1:5500"),
-        ])
+        .allow_origin(origins)
         .allow_credentials(true); // 允许凭据;
     let app_state = Arc::new(AppState {
         pool,
-        user_vec: Mutex::new(Vec::new()),
+        token_verifier: TokenVerifier::from_config(&config.auth),
+        oauth_client: OAuthClient::from_config(&config),
+        sitemap: SitemapCache::default(),
+        config,
     });
+    spawn_revocation_sync(app_state.clone(), app_state.config.auth.revocation_poll_seconds);
     info!("Server is running on: {}", addr);
 
     let listener = tokio::net::TcpListener::bind(addr).await?;
@@ -86,10 +129,21 @@ async fn run() -> anyhow::Result<()> {
         .route("/late", get(get_articles_latest))
         .route("/:article_id", delete(delete_article).layer(from_fn_with_state(app_state.clone(),require_login)))
         .route("/titles/all", get(get_article_titles))
-        .route("/:article_id/tags", get(get_article_tags));
+        .route("/:article_id/tags", get(get_article_tags))
+        .route("/mine", get(get_my_articles).layer(from_fn_with_state(app_state.clone(),require_login)))
+        .route("/review", get(get_review_articles).layer(from_fn_with_state(app_state.clone(),require_login)))
+        .route("/:article_id/preview", get(get_article_preview).layer(from_fn_with_state(app_state.clone(),require_login)))
+        .route("/:article_id/status", post(post_article_status).layer(from_fn_with_state(app_state.clone(),require_login)))
+        .route("/:article_id/revisions", get(get_article_revisions).layer(from_fn_with_state(app_state.clone(),require_login)))
+        .route("/:article_id/revisions/diff", get(get_article_revision_diff).layer(from_fn_with_state(app_state.clone(),require_login)))
+        .route("/:article_id/revisions/:revision", get(get_article_revision).layer(from_fn_with_state(app_state.clone(),require_login)))
+        .route("/:article_id/revisions/:revision/restore", post(restore_article_revision).layer(from_fn_with_state(app_state.clone(),require_login)))
+        .route("/slug/:slug", get(get_article_by_slug))
+        .route("/by-id/:article_id", get(get_article_permalink))
+        .route("/:article_id/slug", post(post_article_slug).layer(from_fn_with_state(app_state.clone(),require_login)));
 
     let tag_route = Router::new()
-        .route("/:tag_name", get(creata_tag))
+        .route("/:tag_name", post(creata_tag).layer(from_fn_with_state(app_state.clone(),require_login)))
         .route("/delete/:tag_id", delete(delete_tag).layer(from_fn_with_state(app_state.clone(),require_login)))
         .route("/all", get(get_tags))
         .route("/:tag_id/articles", get(get_tag_articles_info));
@@ -104,15 +158,19 @@ async fn run() -> anyhow::Result<()> {
         .route(
             "/:user_id/resume",
             //更新或创建简历
-            post(post_resume),
-        );
+            post(post_resume).layer(from_fn_with_state(app_state.clone(),require_login)),
+        )
+        .route("/:user_detail_id/role", post(update_user_role).layer(from_fn_with_state(app_state.clone(),require_login)));
 
     let auth_route = Router::new()
         .route("/token", get(auth_user))
+        .route("/login", get(oauth_login))
+        .route("/callback", get(oauth_callback))
         .route("/session", get(is_login));
     let comment_route = Router::new()
         .route("/post", post(post_comment).layer(from_fn_with_state(app_state.clone(),require_login)))
-        .route("/:article_id", get(get_comments_by_article_id));
+        .route("/:article_id", get(get_comments_by_article_id))
+        .route("/:article_id/:comment_id", delete(delete_comment).layer(from_fn_with_state(app_state.clone(),require_login)));
     let catalogue_route = Router::new()
         .route("/", post(post_catalogue).layer(from_fn_with_state(app_state.clone(),require_login)))
         .route("/:catalogue_id", post(post_update_catalogue).layer(from_fn_with_state(app_state.clone(),require_login)))
@@ -123,23 +181,48 @@ async fn run() -> anyhow::Result<()> {
         //移除目录下的文章
         .route(
             "/delete/:catalogue_id/:article_id",
-            delete(delete_catalogue_article_by_id),
+            delete(delete_catalogue_article_by_id).layer(from_fn_with_state(app_state.clone(),require_login)),
         )
         .route(
             "/delete/:catalogue_id/all",
-            delete(delete_catalogue_all_articles),
+            delete(delete_catalogue_all_articles).layer(from_fn_with_state(app_state.clone(),require_login)),
         )
         //添加文章到目录
-        .route("/add", post(post_catalogue_article))
-        .route("/post/sorder", post(post_catalogue_article_sort));
+        .route("/add", post(post_catalogue_article).layer(from_fn_with_state(app_state.clone(),require_login)))
+        .route("/post/sorder", post(post_catalogue_article_sort).layer(from_fn_with_state(app_state.clone(),require_login)));
+
+    // 个人访问令牌，只能在浏览器登录后管理
+    let token_route = Router::new()
+        .route("/", get(get_access_tokens).post(post_access_token))
+        .route("/:token_id", delete(delete_access_token))
+        .route_layer(from_fn_with_state(app_state.clone(), require_login));
+    // 登录设备管理
+    let session_route = Router::new()
+        .route("/", get(get_sessions))
+        .route("/:session_id", delete(delete_session))
+        .route("/online", get(get_online_users))
+        .route_layer(from_fn_with_state(app_state.clone(), require_login));
+    // RSS / Atom 订阅，format 为 rss 或 atom
+    let feed_route = Router::new()
+        .route("/:format", get(get_site_feed))
+        .route("/users/:user_id/:format", get(get_user_feed))
+        .route("/tags/:tag_id/:format", get(get_tag_feed))
+        .route("/catalogues/:catalogue_id/:format", get(get_catalogue_feed));
 
     let app = Router::new()
+        .route("/sitemap.xml", get(get_sitemap))
+        .route("/sitemaps/:file", get(get_sitemap_page))
+        .route("/robots.txt", get(get_robots))
+        .route("/api/search", get(search))
+        .nest("/api/feeds", feed_route)
         .nest("/api/tags", tag_route)
         .nest("/api/users", user_route)
         .nest("/api/articles", article_route)
         .nest("/api/auth", auth_route)
         .nest("/api/comments", comment_route)
         .nest("/api/catalogues", catalogue_route)
+        .nest("/api/tokens", token_route)
+        .nest("/api/sessions", session_route)
         .layer(session_layer)
         .with_state(app_state.clone())
         .layer(cors)
@@ -148,6 +231,11 @@ async fn run() -> anyhow::Result<()> {
                 tracing::info_span!("http_request", method = %request.method(), uri = %request.uri())
             })
             .on_response(DefaultOnResponse::new()));
-    axum::serve(listener, app).await?;
+    // 登录设备记录客户端地址
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await?;
     Ok(())
 }
@@ -0,0 +1,236 @@
+use ammonia::Builder;
+use pulldown_cmark::{html, CowStr, Event, Options, Parser, Tag, TagEnd};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+
+// 目录中的一个标题，anchor 与渲染结果中标题的 id 相同
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TocItem {
+    pub level: u8,
+    pub text: String,
+    pub anchor: String,
+}
+
+// 渲染规则变化时加一，缓存的 content_html 版本不同时重新渲染
+pub const RENDER_VERSION: i32 = 1;
+
+#[derive(Debug, Clone)]
+pub struct RenderedMarkdown {
+    pub html: String,
+    pub toc: Vec<TocItem>,
+}
+
+// 在 ammonia 默认白名单的基础上允许标题锚点、任务列表、脚注和代码语言
+// 标题和脚注的 id 只保留本次渲染生成的，文章中手写 HTML 的 id 会被去掉，避免覆盖页面上的元素
+fn sanitizer(heading_ids: HashSet<String>, footnote_ids: HashSet<String>) -> Builder<'static> {
+    let mut builder = Builder::default();
+    for tag in ["h1", "h2", "h3", "h4", "h5", "h6"] {
+        builder.add_tag_attributes(tag, &["id"]);
+    }
+    builder
+        .add_tags(&["input"])
+        .add_tag_attributes("input", &["checked"])
+        .set_tag_attribute_value("input", "type", "checkbox")
+        .set_tag_attribute_value("input", "disabled", "")
+        .add_tag_attributes("div", &["id"])
+        .add_allowed_classes("div", &["footnote-definition"])
+        .add_allowed_classes("sup", &["footnote-reference", "footnote-definition-label"])
+        .add_tag_attributes("code", &["class"])
+        .attribute_filter(move |tag, attr, value| {
+            let allowed = match (tag, attr) {
+                ("h1" | "h2" | "h3" | "h4" | "h5" | "h6", "id") => heading_ids.contains(value),
+                ("div", "id") => footnote_ids.contains(value),
+                _ => true,
+            };
+            allowed.then_some(Cow::Borrowed(value))
+        });
+    builder
+}
+
+// 相同的锚点依次加上 -1、-2
+fn unique_anchor(base: String, used: &mut HashSet<String>) -> String {
+    let mut anchor = base.clone();
+    let mut n = 0;
+    while used.contains(&anchor) {
+        n += 1;
+        anchor = format!("{}-{}", base, n);
+    }
+    used.insert(anchor.clone());
+    anchor
+}
+
+fn markdown_options() -> Options {
+    Options::ENABLE_TABLES
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+}
+
+// 标题文字生成锚点：保留字母、数字和中文，空白和连字符合并为 -
+pub fn heading_anchor(text: &str) -> String {
+    let mut anchor = String::new();
+    for c in text.trim().chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() {
+            anchor.push(c);
+        } else if (c.is_whitespace() || c == '-' || c == '_') && !anchor.ends_with('-') {
+            anchor.push('-');
+        }
+    }
+    let anchor = anchor.trim_matches('-');
+    if anchor.is_empty() {
+        "section".to_string()
+    } else {
+        anchor.to_string()
+    }
+}
+
+// CommonMark + GFM 表格、任务列表、脚注渲染为过滤后的 HTML，同时生成目录
+// 同名标题的锚点依次加上 -1、-2，只要标题不变锚点就不变
+// 脚注的 id 改为 fn- 加上脚注名生成的锚点
+pub fn render_markdown(source: &str) -> RenderedMarkdown {
+    let mut events: Vec<Event> = Parser::new_ext(source, markdown_options()).collect();
+    let mut toc = Vec::new();
+    let mut used = HashSet::new();
+    let mut heading_ids = HashSet::new();
+    let mut footnotes: HashMap<String, String> = HashMap::new();
+
+    let mut i = 0;
+    while i < events.len() {
+        let footnote = match &mut events[i] {
+            Event::FootnoteReference(name) | Event::Start(Tag::FootnoteDefinition(name)) => {
+                Some(name)
+            }
+            _ => None,
+        };
+        if let Some(name) = footnote {
+            let id = footnotes
+                .entry(name.to_string())
+                .or_insert_with(|| unique_anchor(format!("fn-{}", heading_anchor(name)), &mut used));
+            *name = CowStr::from(id.clone());
+            i += 1;
+            continue;
+        }
+        let level = match &events[i] {
+            Event::Start(Tag::Heading { level, .. }) => *level as u8,
+            _ => {
+                i += 1;
+                continue;
+            }
+        };
+        let mut text = String::new();
+        let mut end = i + 1;
+        while end < events.len() {
+            match &events[end] {
+                Event::End(TagEnd::Heading(_)) => break,
+                Event::Text(t) | Event::Code(t) => text.push_str(t),
+                _ => {}
+            }
+            end += 1;
+        }
+        let anchor = unique_anchor(heading_anchor(&text), &mut used);
+        heading_ids.insert(anchor.clone());
+        if let Event::Start(Tag::Heading { id, .. }) = &mut events[i] {
+            *id = Some(CowStr::from(anchor.clone()));
+        }
+        toc.push(TocItem {
+            level,
+            text: text.trim().to_string(),
+            anchor,
+        });
+        i += 1;
+    }
+
+    let mut unsafe_html = String::with_capacity(source.len() * 3 / 2);
+    html::push_html(&mut unsafe_html, events.into_iter());
+    RenderedMarkdown {
+        html: sanitizer(heading_ids, footnotes.into_values().collect())
+            .clean(&unsafe_html)
+            .to_string(),
+        toc,
+    }
+}
+
+// Markdown 转为纯文本，用于搜索摘要
+pub fn plain_text(source: &str) -> String {
+    let mut text = String::with_capacity(source.len());
+    for event in Parser::new_ext(source, markdown_options()) {
+        match event {
+            Event::Text(t) | Event::Code(t) => text.push_str(&t),
+            // 行内格式结束时不加空格，否则中文中间会多出空格
+            Event::End(
+                TagEnd::Emphasis | TagEnd::Strong | TagEnd::Strikethrough | TagEnd::Link | TagEnd::Image,
+            ) => {}
+            Event::SoftBreak | Event::HardBreak | Event::End(_) if !text.ends_with(' ') => {
+                text.push(' ');
+            }
+            _ => {}
+        }
+    }
+    text.trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn heading_anchor_keeps_letters_and_cjk() {
+        assert_eq!(heading_anchor("Hello, World!"), "hello-world");
+        assert_eq!(heading_anchor("  安装 与 配置  "), "安装-与-配置");
+        assert_eq!(heading_anchor("a -- b__c"), "a-b-c");
+        assert_eq!(heading_anchor("!!!"), "section");
+    }
+
+    #[test]
+    fn render_markdown_builds_toc() {
+        let rendered = render_markdown("# 介绍\n\n## Setup `cargo`\n\n## 介绍\n");
+        let anchors: Vec<_> = rendered.toc.iter().map(|t| t.anchor.as_str()).collect();
+        assert_eq!(anchors, ["介绍", "setup-cargo", "介绍-1"]);
+        assert_eq!(rendered.toc[1].level, 2);
+        assert_eq!(rendered.toc[1].text, "Setup cargo");
+        assert!(rendered.html.contains(r#"<h2 id="setup-cargo">"#));
+        assert!(rendered.html.contains(r#"<h2 id="介绍-1">"#));
+    }
+
+    #[test]
+    fn render_markdown_strips_unsafe_html() {
+        let html = render_markdown("<script>alert(1)</script>\n\n[x](javascript:alert(1))").html;
+        assert!(!html.contains("<script"));
+        assert!(!html.contains("javascript:"));
+    }
+
+    #[test]
+    fn render_markdown_drops_author_ids_and_classes() {
+        let source = "# Title\n\n<h2 id=\"login\" class=\"btn\">x</h2>\n\n<div id=\"app\" class=\"modal\">y</div>\n";
+        let html = render_markdown(source).html;
+        assert!(html.contains(r#"<h1 id="title">"#));
+        assert!(!html.contains("login"));
+        assert!(!html.contains("app"));
+        assert!(!html.contains("btn"));
+        assert!(!html.contains("modal"));
+    }
+
+    #[test]
+    fn render_markdown_footnotes_use_generated_ids() {
+        let html = render_markdown("Text[^note].\n\n[^note]: Footnote.\n").html;
+        assert!(html.contains(r##"href="#fn-note""##));
+        assert!(html.contains(r#"<div class="footnote-definition" id="fn-note">"#));
+    }
+
+    #[test]
+    fn render_markdown_forces_disabled_checkbox() {
+        let html = render_markdown("- [x] done\n\n<input type=\"text\" name=\"q\">\n").html;
+        assert!(html.contains("checked"));
+        assert!(!html.contains("text"));
+        assert!(!html.contains("name="));
+        assert_eq!(html.matches("<input").count(), html.matches(r#"disabled="""#).count());
+        assert_eq!(html.matches("<input").count(), html.matches(r#"type="checkbox""#).count());
+    }
+
+    #[test]
+    fn plain_text_joins_blocks() {
+        assert_eq!(plain_text("# 标题\n\n**加粗**文字和`代码`\n\n- a\n- b"), "标题 加粗文字和代码 a b");
+        assert_eq!(plain_text("[链接](http://x)后"), "链接后");
+    }
+}
@@ -1,5 +1,7 @@
+use crate::access_token::access_token_user;
 use crate::error::AppError;
 use crate::models::state::AppState;
+use crate::session::session_user;
 use axum::body::Body;
 use axum::extract::State;
 use axum::http::Request;
@@ -7,30 +9,32 @@ use axum::middleware::Next;
 use axum::response::IntoResponse;
 use std::sync::Arc;
 use tower_sessions::Session;
-use tracing::{error, info};
+use tracing::info;
 // 定义一个中间件，验证用户是否登录
+// 浏览器使用 session，脚本使用 Authorization: Bearer 个人访问令牌
+// 验证通过后把当前用户 UserSession 放入请求扩展，处理器通过 Extension 取出
 pub async fn require_login(
     State(state): State<Arc<AppState>>,
-    req: Request<Body>, // 泛型 B 处理请求体类型
-    next: Next,         // 链中的下一个中间件或处理器
+    mut req: Request<Body>, // 泛型 B 处理请求体类型
+    next: Next,             // 链中的下一个中间件或处理器
 ) -> Result<impl IntoResponse, AppError> {
-    // 提取 Session
-    if let Some(session) = req.extensions().get::<Session>() {
-        // 判断 session 中是否存在 user_id
-        if let Some(user_id) = session
-            .get::<String>("user")
-            .await
-            .map_err(|e| AppError::InternalError)?
-        {
-            info!("User ID: {} is logged in", user_id);
-            // 用户已登录，继续处理请求
-            Ok(next.run(req).await)
-        } else {
-            // 未登录，返回未授权
-            Err(AppError::UserUnLogin)
-        }
+    let bearer = req
+        .headers()
+        .get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))
+        .map(|t| t.trim().to_string());
+    let user = if let Some(token) = bearer {
+        access_token_user(&state.pool, &token).await?
+    } else if let Some(session) = req.extensions().get::<Session>() {
+        // 已撤销或过期的 session 返回未授权
+        session_user(&state, session).await?
     } else {
         // 未找到 Session，返回未授权
-        Err(AppError::UserUnLogin)
-    }
+        return Err(AppError::UserUnLogin);
+    };
+    info!("User: {} is logged in", user.username);
+    req.extensions_mut().insert(user);
+    // 用户已登录，继续处理请求
+    Ok(next.run(req).await)
 }
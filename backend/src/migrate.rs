@@ -0,0 +1,191 @@
+use crate::dbs::partition_db::list_article_tables;
+use crate::error::AppError;
+use sqlx::migrate::Migrator;
+use sqlx::MySqlPool;
+use tracing::{error, info};
+
+// 内嵌的数据库迁移，由 common::migrate::run_migrations 执行
+pub static MIGRATOR: Migrator = sqlx::migrate!();
+
+// 文章月表在运行时创建，结构升级不能写成 migrations 目录下的 SQL
+// 每一步对所有月表执行，每张表执行到的版本记录在 article_table_versions_table 中
+pub struct ArticleTableStep {
+    pub version: i32,
+    pub description: &'static str,
+    pub statements: fn(&str) -> Vec<String>,
+}
+
+// 新建的月表直接使用最新的结构，见 partition_db::article_table_ddl
+pub const ARTICLE_TABLE_VERSION: i32 = 5;
+
+pub const ARTICLE_TABLE_STEPS: &[ArticleTableStep] = &[
+    // 已有的文章视为已发布
+    ArticleTableStep {
+        version: 1,
+        description: "status columns",
+        statements: |table| {
+            vec![
+                format!(
+                    r#"ALTER TABLE {} ADD COLUMN status VARCHAR(16) NOT NULL DEFAULT 'published' AFTER feature, ADD COLUMN publish_at TIMESTAMP NULL AFTER status, ADD INDEX (status, publish_at)"#,
+                    table
+                ),
+                // 显式写入 updated_at，避免被 ON UPDATE 改成当前时间
+                format!(
+                    r#"UPDATE {} SET publish_at = created_at, updated_at = updated_at"#,
+                    table
+                ),
+                format!(
+                    r#"ALTER TABLE {} ALTER COLUMN status SET DEFAULT 'draft'"#,
+                    table
+                ),
+            ]
+        },
+    },
+    // 渲染结果为空的文章在第一次查看时渲染
+    ArticleTableStep {
+        version: 2,
+        description: "rendered html columns",
+        statements: |table| {
+            vec![format!(
+                r#"ALTER TABLE {} ADD COLUMN content_html MEDIUMTEXT NULL AFTER content, ADD COLUMN toc TEXT NULL AFTER content_html"#,
+                table
+            )]
+        },
+    },
+    // 已有的渲染结果版本为 0，查看时按当前规则重新渲染
+    ArticleTableStep {
+        version: 3,
+        description: "render version column",
+        statements: |table| {
+            vec![format!(
+                r#"ALTER TABLE {} ADD COLUMN render_version INT NOT NULL DEFAULT 0 AFTER toc"#,
+                table
+            )]
+        },
+    },
+    // slug 为空的文章启动时补上，见 slug_db::backfill_article_slugs_db
+    ArticleTableStep {
+        version: 4,
+        description: "slug column",
+        statements: |table| {
+            vec![format!(
+                r#"ALTER TABLE {} ADD COLUMN slug VARCHAR(100) NULL AFTER title"#,
+                table
+            )]
+        },
+    },
+    // 全文索引使用 ngram 分词，支持中文搜索
+    ArticleTableStep {
+        version: 5,
+        description: "fulltext indexes",
+        statements: |table| {
+            vec![
+                format!(
+                    r#"ALTER TABLE {} ADD FULLTEXT INDEX ft_article (title, digest, content) WITH PARSER ngram"#,
+                    table
+                ),
+                format!(
+                    r#"ALTER TABLE {} ADD FULLTEXT INDEX ft_article_title (title) WITH PARSER ngram"#,
+                    table
+                ),
+            ]
+        },
+    },
+];
+
+// 登记月表的结构版本，已经登记过的不会被覆盖
+pub async fn record_article_table(
+    pool: &MySqlPool,
+    table_name: &str,
+    version: i32,
+) -> Result<(), AppError> {
+    sqlx::query(
+        r#"INSERT IGNORE INTO article_table_versions_table (table_name, version) VALUES (?, ?)"#,
+    )
+    .bind(table_name)
+    .bind(version)
+    .execute(pool)
+    .await
+    .map_err(|e| {
+        error!("record article table {} failed: {:?}", table_name, e);
+        AppError::DataBaseError
+    })?;
+    Ok(())
+}
+
+async fn article_table_version(pool: &MySqlPool, table_name: &str) -> Result<i32, AppError> {
+    sqlx::query_scalar::<_, i32>(
+        r#"SELECT version FROM article_table_versions_table WHERE table_name = ?"#,
+    )
+    .bind(table_name)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| {
+        error!("get article table {} version failed: {:?}", table_name, e);
+        AppError::DataBaseError
+    })
+}
+
+// 把所有月表升级到最新的结构，需要在 run_migrations 之后执行
+// 没有登记过的月表是引入版本记录之前创建的，结构与最初的版本 0 相同
+pub async fn migrate_article_tables(pool: &MySqlPool) -> Result<(), AppError> {
+    for table_name in list_article_tables(pool).await? {
+        record_article_table(pool, &table_name, 0).await?;
+        let current = article_table_version(pool, &table_name).await?;
+        if current > ARTICLE_TABLE_VERSION {
+            error!(
+                "article table {} version {} is unknown to this build, refusing to start",
+                table_name, current
+            );
+            return Err(AppError::DataBaseError);
+        }
+        for step in ARTICLE_TABLE_STEPS.iter().filter(|s| s.version > current) {
+            for statement in (step.statements)(&table_name) {
+                sqlx::query(&statement).execute(pool).await.map_err(|e| {
+                    error!(
+                        "migrate article table {} to version {} failed: {:?}",
+                        table_name, step.version, e
+                    );
+                    AppError::DataBaseError
+                })?;
+            }
+            sqlx::query(
+                r#"UPDATE article_table_versions_table SET version = ? WHERE table_name = ?"#,
+            )
+            .bind(step.version)
+            .bind(&table_name)
+            .execute(pool)
+            .await
+            .map_err(|e| {
+                error!(
+                    "update article table {} version failed: {:?}",
+                    table_name, e
+                );
+                AppError::DataBaseError
+            })?;
+            info!(
+                "article table {} migrated to version {}: {}",
+                table_name, step.version, step.description
+            );
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn article_table_steps_are_consecutive() {
+        let versions: Vec<i32> = ARTICLE_TABLE_STEPS.iter().map(|s| s.version).collect();
+        assert_eq!(versions, (1..=ARTICLE_TABLE_VERSION).collect::<Vec<_>>());
+        for step in ARTICLE_TABLE_STEPS {
+            let statements = (step.statements)("articles_table_2024_01");
+            assert!(!statements.is_empty());
+            assert!(statements
+                .iter()
+                .all(|s| s.contains("articles_table_2024_01")));
+        }
+    }
+}
@@ -0,0 +1,36 @@
+use crate::models::permission::Scope;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+
+// 个人访问令牌，令牌本身只在创建时返回一次
+#[derive(Debug, Serialize, Deserialize, Clone, FromRow)]
+pub struct AccessToken {
+    pub id: i64,
+    pub name: String,
+    // 令牌的前几位，用于辨认
+    pub token_prefix: String,
+    // 空格分隔的权限范围
+    pub scopes: String,
+    pub expires_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AccessTokenCreate {
+    pub name: String,
+    pub scopes: Vec<Scope>,
+    // 有效期，天，默认 30，最长 365
+    pub expires_in_days: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AccessTokenCreated {
+    pub id: i64,
+    pub name: String,
+    pub token: String,
+    pub scopes: Vec<Scope>,
+    pub expires_at: DateTime<Utc>,
+}
@@ -5,30 +5,106 @@ use sqlx::{prelude::FromRow, MySqlPool};
 use std::sync::Mutex;
 
 use super::user::UserInfo;
+use crate::markdown::TocItem;
+
+// 文章状态，只有 published 的文章对所有人可见
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ArticleStatus {
+    #[default]
+    Draft,
+    InReview,
+    Scheduled,
+    Published,
+    Archived,
+}
+
+impl ArticleStatus {
+    pub fn parse(status: &str) -> Option<ArticleStatus> {
+        match status {
+            "draft" => Some(ArticleStatus::Draft),
+            "in_review" => Some(ArticleStatus::InReview),
+            "scheduled" => Some(ArticleStatus::Scheduled),
+            "published" => Some(ArticleStatus::Published),
+            "archived" => Some(ArticleStatus::Archived),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ArticleStatus::Draft => "draft",
+            ArticleStatus::InReview => "in_review",
+            ArticleStatus::Scheduled => "scheduled",
+            ArticleStatus::Published => "published",
+            ArticleStatus::Archived => "archived",
+        }
+    }
+
+    // 作者可以提交审核、撤回、归档自己的文章，发布和定时发布需要 editor 以上
+    pub fn can_transition(&self, to: ArticleStatus, is_editor: bool) -> bool {
+        use ArticleStatus::*;
+        match (self, to) {
+            (Draft, InReview) | (InReview, Draft) | (Scheduled, Draft) => true,
+            (Published, Archived) | (Archived, Draft) => true,
+            (Draft | InReview | Scheduled | Archived, Published) => is_editor,
+            (Draft | InReview | Scheduled, Scheduled) => is_editor,
+            (Published, Draft) => is_editor,
+            _ => false,
+        }
+    }
+}
 
 #[derive(Debug, Serialize, Deserialize, Clone, FromRow)]
 pub struct Article {
     pub id: i64,
     pub title: String,
+    // 永久链接 /articles/slug/:slug
+    pub slug: Option<String>,
     pub content: String,
+    // content 渲染后的 HTML 和目录（JSON），在详情中以 html、toc 返回
+    #[serde(skip)]
+    pub content_html: Option<String>,
+    #[serde(skip)]
+    pub toc: Option<String>,
+    #[serde(skip)]
+    pub render_version: i32,
     pub digest: String,
     pub user_detail_id: i64,
     pub feature: bool,
+    pub status: String,
+    // 定时发布的时间，已发布的文章为实际发布时间
+    pub publish_at: Option<chrono::DateTime<Utc>>,
     pub created_at: chrono::DateTime<Utc>,
     pub updated_at: chrono::DateTime<Utc>,
 }
 
+// 文章详情，包含渲染后的 HTML 和目录
+#[derive(Debug, Serialize, Clone)]
+pub struct ArticleDetail {
+    #[serde(flatten)]
+    pub article: Article,
+    pub html: String,
+    pub toc: Vec<TocItem>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ArticleDisplay {
     pub id: i64,
     pub title: String,
     pub digest: String,
     pub feature: bool,
+    pub slug: Option<String>,
     pub created_at: chrono::DateTime<Utc>,
     pub updated_at: chrono::DateTime<Utc>,
     pub tags: Vec<String>,
     pub author: UserInfo,
     pub total_page: Option<i64>,
+    // 只在作者和审核列表中返回
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub publish_at: Option<chrono::DateTime<Utc>>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -36,9 +112,20 @@ pub struct ArticleCreate {
     pub title: String,
     pub content: String,
     pub digest: String,
+    // 由服务端根据登录用户填写
+    #[serde(default)]
     pub user_detail_id: i64,
     pub feature: bool,
     pub tags_id: Vec<i64>,
+    // 不填时根据标题生成
+    #[serde(default)]
+    pub slug: Option<String>,
+    // 默认保存为草稿
+    #[serde(default)]
+    pub status: ArticleStatus,
+    // status 为 scheduled 时必填
+    #[serde(default)]
+    pub publish_at: Option<chrono::DateTime<Utc>>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -50,6 +137,24 @@ pub struct ArticleUpdate {
     pub tags_id: Option<Vec<i64>>,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ArticleSlugUpdate {
+    pub slug: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ArticleStatusUpdate {
+    pub status: ArticleStatus,
+    pub publish_at: Option<chrono::DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ArticleStatusParams {
+    pub status: Option<ArticleStatus>,
+    pub page: Option<i32>,
+    pub limit: Option<i32>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, FromRow)]
 pub struct ArticleTitle {
     pub id: i64,
@@ -60,3 +165,60 @@ pub struct ArticleTags {
     pub id: i64,
     pub tag: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ArticleStatus::*;
+
+    const ALL: [ArticleStatus; 5] = [Draft, InReview, Scheduled, Published, Archived];
+
+    #[test]
+    fn status_round_trip() {
+        for status in ALL {
+            assert_eq!(ArticleStatus::parse(status.as_str()), Some(status));
+        }
+        assert_eq!(ArticleStatus::parse("deleted"), None);
+    }
+
+    #[test]
+    fn author_transitions() {
+        let allowed = [
+            (Draft, InReview),
+            (InReview, Draft),
+            (Scheduled, Draft),
+            (Published, Archived),
+            (Archived, Draft),
+        ];
+        for from in ALL {
+            for to in ALL {
+                assert_eq!(
+                    from.can_transition(to, false),
+                    allowed.contains(&(from, to)),
+                    "{:?} -> {:?}",
+                    from,
+                    to
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn editor_transitions() {
+        for from in [Draft, InReview, Scheduled, Archived] {
+            assert!(from.can_transition(Published, true));
+        }
+        for from in [Draft, InReview, Scheduled] {
+            assert!(from.can_transition(Scheduled, true));
+        }
+        assert!(Published.can_transition(Draft, true));
+        // 不能跳过草稿直接提交审核或归档
+        assert!(!Archived.can_transition(InReview, true));
+        assert!(!Draft.can_transition(Archived, true));
+        // 定时发布可以改期，其他状态不能转换到自身
+        assert!(Scheduled.can_transition(Scheduled, true));
+        for status in [Draft, InReview, Published, Archived] {
+            assert!(!status.can_transition(status, true));
+        }
+    }
+}
@@ -19,6 +19,8 @@ pub struct Catalogue {
 
 #[derive(Debug, Serialize, Deserialize, Clone, FromRow)]
 pub struct CatalogueCreate {
+    // 由服务端根据登录用户填写
+    #[serde(default)]
     pub user_detail_id:i64,
     pub catalogue: String,
     pub info: Option<String>,
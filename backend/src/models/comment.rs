@@ -5,7 +5,6 @@ use std::sync::Mutex;
 
 #[derive(Debug, Serialize, Deserialize, Clone, FromRow)]
 pub struct CommentCreate {
-    pub guest: String,
     pub article_id: i32,
     pub parent_id: Option<i32>,
     pub comment: String,
@@ -0,0 +1,41 @@
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+// 订阅范围：全站、作者、标签、目录
+#[derive(Debug, Clone, Copy)]
+pub enum FeedFilter {
+    All,
+    User(i64),
+    Tag(i64),
+    Catalogue(i64),
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FeedParams {
+    // full | digest
+    pub content: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FeedArticle {
+    pub id: i64,
+    pub title: String,
+    pub slug: Option<String>,
+    pub digest: String,
+    // 只在全文订阅中查询；content_html 为缓存的渲染结果，没有缓存或版本不同时为 None
+    pub content: String,
+    pub content_html: Option<String>,
+    pub author: Option<String>,
+    pub tags: Vec<String>,
+    pub publish_at: Option<DateTime<Utc>>,
+    pub updated_at: DateTime<Utc>,
+}
+
+// 订阅内容的版本，用于在渲染前判断条件请求
+// 修改 slug、标签或作者昵称不会改变文章的 updated_at，所以另外比较这些数据的校验和
+#[derive(Debug, Clone)]
+pub struct FeedVersion {
+    pub updated: Option<DateTime<Utc>>,
+    pub count: i64,
+    pub checksum: i64,
+}
@@ -1,7 +1,14 @@
+pub mod access_token;
 pub mod article;
 pub mod catalogue;
 pub mod comment;
+pub mod feed;
 pub mod parameter;
+pub mod permission;
+pub mod revision;
+pub mod search;
+pub mod session;
+pub mod sitemap;
 pub mod state;
 pub mod tag;
 pub mod user;
@@ -4,12 +4,17 @@ use serde::{Deserialize, Serialize};
 use sqlx::{prelude::FromRow, MySqlPool};
 use std::sync::Mutex;
 
+// 搜索条件，from、to 按发布时间过滤
 #[derive(Debug, Deserialize)]
 pub struct ArticleQueryParams {
-    pub title: Option<String>,
-    pub user_id: Option<i32>,
-    pub tag_id: Option<i32>,
+    pub q: String,
+    pub user_id: Option<i64>,
+    pub tag_id: Option<i64>,
+    pub catalogue_id: Option<i64>,
+    pub from: Option<chrono::DateTime<Utc>>,
+    pub to: Option<chrono::DateTime<Utc>>,
     pub page: Option<i32>,
+    pub limit: Option<i32>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -17,3 +22,34 @@ pub struct PageParams {
     pub page: Option<i32>,
     pub limit: Option<i32>,
 }
+
+// 分页列表每页最多返回的条数
+pub const MAX_PAGE_LIMIT: i32 = 100;
+
+// 页码从 1 开始，limit 限制在 1..=max_limit，返回 (limit, offset)
+// offset 用 i64 计算，很大的页码不会溢出成负数
+pub fn paginate(page: Option<i32>, limit: Option<i32>, default_limit: i32, max_limit: i32) -> (i32, i64) {
+    let page = page.unwrap_or(1).max(1);
+    let limit = limit.unwrap_or(default_limit).clamp(1, max_limit);
+    (limit, (page as i64 - 1) * limit as i64)
+}
+
+impl PageParams {
+    pub fn paginate(&self) -> (i32, i64) {
+        paginate(self.page, self.limit, 10, MAX_PAGE_LIMIT)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paginate_clamps_page_and_limit() {
+        assert_eq!(paginate(None, None, 10, 100), (10, 0));
+        assert_eq!(paginate(Some(3), Some(20), 10, 100), (20, 40));
+        assert_eq!(paginate(Some(0), Some(0), 10, 100), (1, 0));
+        assert_eq!(paginate(Some(-5), Some(1000), 10, 100), (100, 0));
+        assert_eq!(paginate(Some(i32::MAX), Some(i32::MAX), 10, 50), (50, (i32::MAX as i64 - 1) * 50));
+    }
+}
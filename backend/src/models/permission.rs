@@ -0,0 +1,135 @@
+use crate::error::AppError;
+use crate::models::user::UserSession;
+use serde::{Deserialize, Serialize};
+
+// 用户角色，按权限从低到高排序
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Author,
+    Editor,
+    Admin,
+}
+
+impl Role {
+    pub fn parse(role: &str) -> Option<Role> {
+        match role {
+            "author" => Some(Role::Author),
+            "editor" => Some(Role::Editor),
+            "admin" => Some(Role::Admin),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Author => "author",
+            Role::Editor => "editor",
+            Role::Admin => "admin",
+        }
+    }
+}
+
+// 个人访问令牌的权限范围，令牌只能调用范围内的接口，并且仍然受角色限制
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    #[serde(rename = "articles:write")]
+    ArticlesWrite,
+    #[serde(rename = "catalogues:write")]
+    CataloguesWrite,
+    #[serde(rename = "tags:write")]
+    TagsWrite,
+    #[serde(rename = "profile:write")]
+    ProfileWrite,
+    #[serde(rename = "users:manage")]
+    UsersManage,
+    #[serde(rename = "comments:write")]
+    CommentsWrite,
+    #[serde(rename = "comments:moderate")]
+    CommentsModerate,
+}
+
+impl Scope {
+    pub fn parse(scope: &str) -> Option<Scope> {
+        match scope {
+            "articles:write" => Some(Scope::ArticlesWrite),
+            "catalogues:write" => Some(Scope::CataloguesWrite),
+            "tags:write" => Some(Scope::TagsWrite),
+            "profile:write" => Some(Scope::ProfileWrite),
+            "users:manage" => Some(Scope::UsersManage),
+            "comments:write" => Some(Scope::CommentsWrite),
+            "comments:moderate" => Some(Scope::CommentsModerate),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Scope::ArticlesWrite => "articles:write",
+            Scope::CataloguesWrite => "catalogues:write",
+            Scope::TagsWrite => "tags:write",
+            Scope::ProfileWrite => "profile:write",
+            Scope::UsersManage => "users:manage",
+            Scope::CommentsWrite => "comments:write",
+            Scope::CommentsModerate => "comments:moderate",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RoleUpdate {
+    pub role: Role,
+}
+
+impl UserSession {
+    // 数据库中未知的角色按最低权限处理
+    pub fn role(&self) -> Role {
+        Role::parse(&self.role).unwrap_or(Role::Author)
+    }
+
+    // 文章、目录：作者本人或 editor 以上
+    pub fn can_manage_content(&self, owner_id: i64) -> bool {
+        self.user_detail_id == owner_id || self.role() >= Role::Editor
+    }
+
+    // 用户资料、简历：本人或 admin
+    pub fn can_manage_user(&self, user_detail_id: i64) -> bool {
+        self.user_detail_id == user_detail_id || self.role() >= Role::Admin
+    }
+
+    // 浏览器 session 登录不限范围，个人访问令牌必须包含对应的范围
+    pub fn require_scope(&self, scope: Scope) -> Result<(), AppError> {
+        match &self.scopes {
+            Some(scopes) if !scopes.contains(&scope) => Err(AppError::PermissionDenied),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn is_access_token(&self) -> bool {
+        self.scopes.is_some()
+    }
+
+    pub fn require_role(&self, role: Role) -> Result<(), AppError> {
+        if self.role() >= role {
+            Ok(())
+        } else {
+            Err(AppError::PermissionDenied)
+        }
+    }
+
+    pub fn require_content_owner(&self, owner_id: i64) -> Result<(), AppError> {
+        if self.can_manage_content(owner_id) {
+            Ok(())
+        } else {
+            Err(AppError::PermissionDenied)
+        }
+    }
+
+    pub fn require_user_owner(&self, user_detail_id: i64) -> Result<(), AppError> {
+        if self.can_manage_user(user_detail_id) {
+            Ok(())
+        } else {
+            Err(AppError::PermissionDenied)
+        }
+    }
+}
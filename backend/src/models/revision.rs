@@ -0,0 +1,146 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use similar::{ChangeTag, TextDiff};
+use sqlx::prelude::FromRow;
+
+// 文章的一个修订版本
+#[derive(Debug, Serialize, Deserialize, Clone, FromRow)]
+pub struct ArticleRevision {
+    pub article_id: i64,
+    pub revision: i32,
+    pub title: String,
+    pub content: String,
+    pub digest: String,
+    pub user_detail_id: Option<i64>,
+    pub nickname: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+// 修订列表，不包含正文
+#[derive(Debug, Serialize, Deserialize, Clone, FromRow)]
+pub struct ArticleRevisionInfo {
+    pub revision: i32,
+    pub title: String,
+    pub user_detail_id: Option<i64>,
+    pub nickname: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RevisionDiffParams {
+    pub from: i32,
+    pub to: i32,
+}
+
+// 一行差异，op 为 equal | insert | delete，行号从 1 开始
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DiffLine {
+    pub op: String,
+    pub old_line: Option<usize>,
+    pub new_line: Option<usize>,
+    pub text: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ArticleRevisionDiff {
+    pub from: i32,
+    pub to: i32,
+    pub title: Vec<DiffLine>,
+    pub digest: Vec<DiffLine>,
+    pub content: Vec<DiffLine>,
+    // content 的 unified diff 文本
+    pub unified: String,
+}
+
+impl ArticleRevisionDiff {
+    pub fn between(from: &ArticleRevision, to: &ArticleRevision) -> ArticleRevisionDiff {
+        let unified = TextDiff::from_lines(&from.content, &to.content)
+            .unified_diff()
+            .header(
+                &format!("revision {}", from.revision),
+                &format!("revision {}", to.revision),
+            )
+            .to_string();
+        ArticleRevisionDiff {
+            from: from.revision,
+            to: to.revision,
+            title: diff_lines(&from.title, &to.title),
+            digest: diff_lines(&from.digest, &to.digest),
+            content: diff_lines(&from.content, &to.content),
+            unified,
+        }
+    }
+}
+
+// 按行比较两段文本
+pub fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    TextDiff::from_lines(old, new)
+        .iter_all_changes()
+        .map(|change| DiffLine {
+            op: match change.tag() {
+                ChangeTag::Equal => "equal",
+                ChangeTag::Insert => "insert",
+                ChangeTag::Delete => "delete",
+            }
+            .to_string(),
+            old_line: change.old_index().map(|i| i + 1),
+            new_line: change.new_index().map(|i| i + 1),
+            text: change.value().trim_end_matches(['\r', '\n']).to_string(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ops(lines: &[DiffLine]) -> Vec<(&str, Option<usize>, Option<usize>, &str)> {
+        lines
+            .iter()
+            .map(|l| (l.op.as_str(), l.old_line, l.new_line, l.text.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn diff_lines_numbers_changes() {
+        let diff = diff_lines("a\nb\nc\n", "a\nB\nc\nd\n");
+        assert_eq!(
+            ops(&diff),
+            vec![
+                ("equal", Some(1), Some(1), "a"),
+                ("delete", Some(2), None, "b"),
+                ("insert", None, Some(2), "B"),
+                ("equal", Some(3), Some(3), "c"),
+                ("insert", None, Some(4), "d"),
+            ]
+        );
+    }
+
+    #[test]
+    fn diff_lines_strips_line_endings() {
+        let diff = diff_lines("标题\r\n", "标题\n");
+        assert!(diff.iter().all(|l| l.text == "标题"));
+        assert!(diff_lines("", "").is_empty());
+        assert_eq!(ops(&diff_lines("", "x")), vec![("insert", None, Some(1), "x")]);
+    }
+
+    #[test]
+    fn between_builds_unified_diff() {
+        let revision = |revision, content: &str| ArticleRevision {
+            article_id: 1,
+            revision,
+            title: "t".to_string(),
+            content: content.to_string(),
+            digest: "d".to_string(),
+            user_detail_id: None,
+            nickname: None,
+            created_at: Utc::now(),
+        };
+        let diff = ArticleRevisionDiff::between(&revision(1, "a\n"), &revision(2, "b\n"));
+        assert_eq!((diff.from, diff.to), (1, 2));
+        assert!(diff.title.iter().all(|l| l.op == "equal"));
+        assert!(diff.unified.contains("--- revision 1"));
+        assert!(diff.unified.contains("+++ revision 2"));
+        assert!(diff.unified.contains("-a\n+b\n"));
+    }
+}
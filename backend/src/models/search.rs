@@ -0,0 +1,31 @@
+use super::catalogue::Catalogue;
+use super::tag::Tag;
+use super::user::UserInfo;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+// 搜索命中的文章，title_highlight 和 snippet 是转义后的 HTML，关键词用 <mark> 标出
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ArticleSearchHit {
+    pub id: i64,
+    pub title: String,
+    pub title_highlight: String,
+    pub slug: Option<String>,
+    pub digest: String,
+    pub snippet: String,
+    pub score: f64,
+    pub tags: Vec<String>,
+    pub author: UserInfo,
+    pub publish_at: Option<DateTime<Utc>>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SearchResult {
+    pub articles: Vec<ArticleSearchHit>,
+    pub total: i64,
+    pub total_page: i64,
+    // 名称匹配的标签和目录
+    pub tags: Vec<Tag>,
+    pub catalogues: Vec<Catalogue>,
+}
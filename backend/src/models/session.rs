@@ -0,0 +1,28 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+
+// 用户的一个登录设备
+#[derive(Debug, Serialize, Deserialize, Clone, FromRow)]
+pub struct UserSessionRecord {
+    pub id: String,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+    // 是否为发出请求的 session
+    #[sqlx(skip)]
+    pub current: bool,
+}
+
+// 在线用户，管理员查看
+#[derive(Debug, Serialize, Deserialize, Clone, FromRow)]
+pub struct OnlineUser {
+    pub session_id: String,
+    pub user_detail_id: i64,
+    pub username: String,
+    pub nickname: Option<String>,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    pub last_seen_at: DateTime<Utc>,
+}
@@ -0,0 +1,20 @@
+use chrono::{DateTime, Utc};
+
+// sitemap 需要的文章信息，作者、标签、目录页面的 lastmod 由其中的文章计算
+#[derive(Debug, Clone)]
+pub struct SitemapArticle {
+    pub id: i64,
+    pub slug: Option<String>,
+    pub published: bool,
+    pub updated_at: DateTime<Utc>,
+    pub user_detail_id: Option<i64>,
+    pub tag_ids: Vec<i64>,
+    pub catalogue_ids: Vec<i64>,
+}
+
+// sitemap 中的一个地址
+#[derive(Debug, Clone)]
+pub struct SitemapUrl {
+    pub loc: String,
+    pub lastmod: DateTime<Utc>,
+}
@@ -1,11 +1,18 @@
-use crate::models::user::User;
+use crate::config::Config;
+use crate::oauth::OAuthClient;
+use crate::sitemap::SitemapCache;
+use crate::token::TokenVerifier;
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use sqlx::{prelude::FromRow, MySqlPool};
-use std::sync::Mutex;
 
 #[derive(Debug)]
 pub struct AppState {
     pub pool: MySqlPool,
-    pub user_vec: Mutex<Vec<User>>,
+    // 启动时加载的配置
+    pub config: Config,
+    pub token_verifier: TokenVerifier,
+    pub oauth_client: OAuthClient,
+    // sitemap 中的已发布文章
+    pub sitemap: SitemapCache,
 }
@@ -1,3 +1,4 @@
+use crate::models::permission::Scope;
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use sqlx::{prelude::FromRow, MySqlPool};
@@ -20,6 +21,10 @@ pub struct User {
     pub id: i64,
     pub username: String,
     pub email: String,
+    // 认证服务的登录会话 id，用于同步退出登录
+    #[serde(default)]
+    #[sqlx(default)]
+    pub sid: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, FromRow)]
@@ -40,6 +45,11 @@ pub struct UserSession {
     pub email: String,
     pub nickname: Option<String>,
     pub avatar: Option<String>,
+    pub role: String,
+    // 使用个人访问令牌时为令牌的权限范围，session 登录为 None
+    #[sqlx(skip)]
+    #[serde(skip)]
+    pub scopes: Option<Vec<Scope>>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, FromRow)]
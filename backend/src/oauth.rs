@@ -0,0 +1,118 @@
+use crate::config::Config;
+use crate::error::AppError;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD as BASE64_URL;
+use base64::Engine;
+use rand::RngCore;
+use reqwest::{Client, Url};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::time::Duration;
+use tracing::{debug, error};
+
+// 发起登录时保存在 session 中，回调时校验 state 并用 verifier 换取 token
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PendingLogin {
+    pub state: String,
+    pub code_verifier: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CallbackQuery {
+    pub code: String,
+    pub state: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+}
+
+fn random_string() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    BASE64_URL.encode(bytes)
+}
+
+// 通过认证服务登录：授权码 + PKCE，token 只在服务端之间传递
+#[derive(Debug)]
+pub struct OAuthClient {
+    client: Client,
+    client_id: String,
+    client_secret: Option<String>,
+    authorize_url: String,
+    token_url: String,
+    redirect_uri: String,
+    // 登录完成后跳转回前端
+    pub frontend_url: String,
+}
+
+impl OAuthClient {
+    pub fn from_config(config: &Config) -> OAuthClient {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(5))
+            .build()
+            .unwrap_or_default();
+        OAuthClient {
+            client,
+            client_id: config.auth.client_id.clone(),
+            client_secret: config.auth.client_secret.clone(),
+            authorize_url: config.auth.authorize_url.clone(),
+            token_url: config.auth.oauth_token_url.clone(),
+            redirect_uri: config.auth.redirect_uri.clone(),
+            frontend_url: config.server.frontend_url.clone(),
+        }
+    }
+
+    // 生成 state 和 PKCE verifier，返回跳转到认证服务登录页的地址
+    pub fn start_login(&self) -> Result<(PendingLogin, String), AppError> {
+        let pending = PendingLogin {
+            state: random_string(),
+            code_verifier: random_string(),
+        };
+        let challenge = BASE64_URL.encode(Sha256::digest(pending.code_verifier.as_bytes()));
+        let mut url = Url::parse(&self.authorize_url).map_err(|e| {
+            error!("invalid auth.authorize_url: {:?}", e);
+            AppError::InternalError
+        })?;
+        url.query_pairs_mut()
+            .append_pair("client_id", &self.client_id)
+            .append_pair("redirect_uri", &self.redirect_uri)
+            .append_pair("state", &pending.state)
+            .append_pair("code_challenge", &challenge)
+            .append_pair("code_challenge_method", "S256");
+        Ok((pending, url.to_string()))
+    }
+
+    // 服务端使用授权码换取 access token
+    pub async fn exchange_code(&self, code: &str, code_verifier: &str) -> Result<String, AppError> {
+        let mut form = vec![
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", &self.redirect_uri),
+            ("client_id", &self.client_id),
+            ("code_verifier", code_verifier),
+        ];
+        if let Some(secret) = &self.client_secret {
+            form.push(("client_secret", secret));
+        }
+        let res = self
+            .client
+            .post(&self.token_url)
+            .form(&form)
+            .send()
+            .await
+            .map_err(|e| {
+                error!("exchange authorization code failed: {:?}", e);
+                AppError::InternalError
+            })?;
+        if !res.status().is_success() {
+            debug!("authorization code rejected: {}", res.status());
+            return Err(AppError::TokenInvalid);
+        }
+        let token = res.json::<TokenResponse>().await.map_err(|e| {
+            error!("parse token response failed: {:?}", e);
+            AppError::InternalError
+        })?;
+        Ok(token.access_token)
+    }
+}
@@ -0,0 +1,19 @@
+use crate::dbs::article_db::publish_due_articles_db;
+use sqlx::MySqlPool;
+use std::time::Duration;
+use tracing::{error, info};
+
+// 定时发布：每隔 period 秒把到期的 scheduled 文章改为 published
+pub fn spawn_publisher(pool: MySqlPool, period: u64) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(period));
+        loop {
+            interval.tick().await;
+            match publish_due_articles_db(&pool).await {
+                Ok(0) => {}
+                Ok(count) => info!("published {} scheduled articles", count),
+                Err(e) => error!("publish scheduled articles failed: {:?}", e),
+            }
+        }
+    });
+}
@@ -0,0 +1,161 @@
+use crate::utils::push_escaped;
+
+// 搜索关键词和高亮摘要
+// 数据库使用 ngram 全文索引检索，这里只负责在结果中标出命中的位置
+
+// 查询按空白拆分为关键词，统一小写
+pub fn search_terms(q: &str) -> Vec<String> {
+    let mut terms: Vec<String> = Vec::new();
+    for term in q.split_whitespace().map(|t| t.to_lowercase()) {
+        if !terms.contains(&term) {
+            terms.push(term);
+        }
+    }
+    terms
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c as u32, 0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xF900..=0xFAFF | 0x20000..=0x2FA1F)
+}
+
+// 关键词在 chars 中出现的位置，[start, end)
+fn find_all(chars: &[char], term: &[char], ranges: &mut Vec<(usize, usize)>) {
+    if term.is_empty() || term.len() > chars.len() {
+        return;
+    }
+    for start in 0..=chars.len() - term.len() {
+        if chars[start..start + term.len()] == *term {
+            ranges.push((start, start + term.len()));
+        }
+    }
+}
+
+fn match_ranges(chars: &[char], terms: &[String]) -> Vec<(usize, usize)> {
+    let lower: Vec<char> = chars
+        .iter()
+        .map(|c| c.to_lowercase().next().unwrap_or(*c))
+        .collect();
+    let mut ranges = Vec::new();
+    for term in terms {
+        let term: Vec<char> = term.chars().collect();
+        let before = ranges.len();
+        find_all(&lower, &term, &mut ranges);
+        // 中文词整体没有命中时按两个字一组匹配，与 ngram 分词一致
+        if ranges.len() == before && term.len() > 2 && term.iter().all(|c| is_cjk(*c)) {
+            for pair in term.windows(2) {
+                find_all(&lower, pair, &mut ranges);
+            }
+        }
+    }
+    ranges.sort();
+    let mut merged: Vec<(usize, usize)> = Vec::new();
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+// 截取第一个命中位置附近最多 max_chars 个字符，转义后用 <mark> 标出关键词
+pub fn highlight(text: &str, terms: &[String], max_chars: usize) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let ranges = match_ranges(&chars, terms);
+    let start = match ranges.first() {
+        Some((first, _)) if chars.len() > max_chars => {
+            first.saturating_sub(max_chars / 4).min(chars.len() - max_chars)
+        }
+        _ => 0,
+    };
+    let end = (start + max_chars).min(chars.len());
+
+    let mut out = String::new();
+    if start > 0 {
+        out.push('…');
+    }
+    let mut pos = start;
+    for (range_start, range_end) in ranges {
+        let range_start = range_start.max(pos);
+        let range_end = range_end.min(end);
+        if range_start >= range_end {
+            continue;
+        }
+        push_escaped(&mut out, &chars[pos..range_start].iter().collect::<String>());
+        out.push_str("<mark>");
+        push_escaped(&mut out, &chars[range_start..range_end].iter().collect::<String>());
+        out.push_str("</mark>");
+        pos = range_end;
+    }
+    push_escaped(&mut out, &chars[pos..end].iter().collect::<String>());
+    if end < chars.len() {
+        out.push('…');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn search_terms_lowercase_and_dedup() {
+        assert_eq!(search_terms("  Rust  rust\tAXUM "), vec!["rust", "axum"]);
+        assert_eq!(search_terms("中文 搜索 中文"), vec!["中文", "搜索"]);
+        assert!(search_terms(" \t ").is_empty());
+    }
+
+    #[test]
+    fn highlight_marks_terms_case_insensitively() {
+        let terms = search_terms("rust");
+        assert_eq!(
+            highlight("Learn Rust and rust", &terms, 100),
+            "Learn <mark>Rust</mark> and <mark>rust</mark>"
+        );
+    }
+
+    #[test]
+    fn highlight_escapes_html() {
+        let terms = search_terms("b");
+        assert_eq!(
+            highlight("<a href=\"x\">b</a> & 'c'", &terms, 100),
+            "&lt;a href=&quot;x&quot;&gt;<mark>b</mark>&lt;/a&gt; &amp; &#39;c&#39;"
+        );
+    }
+
+    #[test]
+    fn highlight_merges_overlapping_terms() {
+        let terms = search_terms("abc bcd");
+        assert_eq!(highlight("xabcdx", &terms, 100), "x<mark>abcd</mark>x");
+    }
+
+    #[test]
+    fn highlight_cjk() {
+        let terms = search_terms("搜索");
+        assert_eq!(
+            highlight("全文搜索引擎", &terms, 100),
+            "全文<mark>搜索</mark>引擎"
+        );
+        // 整词没有命中时按两个字一组匹配
+        let terms = search_terms("中文搜索");
+        assert_eq!(
+            highlight("支持中文的全文搜索", &terms, 100),
+            "支持<mark>中文</mark>的全<mark>文搜索</mark>"
+        );
+    }
+
+    #[test]
+    fn highlight_truncates_around_first_match() {
+        let text = format!("{}关键词{}", "前".repeat(50), "后".repeat(50));
+        let terms = search_terms("关键词");
+        let out = highlight(&text, &terms, 20);
+        assert!(out.starts_with('…'));
+        assert!(out.ends_with('…'));
+        assert!(out.contains("<mark>关键词</mark>"));
+        let visible: String = out.replace("<mark>", "").replace("</mark>", "");
+        assert_eq!(visible.chars().count(), 22);
+        // 没有命中时从头截取
+        let out = highlight(&text, &search_terms("none"), 20);
+        assert_eq!(out, format!("{}…", "前".repeat(20)));
+    }
+}
@@ -0,0 +1,130 @@
+use crate::dbs::session_db::*;
+use crate::dbs::user_db::get_session_user_by_detail_id_db;
+use crate::error::AppError;
+use crate::models::state::AppState;
+use crate::models::user::UserSession;
+use axum::http::HeaderMap;
+use rand::RngCore;
+use std::net::SocketAddr;
+use tower_sessions::Session;
+use tracing::{debug, error};
+
+// session 中保存登记表中的 id
+pub const SESSION_ID_KEY: &str = "session_id";
+// 最近几分钟内有请求的算作在线
+pub const ONLINE_MINUTES: i64 = 5;
+
+fn new_session_id() -> String {
+    let mut bytes = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// 客户端 IP，server.trust_proxy 开启时使用反向代理追加的 X-Forwarded-For 最后一项
+pub fn client_ip(trust_proxy: bool, headers: &HeaderMap, addr: SocketAddr) -> String {
+    if trust_proxy {
+        let forwarded = headers
+            .get("x-forwarded-for")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.rsplit(',').next())
+            .map(|v| v.trim())
+            .filter(|v| !v.is_empty());
+        if let Some(ip) = forwarded {
+            return ip.to_string();
+        }
+    }
+    addr.ip().to_string()
+}
+
+// 登录成功后登记 session，记录设备、IP 和认证服务的登录会话
+// 已登录的 session 再次登录（例如重新获取 token）时沿用原来的登记
+pub async fn register_session(
+    app_state: &AppState,
+    session: &Session,
+    user: &UserSession,
+    auth_sid: Option<&str>,
+    headers: &HeaderMap,
+    addr: SocketAddr,
+) -> Result<(), AppError> {
+    // 登录后更换 session id，防止 session 固定攻击
+    session.cycle_id().await.map_err(|e| {
+        error!("session cycle id error: {:?}", e);
+        AppError::InternalError
+    })?;
+    let user_agent = headers
+        .get("User-Agent")
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.chars().take(255).collect::<String>());
+    // 只沿用同一用户仍然有效的登记，已撤销的登记不能恢复
+    let id = match current_session_id(session).await? {
+        Some(id)
+            if get_active_session_user_db(&app_state.pool, &id).await?
+                == Some(user.user_detail_id) =>
+        {
+            id
+        }
+        _ => new_session_id(),
+    };
+    upsert_session_db(
+        &app_state.pool,
+        &id,
+        user.user_detail_id,
+        user_agent.as_deref(),
+        &client_ip(app_state.config.server.trust_proxy, headers, addr),
+        auth_sid,
+        app_state.config.session.timeout_hours,
+    )
+    .await?;
+    session.insert(SESSION_ID_KEY, &id).await.map_err(|e| {
+        error!("session insert error: {:?}", e);
+        AppError::InternalError
+    })?;
+    Ok(())
+}
+
+pub async fn current_session_id(session: &Session) -> Result<Option<String>, AppError> {
+    session.get::<String>(SESSION_ID_KEY).await.map_err(|e| {
+        error!("session get error: {:?}", e);
+        AppError::InternalError
+    })
+}
+
+// session 对应的当前用户，已撤销或过期的 session 直接清除
+pub async fn session_user(app_state: &AppState, session: &Session) -> Result<UserSession, AppError> {
+    let id = current_session_id(session)
+        .await?
+        .ok_or(AppError::UserUnLogin)?;
+    match get_active_session_user_db(&app_state.pool, &id).await? {
+        Some(user_detail_id) => {
+            let timeout = app_state.config.session.timeout_hours;
+            if let Err(e) = touch_session_db(&app_state.pool, &id, timeout).await {
+                error!("update session last seen failed: {:?}", e);
+            }
+            get_session_user_by_detail_id_db(&app_state.pool, user_detail_id).await
+        }
+        None => {
+            debug!("session {} is revoked or expired", id);
+            session.flush().await.map_err(|e| {
+                error!("session flush error: {:?}", e);
+                AppError::InternalError
+            })?;
+            Err(AppError::UserUnLogin)
+        }
+    }
+}
+
+// 退出登录：撤销登记并清除 session
+pub async fn end_session(
+    app_state: &AppState,
+    session: &Session,
+    user: &UserSession,
+) -> Result<(), AppError> {
+    if let Some(id) = current_session_id(session).await? {
+        revoke_session_db(&app_state.pool, user.user_detail_id, &id).await?;
+    }
+    session.flush().await.map_err(|e| {
+        error!("session flush error: {:?}", e);
+        AppError::InternalError
+    })?;
+    Ok(())
+}
@@ -0,0 +1,190 @@
+use crate::config::SessionConfig;
+use crate::dbs::session_db::delete_stale_sessions_db;
+use async_trait::async_trait;
+use sqlx::MySqlPool;
+use std::time::Duration;
+use tower_sessions::session::{Id, Record};
+use tower_sessions::session_store::{self, ExpiredDeletion, SessionStore};
+use tower_sessions::MemoryStore;
+use tracing::{debug, error, info};
+
+// 已过期或已撤销的登录登记保留的天数
+const STALE_SESSION_DAYS: i64 = 7;
+
+fn backend_error(e: sqlx::Error) -> session_store::Error {
+    error!("session store error: {:?}", e);
+    session_store::Error::Backend(e.to_string())
+}
+
+// session 保存在 tower_sessions_table 中，过期时间为 unix 秒
+#[derive(Debug, Clone)]
+pub struct MySqlSessionStore {
+    pool: MySqlPool,
+}
+
+impl MySqlSessionStore {
+    pub fn new(pool: MySqlPool) -> MySqlSessionStore {
+        MySqlSessionStore { pool }
+    }
+}
+
+#[async_trait]
+impl SessionStore for MySqlSessionStore {
+    async fn create(&self, record: &mut Record) -> session_store::Result<()> {
+        let data =
+            serde_json::to_string(&record).map_err(|e| session_store::Error::Encode(e.to_string()))?;
+        // id 冲突时重新生成，不覆盖其他用户的 session
+        loop {
+            let result = sqlx::query(
+                r#"INSERT IGNORE INTO tower_sessions_table (id, data, expiry_date) VALUES (?, ?, ?)"#,
+            )
+            .bind(record.id.to_string())
+            .bind(&data)
+            .bind(record.expiry_date.unix_timestamp())
+            .execute(&self.pool)
+            .await
+            .map_err(backend_error)?;
+            if result.rows_affected() == 1 {
+                return Ok(());
+            }
+            debug!("session id collision, regenerating");
+            record.id = Id::default();
+        }
+    }
+
+    async fn save(&self, record: &Record) -> session_store::Result<()> {
+        let data =
+            serde_json::to_string(record).map_err(|e| session_store::Error::Encode(e.to_string()))?;
+        sqlx::query(
+            r#"
+            INSERT INTO tower_sessions_table (id, data, expiry_date) VALUES (?, ?, ?)
+            ON DUPLICATE KEY UPDATE data = VALUES(data), expiry_date = VALUES(expiry_date)
+            "#,
+        )
+        .bind(record.id.to_string())
+        .bind(data)
+        .bind(record.expiry_date.unix_timestamp())
+        .execute(&self.pool)
+        .await
+        .map_err(backend_error)?;
+        Ok(())
+    }
+
+    async fn load(&self, session_id: &Id) -> session_store::Result<Option<Record>> {
+        let data = sqlx::query_scalar::<_, String>(
+            r#"SELECT data FROM tower_sessions_table WHERE id = ? AND expiry_date > UNIX_TIMESTAMP()"#,
+        )
+        .bind(session_id.to_string())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(backend_error)?;
+        match data {
+            Some(data) => serde_json::from_str(&data)
+                .map(Some)
+                .map_err(|e| session_store::Error::Decode(e.to_string())),
+            None => Ok(None),
+        }
+    }
+
+    async fn delete(&self, session_id: &Id) -> session_store::Result<()> {
+        sqlx::query(r#"DELETE FROM tower_sessions_table WHERE id = ?"#)
+            .bind(session_id.to_string())
+            .execute(&self.pool)
+            .await
+            .map_err(backend_error)?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ExpiredDeletion for MySqlSessionStore {
+    async fn delete_expired(&self) -> session_store::Result<()> {
+        let result =
+            sqlx::query(r#"DELETE FROM tower_sessions_table WHERE expiry_date <= UNIX_TIMESTAMP()"#)
+                .execute(&self.pool)
+                .await
+                .map_err(backend_error)?;
+        debug!("{} expired sessions deleted", result.rows_affected());
+        Ok(())
+    }
+}
+
+// session.store = memory 时保存在内存中，默认保存在数据库
+// SessionManagerLayer 需要具体类型，用枚举包装两种实现
+#[derive(Debug, Clone)]
+pub enum AppSessionStore {
+    Memory(MemoryStore),
+    MySql(MySqlSessionStore),
+}
+
+impl AppSessionStore {
+    pub fn from_config(config: &SessionConfig, pool: &MySqlPool) -> AppSessionStore {
+        match config.store.as_str() {
+            "memory" => {
+                info!("using memory session store");
+                AppSessionStore::Memory(MemoryStore::default())
+            }
+            _ => {
+                info!("using mysql session store");
+                AppSessionStore::MySql(MySqlSessionStore::new(pool.clone()))
+            }
+        }
+    }
+
+    // 定期删除过期的 session，内存存储在读取时判断过期，不需要清理
+    // 登录登记总是保存在数据库，同时删除过期和已撤销的登记
+    // 删除失败只记录日志，下一次继续
+    pub fn spawn_cleanup(&self, pool: MySqlPool, period: u64) {
+        let store = match self {
+            AppSessionStore::MySql(store) => Some(store.clone()),
+            AppSessionStore::Memory(_) => None,
+        };
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(period));
+            loop {
+                interval.tick().await;
+                if let Some(store) = &store {
+                    if let Err(e) = store.delete_expired().await {
+                        error!("delete expired sessions failed: {:?}", e);
+                    }
+                }
+                match delete_stale_sessions_db(&pool, STALE_SESSION_DAYS).await {
+                    Ok(0) => {}
+                    Ok(n) => debug!("{} stale session records deleted", n),
+                    Err(e) => error!("delete stale session records failed: {:?}", e),
+                }
+            }
+        });
+    }
+}
+
+#[async_trait]
+impl SessionStore for AppSessionStore {
+    async fn create(&self, record: &mut Record) -> session_store::Result<()> {
+        match self {
+            AppSessionStore::Memory(store) => store.create(record).await,
+            AppSessionStore::MySql(store) => store.create(record).await,
+        }
+    }
+
+    async fn save(&self, record: &Record) -> session_store::Result<()> {
+        match self {
+            AppSessionStore::Memory(store) => store.save(record).await,
+            AppSessionStore::MySql(store) => store.save(record).await,
+        }
+    }
+
+    async fn load(&self, session_id: &Id) -> session_store::Result<Option<Record>> {
+        match self {
+            AppSessionStore::Memory(store) => store.load(session_id).await,
+            AppSessionStore::MySql(store) => store.load(session_id).await,
+        }
+    }
+
+    async fn delete(&self, session_id: &Id) -> session_store::Result<()> {
+        match self {
+            AppSessionStore::Memory(store) => store.delete(session_id).await,
+            AppSessionStore::MySql(store) => store.delete(session_id).await,
+        }
+    }
+}
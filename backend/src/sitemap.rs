@@ -0,0 +1,276 @@
+use crate::dbs::sitemap_db::get_sitemap_articles_db;
+use crate::error::AppError;
+use crate::feed::article_url;
+use crate::models::sitemap::{SitemapArticle, SitemapUrl};
+use crate::utils::escape_markup;
+use chrono::{DateTime, SecondsFormat, Utc};
+use sqlx::MySqlPool;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+// 单个 sitemap 文件最多包含的地址数，超过后生成 sitemap 索引
+pub const MAX_SITEMAP_URLS: usize = 50_000;
+// 增量刷新可能漏掉其他实例删除的文章，定期全量重新加载
+const FULL_RELOAD: Duration = Duration::from_secs(3600);
+
+#[derive(Debug, Default)]
+struct SitemapState {
+    articles: HashMap<i64, SitemapArticle>,
+    // 已加载的文章中最新的 updated_at，增量刷新从这里开始
+    since: Option<DateTime<Utc>>,
+    // 本实例中修改过、等待刷新的文章
+    pending: HashSet<i64>,
+    refreshed_at: Option<Instant>,
+    loaded_at: Option<Instant>,
+}
+
+// 内存中的已发布文章列表，首次请求时全量加载，之后按修改时间增量刷新
+#[derive(Debug, Default)]
+pub struct SitemapCache {
+    state: Mutex<SitemapState>,
+}
+
+impl SitemapCache {
+    // 文章新建、修改、删除、状态或 slug 变化后调用，下次请求 sitemap 时刷新
+    pub fn article_changed(&self, article_id: i64) {
+        self.state.lock().unwrap().pending.insert(article_id);
+    }
+
+    pub async fn refresh(&self, pool: &MySqlPool, refresh_seconds: u64) -> Result<(), AppError> {
+        let (full, since, pending) = {
+            let mut state = self.state.lock().unwrap();
+            let full = state.loaded_at.is_none_or(|t| t.elapsed() >= FULL_RELOAD);
+            let due = state
+                .refreshed_at
+                .is_none_or(|t| t.elapsed() >= Duration::from_secs(refresh_seconds));
+            if !full && !due && state.pending.is_empty() {
+                return Ok(());
+            }
+            let pending: Vec<i64> = state.pending.drain().collect();
+            (full, state.since, pending)
+        };
+
+        let since = if full { None } else { since };
+        let articles = match get_sitemap_articles_db(pool, since, &pending).await {
+            Ok(articles) => articles,
+            Err(e) => {
+                // 刷新失败时保留待刷新的文章
+                self.state.lock().unwrap().pending.extend(pending);
+                return Err(e);
+            }
+        };
+
+        let mut state = self.state.lock().unwrap();
+        if since.is_none() {
+            state.articles.clear();
+            state.loaded_at = Some(Instant::now());
+        }
+        // 查不到的待刷新文章已被删除
+        for id in &pending {
+            state.articles.remove(id);
+        }
+        debug!("sitemap refresh: full = {}, {} articles changed", since.is_none(), articles.len());
+        for article in articles {
+            state.since = state.since.max(Some(article.updated_at));
+            if article.published {
+                state.articles.insert(article.id, article);
+            } else {
+                state.articles.remove(&article.id);
+            }
+        }
+        state.refreshed_at = Some(Instant::now());
+        Ok(())
+    }
+
+    // 首页、文章、作者、标签、目录页面，作者、标签、目录的 lastmod 为其中最新文章的 updated_at
+    pub fn urls(&self, site: &str) -> Vec<SitemapUrl> {
+        let state = self.state.lock().unwrap();
+        let site = site.trim_end_matches('/');
+        let mut users = BTreeMap::new();
+        let mut tags = BTreeMap::new();
+        let mut catalogues = BTreeMap::new();
+        let mut latest = DateTime::UNIX_EPOCH;
+        let mut articles: Vec<&SitemapArticle> = state.articles.values().collect();
+        articles.sort_by_key(|a| a.id);
+
+        let mut urls = Vec::with_capacity(articles.len() + 1);
+        urls.push(SitemapUrl {
+            loc: format!("{}/", site),
+            lastmod: latest,
+        });
+        for article in articles {
+            latest = latest.max(article.updated_at);
+            urls.push(SitemapUrl {
+                loc: article_url(site, article.id, article.slug.as_deref()),
+                lastmod: article.updated_at,
+            });
+            let touch = |map: &mut BTreeMap<i64, DateTime<Utc>>, id: i64| {
+                let lastmod = map.entry(id).or_insert(article.updated_at);
+                *lastmod = (*lastmod).max(article.updated_at);
+            };
+            if let Some(user_id) = article.user_detail_id {
+                touch(&mut users, user_id);
+            }
+            for tag_id in &article.tag_ids {
+                touch(&mut tags, *tag_id);
+            }
+            for catalogue_id in &article.catalogue_ids {
+                touch(&mut catalogues, *catalogue_id);
+            }
+        }
+        urls[0].lastmod = latest;
+        for (path, map) in [("users", users), ("tags", tags), ("catalogues", catalogues)] {
+            urls.extend(map.into_iter().map(|(id, lastmod)| SitemapUrl {
+                loc: format!("{}/{}/{}", site, path, id),
+                lastmod,
+            }));
+        }
+        urls
+    }
+}
+
+fn rfc3339(time: &DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+pub fn render_urlset(urls: &[SitemapUrl]) -> String {
+    let mut xml = String::new();
+    xml.push_str(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    xml.push('\n');
+    xml.push_str(r#"<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">"#);
+    for url in urls {
+        let _ = write!(
+            xml,
+            "<url><loc>{}</loc><lastmod>{}</lastmod></url>",
+            escape_markup(&url.loc),
+            rfc3339(&url.lastmod)
+        );
+    }
+    xml.push_str("</urlset>\n");
+    xml
+}
+
+// sitemap 索引，每个 sitemap 的 lastmod 为其中最新的地址
+pub fn render_index(base: &str, urls: &[SitemapUrl]) -> String {
+    let mut xml = String::new();
+    xml.push_str(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    xml.push('\n');
+    xml.push_str(r#"<sitemapindex xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">"#);
+    for (i, chunk) in urls.chunks(MAX_SITEMAP_URLS).enumerate() {
+        let lastmod = chunk
+            .iter()
+            .map(|u| u.lastmod)
+            .max()
+            .unwrap_or(DateTime::UNIX_EPOCH);
+        let _ = write!(
+            xml,
+            "<sitemap><loc>{}</loc><lastmod>{}</lastmod></sitemap>",
+            escape_markup(&format!("{}/sitemaps/{}.xml", base.trim_end_matches('/'), i + 1)),
+            rfc3339(&lastmod)
+        );
+    }
+    xml.push_str("</sitemapindex>\n");
+    xml
+}
+
+// sitemap 索引和 robots.txt 中本服务的地址：配置的 public_url，其次是允许的 Host，最后是前端地址
+// 响应带有 Cache-Control: public，不在允许列表中的 Host 不能写进响应，避免缓存投毒
+pub fn public_base(
+    public_url: Option<&str>,
+    allowed_hosts: &[String],
+    host: Option<&str>,
+    frontend_url: &str,
+) -> String {
+    if let Some(url) = public_url {
+        return url.trim_end_matches('/').to_string();
+    }
+    match host.filter(|h| allowed_hosts.iter().any(|a| a.eq_ignore_ascii_case(h))) {
+        Some(host) => format!("http://{}", host.to_ascii_lowercase()),
+        None => frontend_url.trim_end_matches('/').to_string(),
+    }
+}
+
+// robots.txt：禁止抓取的路径，并指向 sitemap
+pub fn render_robots(base: &str, disallow: &[String]) -> String {
+    let mut text = String::from("User-agent: *\n");
+    if disallow.is_empty() {
+        text.push_str("Disallow:\n");
+    }
+    for path in disallow {
+        let _ = writeln!(text, "Disallow: {}", path);
+    }
+    let _ = writeln!(text, "\nSitemap: {}/sitemap.xml", base.trim_end_matches('/'));
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn url(loc: &str, day: u32) -> SitemapUrl {
+        SitemapUrl {
+            loc: loc.to_string(),
+            lastmod: Utc.with_ymd_and_hms(2024, 5, day, 8, 0, 0).unwrap(),
+        }
+    }
+
+    #[test]
+    fn urlset_escapes_locations() {
+        let xml = render_urlset(&[url("https://example.com/", 2), url("https://example.com/posts/a&b", 1)]);
+        assert!(xml.starts_with(r#"<?xml version="1.0" encoding="UTF-8"?>"#));
+        assert!(xml.contains(
+            "<url><loc>https://example.com/</loc><lastmod>2024-05-02T08:00:00Z</lastmod></url>"
+        ));
+        assert!(xml.contains("<loc>https://example.com/posts/a&amp;b</loc>"));
+        assert!(xml.ends_with("</urlset>\n"));
+    }
+
+    #[test]
+    fn index_uses_latest_lastmod_per_chunk() {
+        let mut urls: Vec<SitemapUrl> = (0..MAX_SITEMAP_URLS).map(|_| url("https://example.com/", 1)).collect();
+        urls[10] = url("https://example.com/", 3);
+        urls.push(url("https://example.com/", 2));
+        let xml = render_index("https://api.example.com/", &urls);
+        assert!(xml.contains(
+            "<sitemap><loc>https://api.example.com/sitemaps/1.xml</loc><lastmod>2024-05-03T08:00:00Z</lastmod></sitemap>"
+        ));
+        assert!(xml.contains(
+            "<sitemap><loc>https://api.example.com/sitemaps/2.xml</loc><lastmod>2024-05-02T08:00:00Z</lastmod></sitemap>"
+        ));
+        assert!(!xml.contains("sitemaps/3.xml"));
+    }
+
+    #[test]
+    fn robots_lists_disallowed_paths() {
+        assert_eq!(
+            render_robots("https://example.com/", &[]),
+            "User-agent: *\nDisallow:\n\nSitemap: https://example.com/sitemap.xml\n"
+        );
+        let disallow = vec!["/posts/new".to_string(), "/tags/new".to_string()];
+        assert_eq!(
+            render_robots("https://example.com", &disallow),
+            "User-agent: *\nDisallow: /posts/new\nDisallow: /tags/new\n\nSitemap: https://example.com/sitemap.xml\n"
+        );
+    }
+
+    #[test]
+    fn public_base_ignores_unknown_hosts() {
+        let allowed = vec!["blog.example.com".to_string()];
+        let frontend = "https://www.example.com/";
+        assert_eq!(
+            public_base(Some("https://api.example.com/"), &allowed, Some("blog.example.com"), frontend),
+            "https://api.example.com"
+        );
+        assert_eq!(
+            public_base(None, &allowed, Some("Blog.Example.com"), frontend),
+            "http://blog.example.com"
+        );
+        assert_eq!(public_base(None, &allowed, Some("evil.com"), frontend), "https://www.example.com");
+        assert_eq!(public_base(None, &[], Some("blog.example.com"), frontend), "https://www.example.com");
+        assert_eq!(public_base(None, &allowed, None, frontend), "https://www.example.com");
+    }
+}
@@ -0,0 +1,117 @@
+use pinyin::ToPinyin;
+
+// slug 最长字符数
+pub const MAX_SLUG_LEN: usize = 80;
+
+fn push_separator(slug: &mut String) {
+    if !slug.is_empty() && !slug.ends_with('-') {
+        slug.push('-');
+    }
+}
+
+// 标题生成 slug：英文和数字转小写，汉字转为不带声调的拼音，其余字符作为分隔符
+// 例如 "Rust 异步编程" -> "rust-yi-bu-bian-cheng"
+pub fn slugify(title: &str) -> String {
+    let mut slug = String::new();
+    for c in title.chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if let Some(pinyin) = c.to_pinyin() {
+            push_separator(&mut slug);
+            // 拼音中的 ü 按输入法习惯写作 v，例如 "绿" -> "lv"
+            for p in pinyin.plain().chars() {
+                match p {
+                    'ü' => slug.push('v'),
+                    p if p.is_ascii_alphanumeric() => slug.push(p.to_ascii_lowercase()),
+                    _ => {}
+                }
+            }
+            push_separator(&mut slug);
+        } else {
+            push_separator(&mut slug);
+        }
+    }
+    // 超长时在分隔符处截断，slug 中只有 ASCII 字符，按字节截断不会切开字符
+    if slug.len() > MAX_SLUG_LEN {
+        let cut = match slug.as_bytes()[MAX_SLUG_LEN] {
+            b'-' => MAX_SLUG_LEN,
+            _ => slug[..MAX_SLUG_LEN].rfind('-').unwrap_or(MAX_SLUG_LEN),
+        };
+        slug.truncate(cut);
+    }
+    let slug = slug.trim_matches('-');
+    if slug.is_empty() {
+        "article".to_string()
+    } else if slug.bytes().all(|b| b.is_ascii_digit()) {
+        // 纯数字容易与文章 id 混淆
+        format!("article-{}", slug)
+    } else {
+        slug.to_string()
+    }
+}
+
+// 手动设置的 slug 只允许小写字母、数字和 -，不能以 - 开头或结尾，也不能是纯数字
+pub fn is_valid_slug(slug: &str) -> bool {
+    !slug.is_empty()
+        && slug.len() <= MAX_SLUG_LEN
+        && slug
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-')
+        && !slug.starts_with('-')
+        && !slug.ends_with('-')
+        && !slug.contains("--")
+        && !slug.bytes().all(|b| b.is_ascii_digit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slugify_ascii_and_cjk() {
+        assert_eq!(slugify("Hello, World!"), "hello-world");
+        assert_eq!(slugify("Rust 异步编程"), "rust-yi-bu-bian-cheng");
+        assert_eq!(slugify("  --Axum 0.7--  "), "axum-0-7");
+    }
+
+    #[test]
+    fn slugify_maps_u_umlaut() {
+        assert_eq!(slugify("绿色"), "lv-se");
+        assert_eq!(slugify("女"), "nv");
+        assert!(is_valid_slug(&slugify("旅行日记")));
+    }
+
+    #[test]
+    fn slugify_fallbacks() {
+        assert_eq!(slugify(""), "article");
+        assert_eq!(slugify("!!! ???"), "article");
+        assert_eq!(slugify("2024"), "article-2024");
+        // 没有拼音的字符作为分隔符
+        assert_eq!(slugify("café ☕ time"), "caf-time");
+    }
+
+    #[test]
+    fn slugify_length_limit() {
+        let slug = slugify(&"汉字".repeat(100));
+        assert!(slug.len() <= MAX_SLUG_LEN);
+        assert!(!slug.ends_with('-'));
+        assert!(is_valid_slug(&slug));
+        // 没有分隔符时直接截断
+        let slug = slugify(&"a".repeat(200));
+        assert_eq!(slug, "a".repeat(MAX_SLUG_LEN));
+        // 截断位置正好是分隔符
+        let title = format!("{} tail", "b".repeat(MAX_SLUG_LEN));
+        assert_eq!(slugify(&title), "b".repeat(MAX_SLUG_LEN));
+    }
+
+    #[test]
+    fn valid_slugs() {
+        assert!(is_valid_slug("rust-async"));
+        assert!(is_valid_slug("article-2024"));
+        assert!(is_valid_slug(&"a".repeat(MAX_SLUG_LEN)));
+        for slug in ["", "-rust", "rust-", "rust--async", "Rust", "2024", "lü", "中文", "a_b"] {
+            assert!(!is_valid_slug(slug), "{}", slug);
+        }
+        assert!(!is_valid_slug(&"a".repeat(MAX_SLUG_LEN + 1)));
+    }
+}
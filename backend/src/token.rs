@@ -0,0 +1,288 @@
+use crate::config::AuthConfig;
+use crate::dbs::session_db::revoke_auth_sessions_db;
+use crate::error::AppError;
+use crate::models::state::AppState;
+use crate::models::user::User;
+use crate::utils::get_auth;
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use reqwest::Client;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+use tracing::{debug, error, info, warn};
+
+// 公钥缓存有效期
+const JWKS_TTL: Duration = Duration::from_secs(600);
+// 遇到未知 kid 时最短的重新拉取间隔
+const JWKS_MIN_REFRESH: Duration = Duration::from_secs(30);
+// 已撤销的登录会话保留的时间，超过 access token 的有效期即可
+const REVOKED_KEEP: Duration = Duration::from_secs(24 * 3600);
+
+// 认证服务签发的 JWT 载荷
+#[derive(Debug, Deserialize)]
+pub struct Claims {
+    pub sub: String,
+    pub email: String,
+    pub preferred_username: String,
+    #[serde(default)]
+    pub sid: Option<String>,
+}
+
+#[derive(Debug)]
+struct CachedKeys {
+    keys: JwkSet,
+    fetched_at: Instant,
+}
+
+#[derive(Debug, Deserialize)]
+struct RevocationList {
+    revoked: Vec<String>,
+    now: i64,
+}
+
+#[derive(Debug, Default)]
+struct Revoked {
+    // 已撤销的登录会话 id 和同步到的时间
+    sids: HashMap<String, Instant>,
+    // 认证服务返回的时间，下次从这里开始拉取
+    since: Option<i64>,
+}
+
+// 使用认证服务发布的公钥在本地验证 token
+// 只有开启了 auth.introspection 时才在本地验证不可用时回退到 auth.token_url
+// 配置了 auth.revocation_url 时拒绝认证服务上已撤销（退出登录）的 token
+#[derive(Debug)]
+pub struct TokenVerifier {
+    client: Client,
+    jwks_url: Option<String>,
+    introspection_url: Option<String>,
+    audience: String,
+    issuer: Option<String>,
+    cache: RwLock<Option<CachedKeys>>,
+    revocation_url: Option<String>,
+    client_id: String,
+    client_secret: Option<String>,
+    revoked: Mutex<Revoked>,
+}
+
+impl TokenVerifier {
+    pub fn from_config(config: &AuthConfig) -> TokenVerifier {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(5))
+            .build()
+            .unwrap_or_default();
+        TokenVerifier {
+            client,
+            jwks_url: config.jwks_url.clone(),
+            introspection_url: if config.introspection {
+                config.token_url.clone()
+            } else {
+                None
+            },
+            audience: config.audience.clone(),
+            issuer: config.issuer.clone(),
+            cache: RwLock::new(None),
+            revocation_url: config.revocation_url.clone(),
+            client_id: config.client_id.clone(),
+            client_secret: config.client_secret.clone(),
+            revoked: Mutex::new(Revoked::default()),
+        }
+    }
+
+    pub async fn verify(&self, token: &str) -> Result<User, AppError> {
+        let user = self.verify_token(token).await?;
+        if user.sid.as_deref().is_some_and(|sid| self.is_revoked(sid)) {
+            debug!("token of revoked login {:?}", user.sid);
+            return Err(AppError::TokenInvalid);
+        }
+        Ok(user)
+    }
+
+    fn is_revoked(&self, sid: &str) -> bool {
+        self.revoked.lock().unwrap().sids.contains_key(sid)
+    }
+
+    // 拉取上次之后撤销的登录会话，返回新撤销的 sid
+    pub async fn poll_revocations(&self) -> Result<Vec<String>, AppError> {
+        let Some(url) = self.revocation_url.as_deref() else {
+            return Ok(Vec::new());
+        };
+        let since = self
+            .revoked
+            .lock()
+            .unwrap()
+            .since
+            .unwrap_or_else(|| chrono::Utc::now().timestamp() - REVOKED_KEEP.as_secs() as i64);
+        let form = [
+            ("client_id", self.client_id.clone()),
+            ("client_secret", self.client_secret.clone().unwrap_or_default()),
+            ("since", since.to_string()),
+        ];
+        let res = self.client.post(url).form(&form).send().await.map_err(|e| {
+            error!("request revocations failed: {:?}", e);
+            AppError::InternalError
+        })?;
+        if !res.status().is_success() {
+            error!("request revocations failed: {}", res.status());
+            return Err(AppError::InternalError);
+        }
+        let list = res.json::<RevocationList>().await.map_err(|e| {
+            error!("parse revocations failed: {:?}", e);
+            AppError::InternalError
+        })?;
+
+        let mut revoked = self.revoked.lock().unwrap();
+        revoked.since = Some(list.now);
+        revoked.sids.retain(|_, at| at.elapsed() < REVOKED_KEEP);
+        let now = Instant::now();
+        let new_sids = list
+            .revoked
+            .into_iter()
+            .filter(|sid| revoked.sids.insert(sid.clone(), now).is_none())
+            .collect();
+        Ok(new_sids)
+    }
+
+    async fn verify_token(&self, token: &str) -> Result<User, AppError> {
+        if self.jwks_url.is_some() {
+            match self.verify_local(token).await {
+                Ok(user) => return Ok(user),
+                // token 本身无效时不回退
+                Err(AppError::TokenInvalid) => return Err(AppError::TokenInvalid),
+                Err(AppError::TokenExpired) => return Err(AppError::TokenExpired),
+                Err(e) => {
+                    if self.introspection_url.is_none() {
+                        return Err(e);
+                    }
+                    warn!("local token verification unavailable, fall back to introspection");
+                }
+            }
+        }
+        match &self.introspection_url {
+            Some(url) => get_auth(&self.client, token, url).await,
+            None => {
+                error!("neither auth.jwks_url nor auth.introspection is configured");
+                Err(AppError::InternalError)
+            }
+        }
+    }
+
+    async fn verify_local(&self, token: &str) -> Result<User, AppError> {
+        let header = decode_header(token).map_err(|e| {
+            debug!("decode token header failed: {:?}", e);
+            AppError::TokenInvalid
+        })?;
+        let kid = header.kid.ok_or(AppError::TokenInvalid)?;
+        let key = self.decoding_key(&kid).await?;
+
+        let mut validation = Validation::new(Algorithm::EdDSA);
+        validation.set_audience(&[&self.audience]);
+        if let Some(issuer) = &self.issuer {
+            validation.set_issuer(&[issuer]);
+        }
+        let data = decode::<Claims>(token, &key, &validation).map_err(|e| {
+            debug!("verify token failed: {:?}", e);
+            match e.kind() {
+                jsonwebtoken::errors::ErrorKind::ExpiredSignature => AppError::TokenExpired,
+                _ => AppError::TokenInvalid,
+            }
+        })?;
+        let id = data
+            .claims
+            .sub
+            .parse::<i64>()
+            .map_err(|_| AppError::TokenInvalid)?;
+        Ok(User {
+            id,
+            username: data.claims.preferred_username,
+            email: data.claims.email,
+            sid: data.claims.sid,
+        })
+    }
+
+    // 从缓存中查找 kid 对应的公钥，缓存过期或找不到时重新拉取
+    async fn decoding_key(&self, kid: &str) -> Result<DecodingKey, AppError> {
+        {
+            let cache = self.cache.read().await;
+            if let Some(cached) = cache.as_ref() {
+                let fresh = cached.fetched_at.elapsed() < JWKS_TTL;
+                match cached.keys.find(kid) {
+                    Some(jwk) if fresh => return jwk_to_key(jwk),
+                    None if cached.fetched_at.elapsed() < JWKS_MIN_REFRESH => {
+                        return Err(AppError::TokenInvalid)
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let mut cache = self.cache.write().await;
+        match self.fetch_keys().await {
+            Ok(keys) => {
+                *cache = Some(CachedKeys {
+                    keys,
+                    fetched_at: Instant::now(),
+                });
+            }
+            // 认证服务不可用时继续使用旧的公钥
+            Err(e) => {
+                if cache.is_none() {
+                    return Err(e);
+                }
+                warn!("refresh jwks failed, using cached keys");
+            }
+        }
+        match cache.as_ref().and_then(|cached| cached.keys.find(kid)) {
+            Some(jwk) => jwk_to_key(jwk),
+            None => Err(AppError::TokenInvalid),
+        }
+    }
+
+    async fn fetch_keys(&self) -> Result<JwkSet, AppError> {
+        let url = self.jwks_url.as_deref().ok_or(AppError::InternalError)?;
+        let res = self.client.get(url).send().await.map_err(|e| {
+            error!("fetch jwks failed: {:?}", e);
+            AppError::InternalError
+        })?;
+        res.json::<JwkSet>().await.map_err(|e| {
+            error!("parse jwks failed: {:?}", e);
+            AppError::InternalError
+        })
+    }
+}
+
+fn jwk_to_key(jwk: &jsonwebtoken::jwk::Jwk) -> Result<DecodingKey, AppError> {
+    DecodingKey::from_jwk(jwk).map_err(|e| {
+        error!("invalid jwk: {:?}", e);
+        AppError::InternalError
+    })
+}
+
+// 定期同步认证服务上撤销的登录，结束对应的本地 session
+pub fn spawn_revocation_sync(app_state: Arc<AppState>, period: u64) {
+    if app_state.config.auth.revocation_url.is_none() {
+        return;
+    }
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(period));
+        loop {
+            interval.tick().await;
+            let sids = match app_state.token_verifier.poll_revocations().await {
+                Ok(sids) if sids.is_empty() => continue,
+                Ok(sids) => sids,
+                Err(e) => {
+                    error!("sync revoked logins failed: {:?}", e);
+                    continue;
+                }
+            };
+            match revoke_auth_sessions_db(&app_state.pool, &sids).await {
+                Ok(0) => {}
+                Ok(count) => info!("ended {} sessions logged out on the auth service", count),
+                Err(e) => error!("revoke sessions failed: {:?}", e),
+            }
+        }
+    });
+}
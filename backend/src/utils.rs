@@ -4,14 +4,17 @@ use chrono::Datelike;
 use reqwest::Client;
 use reqwest::StatusCode;
 use tracing::{debug, error};
-pub async fn get_auth(token: &str, url: &str) -> Result<User, AppError> {
-    let client = Client::new();
+// 调用认证服务验证 token，仅在配置了 AUTH_INTROSPECTION 时使用
+pub async fn get_auth(client: &Client, token: &str, url: &str) -> Result<User, AppError> {
     let res = client
         .get(url)
         .header("Authorization", format!("Bearer {}", token))
         .send()
         .await
-        .unwrap();
+        .map_err(|e| {
+            error!("request auth service failed: {:?}", e);
+            AppError::InternalError
+        })?;
     debug!("get user info response: {:?}", res);
     match res.status() {
         StatusCode::OK => {
@@ -40,14 +43,52 @@ pub async fn get_auth(token: &str, url: &str) -> Result<User, AppError> {
 }
 // 返回年份_月份，如2021_08
 pub async fn get_now_date() -> String {
-    // let now = chrono::Utc::now();
-    // let year = now.year();
-    // let month = now.month();
-    // format!("{}_{}", year, month)
-    "2024_10".to_string()
+    let now = chrono::Utc::now();
+    format!("{}_{:02}", now.year(), now.month())
 }
 
 pub async fn get_table_name() -> String {
     let now_date = get_now_date().await;
     format!("articles_table_{}", now_date)
 }
+
+// 转义 HTML / XML 中的特殊字符，搜索摘要、订阅和 sitemap 共用
+// XML 1.0 不允许的控制字符直接丢弃
+pub fn push_escaped(out: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c if (c as u32) < 0x20 && !matches!(c, '\t' | '\n' | '\r') => {}
+            _ => out.push(c),
+        }
+    }
+}
+
+pub fn escape_markup(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    push_escaped(&mut out, text);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_markup_special_chars() {
+        assert_eq!(
+            escape_markup(r#"<a href="x">Tom & 'Jerry'</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; &#39;Jerry&#39;&lt;/a&gt;"
+        );
+        assert_eq!(escape_markup("中文"), "中文");
+    }
+
+    #[test]
+    fn escape_markup_drops_control_chars() {
+        assert_eq!(escape_markup("a\u{0}b\u{1b}c\td\ne\r"), "abc\td\ne\r");
+    }
+}
@@ -0,0 +1,52 @@
+// 集成测试共用的临时数据库
+// 测试时创建临时数据库并在结束后删除，没有设置 TEST_DATABASE_URL 时跳过
+use rand::RngCore;
+use sqlx::mysql::MySqlPoolOptions;
+use sqlx::{Executor, MySqlPool};
+
+pub struct TestDatabase {
+    server: MySqlPool,
+    name: String,
+    pub pool: MySqlPool,
+}
+
+pub async fn test_database() -> Option<TestDatabase> {
+    let Ok(url) = std::env::var("TEST_DATABASE_URL") else {
+        eprintln!("TEST_DATABASE_URL is not set, skipping");
+        return None;
+    };
+    let url = url.trim_end_matches('/').to_string();
+    let server = MySqlPool::connect(&url).await.unwrap();
+    let name = format!("blog_test_{:08x}", rand::thread_rng().next_u32());
+    server
+        .execute(format!("CREATE DATABASE {}", name).as_str())
+        .await
+        .unwrap();
+    // 与 MySQL 8 的默认值一致，列表查询不能依赖非聚合列
+    let pool = MySqlPoolOptions::new()
+        .after_connect(|conn, _| {
+            Box::pin(async move {
+                conn.execute("SET SESSION sql_mode = 'ONLY_FULL_GROUP_BY,STRICT_TRANS_TABLES'")
+                    .await?;
+                Ok(())
+            })
+        })
+        .connect(&format!("{}/{}", url, name))
+        .await
+        .unwrap();
+    Some(TestDatabase { server, name, pool })
+}
+
+impl TestDatabase {
+    pub async fn drop(self) {
+        self.pool.close().await;
+        self.server
+            .execute(format!("DROP DATABASE {}", self.name).as_str())
+            .await
+            .unwrap();
+    }
+}
+
+pub async fn exec(pool: &MySqlPool, sql: &str) {
+    pool.execute(sql).await.unwrap();
+}
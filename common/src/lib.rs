@@ -0,0 +1,3 @@
+// auth 和 backend 共用的代码
+pub mod config;
+pub mod migrate;
@@ -0,0 +1,37 @@
+use sqlx::migrate::{MigrateError, Migrator};
+use sqlx::MySqlPool;
+use tracing::{error, info};
+
+// 执行所有未应用的迁移，migrator 为各服务用 sqlx::migrate!() 内嵌的迁移，已执行的版本记录在 _sqlx_migrations 表中
+// 数据库中存在本程序不认识的更新版本时拒绝启动
+pub async fn run_migrations(migrator: &Migrator, pool: &MySqlPool) -> Result<(), MigrateError> {
+    migrator.run(pool).await.map_err(|e| {
+        match &e {
+            MigrateError::VersionMissing(version) => error!(
+                "database schema version {} is unknown to this build, refusing to start",
+                version
+            ),
+            _ => error!("run migrations failed: {:?}", e),
+        }
+        e
+    })?;
+    info!("database schema is up to date");
+    Ok(())
+}
+
+// 子命令为 migrate 时只执行迁移然后退出
+pub fn is_migrate_command(command: Option<&str>) -> bool {
+    command == Some("migrate")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn migrate_command() {
+        assert!(is_migrate_command(Some("migrate")));
+        assert!(!is_migrate_command(Some("serve")));
+        assert!(!is_migrate_command(None));
+    }
+}
@@ -2,7 +2,8 @@ export const apiDomain = 'http://localhost:8002/api';
 export const authDomain = 'http://localhost:8001';
 export const localDomain = 'http://localhost:3000';
 
-export const loginUrl = () => `${authDomain}/auth/login?redirect=${localDomain}`;
+// 由后端发起授权码登录，登录完成后跳转回 localDomain
+export const loginUrl = () => `${apiDomain}/auth/login`;
 export const RegisterUrl = () => `${authDomain}/auth/register`;
 // export const authTokenUrl = () => `${authDomain}/`;
 
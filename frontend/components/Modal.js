@@ -1,5 +1,5 @@
 import React from 'react';
-import { loginUrl,localDomain } from "@/api_list";
+import { loginUrl } from "@/api_list";
 
 const Modal = ({ isOpen,}) => {
     const onClose = () => {
@@ -8,7 +8,7 @@ const Modal = ({ isOpen,}) => {
     }
     const onLogin = () => {
         // Redirect to the login page
-        window.location.href = loginUrl()+`?redirect=${localDomain}`;
+        window.location.href = loginUrl();
     }
 
     if (!isOpen) return null;
@@ -3,7 +3,7 @@ import Head from 'next/head';
 import { useState, useEffect } from 'react';
 import ArticleList from '../components/ArticleList';
 import FeatureArticleList from '../components/FeatureArticleList';
-import { authTokenUrl, getLateArticlesUrl, getFeatureArticleUrl } from "@/api_list";
+import { getAuthUserSessionUrl, getLateArticlesUrl, getFeatureArticleUrl } from "@/api_list";
 import { loginUrl } from "@/api_list";
 
 export default function Home() {
@@ -17,13 +17,10 @@ export default function Home() {
 
     useEffect(() => {
         const urlParams = new URLSearchParams(window.location.search);
-        const token = urlParams.get('token');
-        if (token) {
-            fetch(authTokenUrl(), {
+        // 后端完成授权码登录后跳转回来，session 已经建立
+        if (urlParams.get('login') === 'success') {
+            fetch(getAuthUserSessionUrl(), {
                 method: 'GET',
-                headers: {
-                    'Authorization': `Bearer ${token}`
-                },
                 credentials: 'include'
             })
                 .then(response => {